@@ -0,0 +1,564 @@
+//! Where notes are kept.
+//!
+//! [`FilesystemBackend`] keeps each note in its own file, as quicknotes always has.
+//! [`SqliteBackend`] keeps a whole vault in a single `SQLite` database, with the preamble and body
+//! of each note stored separately. Notes are identified by their path in either case; for the
+//! `SQLite` backend, these are only names, and don't need to exist on disk.
+//!
+//! The `quicknotes` command always uses [`FilesystemBackend`]. Library users can keep a vault in
+//! any backend by making, reading, indexing and deleting notes with the `_in` functions, such as
+//! [`make_note_in`](crate::make_note_in). Notes are still written in the editor through a
+//! temporary file, and the index is kept beside the vault on disk. The rest of the library, such
+//! as attachments, dailies and the inbox, works with files directly.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OptionalExtension};
+use rusqlite_migration::{Migrations, M};
+use tempfile::Builder as TempFileBuilder;
+use thiserror::Error;
+use walkdir::WalkDir;
+
+use crate::index::NoteKind;
+use crate::storage::{Recovery, StoreNote, StoreNoteAt, StoreNoteOver, TempFileHandle};
+use crate::{note, storage, warning, NoteConfig, TEMPFILE_PREFIX};
+
+/// A place that notes can be stored in, read from, listed and deleted.
+pub trait Backend {
+    /// Read the note at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::NotFound`] if there is no note there, or any
+    /// other error if the note could not be read.
+    fn read(&self, path: &Path) -> io::Result<String>;
+
+    /// Store a new note at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::AlreadyExists`] if there is already a note there,
+    /// so that nothing is ever clobbered, or any other error if the note could not be stored.
+    fn create(&self, path: &Path, contents: &str) -> io::Result<()>;
+
+    /// Replace the contents of the note at the given path, storing it if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note could not be stored.
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()>;
+
+    /// List the paths of every note within the given directory, including those in
+    /// subdirectories, in no particular order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the notes could not be listed.
+    fn list(&self, directory: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Delete the note at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::NotFound`] if there is no note there, or any
+    /// other error if the note could not be deleted.
+    fn delete(&self, path: &Path) -> io::Result<()>;
+}
+
+/// Store a new note at the given path, or beside it with a numbered suffix if there is already a
+/// note there (e.g. `my-note-1.md`). Returns the path the note was stored at.
+///
+/// # Errors
+///
+/// Returns an error if the path has no filename, or the backend fails to list or store notes.
+pub fn create_unclobbered<B: Backend + ?Sized>(
+    backend: &B,
+    path: &Path,
+    contents: &str,
+) -> io::Result<PathBuf> {
+    let (Some(directory), Some(stem)) = (path.parent(), path.file_stem().and_then(|s| s.to_str()))
+    else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a valid path for a note", path.display()),
+        ));
+    };
+    let extension = path.extension().and_then(|extension| extension.to_str());
+
+    let mut destination = path.to_owned();
+    // As with storing a note from the editor, this loops in case another note takes the new name
+    // first.
+    loop {
+        match backend.create(&destination, contents) {
+            Ok(()) => return Ok(destination),
+
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                let existing_paths = backend.list(directory)?;
+                let file_names = existing_paths
+                    .iter()
+                    .filter(|existing_path| existing_path.parent() == Some(directory))
+                    .filter_map(|existing_path| existing_path.file_name()?.to_str());
+
+                destination = directory.join(storage::next_destination_basename(
+                    file_names, stem, extension,
+                ));
+            }
+
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Keeps each note in its own file on the local filesystem.
+///
+/// Notes are stored the same way as notes written in the editor: they are written to a temporary
+/// file first, and a note that cannot be stored is moved to the recovery directory (or, without
+/// one, left in the temporary file), so it is never lost or left half-written.
+#[derive(Clone, Debug, Default)]
+pub struct FilesystemBackend {
+    temp_dir: Option<PathBuf>,
+    recovery: Option<FilesystemRecovery>,
+}
+
+/// Where a [`FilesystemBackend`] moves notes that cannot be stored.
+#[derive(Clone, Debug)]
+struct FilesystemRecovery {
+    directory: PathBuf,
+    /// The directory that notes of each kind are stored in, so that the kind of a recovered note
+    /// can be recorded.
+    kind_directories: Vec<(NoteKind, PathBuf)>,
+}
+
+impl FilesystemBackend {
+    /// Store notes the way the given configuration does, using its temporary directory and moving
+    /// notes that cannot be stored to its recovery directory.
+    #[must_use]
+    pub fn new(config: &NoteConfig) -> Self {
+        let kind_directories = [
+            NoteKind::Note,
+            NoteKind::Daily,
+            NoteKind::Weekly,
+            NoteKind::Monthly,
+        ]
+        .map(|kind| (kind, config.kind_directory_path(kind)));
+
+        Self {
+            temp_dir: config.temp_root_override.clone(),
+            recovery: Some(FilesystemRecovery {
+                directory: config.recovery_directory_path(),
+                kind_directories: kind_directories.to_vec(),
+            }),
+        }
+    }
+
+    /// Write the contents of a note to be stored at `path` into a temporary file.
+    fn tempfile_for(&self, path: &Path, contents: &str) -> io::Result<TempFileHandle> {
+        let suffix = path
+            .extension()
+            .map(|extension| format!(".{}", extension.to_string_lossy()))
+            .unwrap_or_default();
+        let mut builder = TempFileBuilder::new();
+        builder.prefix(TEMPFILE_PREFIX).suffix(&suffix);
+        let mut tempfile = match &self.temp_dir {
+            Some(temp_dir) => builder.tempfile_in(temp_dir)?,
+            None => builder.tempfile()?,
+        };
+        tempfile.write_all(contents.as_bytes())?;
+        let temppath = tempfile.into_temp_path();
+
+        match &self.recovery {
+            Some(recovery) => {
+                let kind = recovery
+                    .kind_directories
+                    .iter()
+                    .find(|(_kind, directory)| path.starts_with(directory))
+                    .map_or(NoteKind::Note, |(kind, _directory)| *kind);
+
+                TempFileHandle::open(
+                    temppath,
+                    Recovery {
+                        directory: recovery.directory.clone(),
+                        kind,
+                    },
+                )
+            }
+
+            None => TempFileHandle::open_without_recovery(temppath),
+        }
+    }
+}
+
+impl Backend for FilesystemBackend {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn create(&self, path: &Path, contents: &str) -> io::Result<()> {
+        // Checked up front, as a note that can't be stored is preserved, and that shouldn't happen
+        // for every name that `create_unclobbered` tries. If another note takes the path in the
+        // meantime, it still isn't clobbered.
+        match fs::symlink_metadata(path) {
+            Ok(_metadata) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("a note already exists at {}", path.display()),
+                ))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        let store = StoreNoteAt {
+            destination: path.to_owned(),
+        };
+        store
+            .store(self.tempfile_for(path, contents)?)
+            .map_err(io::Error::other)?;
+
+        Ok(())
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        let store = StoreNoteOver {
+            destination: path.to_owned(),
+        };
+        store
+            .store(self.tempfile_for(path, contents)?)
+            .map_err(io::Error::other)?;
+
+        Ok(())
+    }
+
+    /// Unreadable entries are skipped, with a warning, so that the rest can still be listed.
+    fn list(&self, directory: &Path) -> io::Result<Vec<PathBuf>> {
+        let paths = WalkDir::new(directory)
+            .into_iter()
+            .filter_map(|entry_res| match entry_res {
+                Ok(entry) => (!entry.file_type().is_dir()).then(|| entry.into_path()),
+                Err(err) => {
+                    if let Some(path) = err.path() {
+                        warning!(
+                            "Cannot traverse {}: {}",
+                            path.display().to_string(),
+                            io::Error::from(err)
+                        );
+                    } else {
+                        warning!("Cannot traverse notes: {}", io::Error::from(err));
+                    }
+
+                    None
+                }
+            })
+            .collect();
+
+        Ok(paths)
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+}
+
+/// Keeps a whole vault of notes in a single `SQLite` database.
+pub struct SqliteBackend {
+    connection: Connection,
+}
+
+impl SqliteBackend {
+    /// Open the vault in the `SQLite` database at the given path, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or set up.
+    pub fn open(path: &Path) -> Result<Self, OpenSqliteBackendError> {
+        Self::setup(Connection::open(path)?)
+    }
+
+    /// Open an empty vault that is only kept in memory, such as for testing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be set up.
+    pub fn open_in_memory() -> Result<Self, OpenSqliteBackendError> {
+        Self::setup(Connection::open_in_memory()?)
+    }
+
+    fn setup(mut connection: Connection) -> Result<Self, OpenSqliteBackendError> {
+        migrations().to_latest(&mut connection)?;
+
+        Ok(Self { connection })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum OpenSqliteBackendError {
+    #[error("could not open vault: {0}")]
+    ConnectionOpenError(#[from] rusqlite::Error),
+
+    #[error("could not setup vault: {0}")]
+    MigrationError(#[from] rusqlite_migration::Error),
+}
+
+impl Backend for SqliteBackend {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.connection
+            .query_row(
+                "SELECT preamble, body FROM notes WHERE path = ?;",
+                (path_string(path)?,),
+                |row| Ok(row.get::<_, String>(0)? + &row.get::<_, String>(1)?),
+            )
+            .optional()
+            .map_err(io::Error::other)?
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn create(&self, path: &Path, contents: &str) -> io::Result<()> {
+        let (preamble, body) = split_preamble(contents);
+        let inserted = self
+            .connection
+            .execute(
+                "INSERT INTO notes VALUES (?1, ?2, ?3) ON CONFLICT(path) DO NOTHING;",
+                (path_string(path)?, preamble, body),
+            )
+            .map_err(io::Error::other)?;
+
+        if inserted == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("a note already exists at {}", path.display()),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        let (preamble, body) = split_preamble(contents);
+        self.connection
+            .execute(
+                "INSERT INTO notes VALUES (?1, ?2, ?3)
+                    ON CONFLICT(path) DO UPDATE SET preamble=?2, body=?3
+                ;",
+                (path_string(path)?, preamble, body),
+            )
+            .map_err(io::Error::other)?;
+
+        Ok(())
+    }
+
+    fn list(&self, directory: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut query = self
+            .connection
+            .prepare("SELECT path FROM notes;")
+            .map_err(io::Error::other)?;
+
+        // Paths are compared by component, so that e.g. `notes` does not contain `notes-old/a.md`
+        let paths = query
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(io::Error::other)?
+            .map(|path_res| path_res.map(PathBuf::from).map_err(io::Error::other))
+            .filter(|path_res| {
+                path_res.as_ref().map_or(true, |path| {
+                    path.starts_with(directory) && path != directory
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(paths)
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        let deleted = self
+            .connection
+            .execute("DELETE FROM notes WHERE path = ?;", (path_string(path)?,))
+            .map_err(io::Error::other)?;
+
+        if deleted == 0 {
+            return Err(not_found(path));
+        }
+
+        Ok(())
+    }
+}
+
+/// Split the contents of a note into the text of its preamble (including both fences) and
+/// everything after it. If the note has no valid preamble, it is all treated as the body, so that
+/// it is stored exactly as it was given.
+fn split_preamble(contents: &str) -> (&str, &str) {
+    match note::extract_preamble_and_body(contents.as_bytes()) {
+        Ok((_preamble, body)) => contents.split_at(contents.len() - body.len()),
+        Err(_err) => ("", contents),
+    }
+}
+
+fn path_string(path: &Path) -> io::Result<&str> {
+    path.to_str().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a valid UTF-8 path", path.display()),
+        )
+    })
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no note exists at {}", path.display()),
+    )
+}
+
+fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![M::up(
+        "CREATE TABLE notes (
+            path TEXT PRIMARY KEY,
+            preamble TEXT NOT NULL,
+            body TEXT NOT NULL
+        );",
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::{recovery, DailyConfig, DateDialect, InboxConfig};
+
+    const NOTE: &str = "---\ntitle = \"Flux Capacitor\"\ncreated_at = 2015-10-21T07:28:00-07:00\n---\n\nIt's what makes time travel possible.\n";
+
+    fn check_backend<B: Backend>(backend: &B, root: &Path) {
+        let notes_directory = root.join("notes");
+        let path = notes_directory.join("flux-capacitor.md");
+
+        backend.create(&path, NOTE).expect("could not create note");
+        assert_eq!(backend.read(&path).expect("could not read note"), NOTE);
+        assert_eq!(
+            backend
+                .create(&path, "clobbered")
+                .expect_err("notes must not be clobbered")
+                .kind(),
+            io::ErrorKind::AlreadyExists
+        );
+
+        let unclobbered_path =
+            create_unclobbered(backend, &path, "no preamble").expect("could not create note");
+        assert_eq!(
+            unclobbered_path,
+            notes_directory.join("flux-capacitor-1.md")
+        );
+        assert_eq!(
+            backend
+                .read(&unclobbered_path)
+                .expect("could not read note"),
+            "no preamble"
+        );
+
+        backend
+            .write(&path, "rewritten")
+            .expect("could not write note");
+        assert_eq!(
+            backend.read(&path).expect("could not read note"),
+            "rewritten"
+        );
+        assert_eq!(
+            backend
+                .list(&notes_directory)
+                .expect("could not list notes")
+                .into_iter()
+                .sorted()
+                .collect::<Vec<_>>(),
+            vec![unclobbered_path, path.clone()]
+        );
+        assert!(backend
+            .list(&root.join("daily"))
+            .expect("could not list notes")
+            .is_empty());
+
+        backend.delete(&path).expect("could not delete note");
+        assert_eq!(
+            backend
+                .read(&path)
+                .expect_err("note must be deleted")
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            backend
+                .delete(&path)
+                .expect_err("note must be deleted")
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn filesystem_backend_stores_notes() {
+        let root = tempfile::tempdir().expect("could not create tempdir");
+        fs::create_dir_all(root.path().join("notes")).expect("could not create notes directory");
+        fs::create_dir_all(root.path().join("daily")).expect("could not create daily directory");
+
+        check_backend(&FilesystemBackend::default(), root.path());
+    }
+
+    #[test]
+    fn filesystem_backend_keeps_notes_that_cannot_be_stored() {
+        let root = tempfile::tempdir().expect("could not create tempdir");
+        let temp_root = tempfile::tempdir().expect("could not create tempdir");
+        let config = NoteConfig {
+            file_extension: "md".to_string(),
+            root_dir: root.path().to_owned(),
+            temp_root_override: Some(temp_root.path().to_owned()),
+            date_dialect: DateDialect::default(),
+            daily: DailyConfig::default(),
+            inbox: InboxConfig::default(),
+        };
+        let backend = FilesystemBackend::new(&config);
+        fs::create_dir_all(root.path().join("notes")).expect("could not create notes directory");
+        fs::create_dir_all(root.path().join("daily")).expect("could not create daily directory");
+        check_backend(&backend, root.path());
+
+        // The directory does not exist, so the note can't be written there
+        let path = root.path().join("daily/2015/2015-10-21.md");
+        backend
+            .write(&path, NOTE)
+            .expect_err("note must not be stored");
+
+        let notes =
+            recovery::list(&config.recovery_directory_path()).expect("could not list notes");
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].destination, path);
+        assert_eq!(notes[0].kind, NoteKind::Daily);
+        assert_eq!(
+            fs::read_to_string(&notes[0].path).expect("could not read note"),
+            NOTE
+        );
+    }
+
+    #[test]
+    fn sqlite_backend_stores_notes() {
+        let backend = SqliteBackend::open_in_memory().expect("could not open vault");
+
+        check_backend(&backend, Path::new("/vault"));
+    }
+
+    #[test]
+    fn sqlite_backend_stores_preamble_and_body_separately() {
+        let backend = SqliteBackend::open_in_memory().expect("could not open vault");
+        backend
+            .create(Path::new("/vault/notes/flux-capacitor.md"), NOTE)
+            .expect("could not create note");
+
+        let (preamble, body) = backend
+            .connection
+            .query_row("SELECT preamble, body FROM notes;", [], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .expect("could not query vault");
+
+        assert!(preamble.starts_with("---\ntitle = \"Flux Capacitor\""));
+        assert!(preamble.ends_with("---\n"));
+        assert_eq!(body, "\nIt's what makes time travel possible.\n");
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::index::NoteKind;
+use crate::warning;
+
+/// The path of the manifest within a bundle.
+const MANIFEST_PATH: &str = "manifest.toml";
+
+/// The version of the bundle format written by [`write`]. Bundles with any other version are
+/// rejected by [`read`].
+const BUNDLE_VERSION: u32 = 1;
+
+/// The most data a bundle may hold once decompressed, so that a small, maliciously compressed
+/// bundle can't exhaust memory.
+const MAX_BUNDLE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// A note stored in a bundle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BundledNote {
+    /// The path of the note, relative to the root directory it came from.
+    pub path: PathBuf,
+    pub kind: NoteKind,
+    pub contents: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+
+    #[serde(default)]
+    notes: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestEntry {
+    path: String,
+    sha256: String,
+    kind: ManifestNoteKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ManifestNoteKind {
+    Note,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl From<NoteKind> for ManifestNoteKind {
+    fn from(kind: NoteKind) -> Self {
+        match kind {
+            NoteKind::Note => Self::Note,
+            NoteKind::Daily => Self::Daily,
+            NoteKind::Weekly => Self::Weekly,
+            NoteKind::Monthly => Self::Monthly,
+        }
+    }
+}
+
+impl From<ManifestNoteKind> for NoteKind {
+    fn from(kind: ManifestNoteKind) -> Self {
+        match kind {
+            ManifestNoteKind::Note => Self::Note,
+            ManifestNoteKind::Daily => Self::Daily,
+            ManifestNoteKind::Weekly => Self::Weekly,
+            ManifestNoteKind::Monthly => Self::Monthly,
+        }
+    }
+}
+
+/// Write the given notes to `writer` as a gzipped tarball, along with a manifest of their paths,
+/// hashes and kinds.
+pub fn write<W: Write>(writer: W, notes: &[BundledNote]) -> Result<(), WriteError> {
+    let manifest = Manifest {
+        version: BUNDLE_VERSION,
+        notes: notes
+            .iter()
+            .map(|note| {
+                Ok(ManifestEntry {
+                    path: bundle_path(&note.path)?,
+                    sha256: hash(&note.contents),
+                    kind: note.kind.into(),
+                })
+            })
+            .collect::<Result<_, WriteError>>()?,
+    };
+    let serialized_manifest = toml::to_string(&manifest)?;
+
+    let mut builder = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+    append_file(&mut builder, MANIFEST_PATH, serialized_manifest.as_bytes())?;
+    for (note, entry) in notes.iter().zip(&manifest.notes) {
+        append_file(&mut builder, &entry.path, &note.contents)?;
+    }
+
+    builder.into_inner()?.finish()?;
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum WriteError {
+    #[error("note path {0:?} is not valid UTF-8")]
+    InvalidPath(PathBuf),
+
+    #[error("could not serialize manifest: {0}")]
+    SerializeError(#[from] toml::ser::Error),
+
+    #[error("could not write bundle: {0}")]
+    IOError(#[from] io::Error),
+}
+
+/// Read the notes from a bundle written by [`write`], checking each against the manifest.
+pub fn read<R: Read>(reader: R) -> Result<Vec<BundledNote>, ReadError> {
+    read_with_limit(reader, MAX_BUNDLE_SIZE)
+}
+
+fn read_with_limit<R: Read>(reader: R, max_size: u64) -> Result<Vec<BundledNote>, ReadError> {
+    // One byte past the limit is read, so that we can tell a bundle that is exactly at the limit
+    // from one that is over it
+    let mut tarball = Vec::new();
+    GzDecoder::new(reader)
+        .take(max_size + 1)
+        .read_to_end(&mut tarball)?;
+    if tarball.len() as u64 > max_size {
+        return Err(ReadError::TooLarge(max_size));
+    }
+
+    let mut archive = tar::Archive::new(tarball.as_slice());
+    let mut files = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.insert(path, contents);
+    }
+
+    let manifest_contents = files
+        .remove(MANIFEST_PATH)
+        .ok_or(ReadError::MissingManifest)?;
+    let manifest = toml::from_str::<Manifest>(&String::from_utf8_lossy(&manifest_contents))?;
+    if manifest.version != BUNDLE_VERSION {
+        return Err(ReadError::UnsupportedVersion(manifest.version));
+    }
+
+    let notes = manifest
+        .notes
+        .into_iter()
+        .map(|entry| {
+            let path = PathBuf::from(&entry.path);
+            if !crate::is_plain_relative_path(&path) {
+                return Err(ReadError::InvalidPath(entry.path));
+            }
+
+            let contents = files
+                .remove(&entry.path)
+                .ok_or_else(|| ReadError::MissingNote(entry.path.clone()))?;
+            if hash(&contents) != entry.sha256 {
+                return Err(ReadError::HashMismatch(entry.path));
+            }
+
+            Ok(BundledNote {
+                path,
+                kind: entry.kind.into(),
+                contents,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for path in files.keys() {
+        warning!("Ignoring {path} in bundle, as it is not in the manifest");
+    }
+
+    Ok(notes)
+}
+
+#[derive(Error, Debug)]
+pub enum ReadError {
+    #[error("could not read bundle: {0}")]
+    IOError(#[from] io::Error),
+
+    #[error("bundle is larger than {0} bytes once decompressed")]
+    TooLarge(u64),
+
+    #[error("bundle has no {MANIFEST_PATH}")]
+    MissingManifest,
+
+    #[error("could not parse manifest: {0}")]
+    ManifestError(#[from] toml::de::Error),
+
+    #[error("unsupported bundle version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("manifest lists {0:?}, which is not a relative path")]
+    InvalidPath(String),
+
+    #[error("manifest lists {0:?}, but it is not in the bundle")]
+    MissingNote(String),
+
+    #[error("{0:?} does not match the hash in the manifest; the bundle may be corrupt")]
+    HashMismatch(String),
+}
+
+/// Get the SHA-256 hash of the given contents, as hex.
+pub fn hash(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+/// Get the path of a note within the bundle, which always uses `/` as a separator.
+fn bundle_path(path: &Path) -> Result<String, WriteError> {
+    path.components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()
+        .map(|parts| parts.join("/"))
+        .ok_or_else(|| WriteError::InvalidPath(path.to_owned()))
+}
+
+fn append_file<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    contents: &[u8],
+) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+
+    builder.append_data(&mut header, path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_can_be_read_back_from_a_bundle() {
+        let notes = vec![
+            BundledNote {
+                path: PathBuf::from("notes/my-cool-note.txt"),
+                kind: NoteKind::Note,
+                contents: b"hello, world!\n".to_vec(),
+            },
+            BundledNote {
+                path: PathBuf::from("daily/2015/10/21.txt"),
+                kind: NoteKind::Daily,
+                contents: b"today was a cool day\n".to_vec(),
+            },
+        ];
+
+        let mut bundle = Vec::new();
+        write(&mut bundle, &notes).expect("could not write bundle");
+
+        assert_eq!(
+            read(bundle.as_slice()).expect("could not read bundle"),
+            notes
+        );
+    }
+
+    #[test]
+    fn bundles_over_the_size_limit_are_rejected() {
+        let notes = vec![BundledNote {
+            path: PathBuf::from("notes/big.txt"),
+            kind: NoteKind::Note,
+            contents: vec![b'a'; 64 * 1024],
+        }];
+
+        let mut bundle = Vec::new();
+        write(&mut bundle, &notes).expect("could not write bundle");
+
+        let result = read_with_limit(bundle.as_slice(), 32 * 1024);
+
+        assert!(
+            matches!(result, Err(ReadError::TooLarge(_))),
+            "expected bundle to be too large, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn corrupt_notes_are_rejected() {
+        let mut bundle = Vec::new();
+        {
+            let mut builder =
+                tar::Builder::new(GzEncoder::new(&mut bundle, Compression::default()));
+            let manifest = format!(
+                "version = 1\n[[notes]]\npath = \"notes/a.txt\"\nsha256 = \"{}\"\nkind = \"note\"\n",
+                hash(b"original")
+            );
+            append_file(&mut builder, MANIFEST_PATH, manifest.as_bytes()).unwrap();
+            append_file(&mut builder, "notes/a.txt", b"tampered").unwrap();
+            builder.into_inner().unwrap().finish().unwrap();
+        }
+
+        let result = read(bundle.as_slice());
+
+        assert!(
+            matches!(result, Err(ReadError::HashMismatch(_))),
+            "expected hash mismatch, got {result:?}"
+        );
+    }
+}
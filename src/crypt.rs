@@ -0,0 +1,198 @@
+//! Encryption for the bodies of notes marked `encrypted = true`.
+//!
+//! The body is encrypted with ChaCha20-Poly1305, using a key derived from a passphrase with
+//! scrypt, and stored as base64 between two armor lines. The preamble is left as plain text, so
+//! that the note can still be indexed by its title and tags.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use thiserror::Error;
+
+const ARMOR_BEGIN: &str = "-----BEGIN QUICKNOTES ENCRYPTED BODY-----";
+const ARMOR_END: &str = "-----END QUICKNOTES ENCRYPTED BODY-----";
+
+/// The number of base64 characters on each line of an encrypted body.
+const ARMOR_LINE_LENGTH: usize = 64;
+
+/// The version of the format written by [`encrypt`]. Bodies with any other version are rejected by
+/// [`decrypt`].
+const FORMAT_VERSION: u8 = 1;
+
+/// The scrypt cost parameter (as a power of two) used for new bodies. It is stored alongside each
+/// body, so it can be raised without breaking existing notes.
+const SCRYPT_LOG_N: u8 = 15;
+/// The highest scrypt cost parameter [`decrypt`] accepts. The cost is read from the note, so this
+/// stops a crafted note from making decryption take gigabytes of memory (scrypt needs 128 * r *
+/// `2^log_n` bytes, i.e. 64 MiB here).
+const MAX_SCRYPT_LOG_N: u8 = 16;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const HEADER_LENGTH: usize = 2 + SALT_LENGTH + NONCE_LENGTH;
+
+/// Check if the given note body has been encrypted by [`encrypt`].
+pub fn is_encrypted(body: &str) -> bool {
+    body.trim_start().starts_with(ARMOR_BEGIN)
+}
+
+/// Encrypt a note body with the given passphrase. The result is armored text, suitable for
+/// storing after the note's preamble.
+pub fn encrypt(plaintext: &str, passphrase: &str) -> Result<String, EncryptError> {
+    let mut salt = [0; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt, SCRYPT_LOG_N)?;
+
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_err| EncryptError::CipherError)?;
+
+    let mut payload = Vec::with_capacity(HEADER_LENGTH + ciphertext.len());
+    payload.push(FORMAT_VERSION);
+    payload.push(SCRYPT_LOG_N);
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&ciphertext);
+
+    let encoded = BASE64.encode(payload);
+    let mut armored = format!("{ARMOR_BEGIN}\n");
+    for line in encoded.as_bytes().chunks(ARMOR_LINE_LENGTH) {
+        // base64 is always ASCII
+        armored += std::str::from_utf8(line).expect("base64 must be valid UTF-8");
+        armored.push('\n');
+    }
+    armored += ARMOR_END;
+    armored.push('\n');
+
+    Ok(armored)
+}
+
+#[derive(Error, Debug)]
+pub enum EncryptError {
+    #[error("could not derive a key from the passphrase")]
+    KeyDerivationError,
+
+    #[error("could not encrypt note")]
+    CipherError,
+}
+
+/// Decrypt a note body that was encrypted by [`encrypt`].
+pub fn decrypt(armored: &str, passphrase: &str) -> Result<String, DecryptError> {
+    let encoded = armored
+        .trim()
+        .strip_prefix(ARMOR_BEGIN)
+        .and_then(|rest| rest.strip_suffix(ARMOR_END))
+        .ok_or(DecryptError::MalformedBody)?
+        .lines()
+        .map(str::trim)
+        .collect::<String>();
+
+    let payload = BASE64
+        .decode(encoded)
+        .map_err(|_err| DecryptError::MalformedBody)?;
+    if payload.len() < HEADER_LENGTH {
+        return Err(DecryptError::MalformedBody);
+    }
+
+    let (header, ciphertext) = payload.split_at(HEADER_LENGTH);
+    let version = header[0];
+    if version != FORMAT_VERSION {
+        return Err(DecryptError::UnsupportedVersion(version));
+    }
+
+    let log_n = header[1];
+    if log_n > MAX_SCRYPT_LOG_N {
+        return Err(DecryptError::UnsupportedCost(log_n));
+    }
+
+    let salt = &header[2..2 + SALT_LENGTH];
+    let nonce = Nonce::from_slice(&header[2 + SALT_LENGTH..]);
+    let key = derive_key(passphrase, salt, log_n).map_err(|_err| DecryptError::MalformedBody)?;
+
+    let plaintext = ChaCha20Poly1305::new(&key)
+        .decrypt(nonce, ciphertext)
+        .map_err(|_err| DecryptError::WrongPassphrase)?;
+
+    String::from_utf8(plaintext).map_err(|_err| DecryptError::MalformedBody)
+}
+
+#[derive(Error, Debug)]
+pub enum DecryptError {
+    #[error("encrypted body is malformed")]
+    MalformedBody,
+
+    #[error("unsupported encryption format version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("encrypted body asks for a key derivation cost of 2^{0}, which is too high")]
+    UnsupportedCost(u8),
+
+    #[error("could not decrypt note; the passphrase may be wrong")]
+    WrongPassphrase,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], log_n: u8) -> Result<Key, EncryptError> {
+    let params = scrypt::Params::new(log_n, SCRYPT_R, SCRYPT_P, scrypt::Params::RECOMMENDED_LEN)
+        .map_err(|_err| EncryptError::KeyDerivationError)?;
+
+    let mut key = Key::default();
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .map_err(|_err| EncryptError::KeyDerivationError)?;
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bodies_can_be_decrypted_with_the_same_passphrase() {
+        let body = "\nthe vault combination is 12-34-56\n";
+
+        let encrypted = encrypt(body, "hunter2").expect("could not encrypt");
+
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("vault"));
+        assert_eq!(
+            decrypt(&encrypted, "hunter2").expect("could not decrypt"),
+            body
+        );
+    }
+
+    #[test]
+    fn bodies_cannot_be_decrypted_with_the_wrong_passphrase() {
+        let encrypted = encrypt("secret", "hunter2").expect("could not encrypt");
+
+        let result = decrypt(&encrypted, "hunter3");
+
+        assert!(
+            matches!(result, Err(DecryptError::WrongPassphrase)),
+            "expected wrong passphrase, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn bodies_with_too_high_a_cost_are_rejected() {
+        let encrypted = encrypt("secret", "hunter2").expect("could not encrypt");
+        let encoded = encrypted
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>();
+        let mut payload = BASE64.decode(encoded).expect("could not decode");
+        payload[1] = 40;
+        let tampered = format!("{ARMOR_BEGIN}\n{}\n{ARMOR_END}\n", BASE64.encode(payload));
+
+        let result = decrypt(&tampered, "hunter2");
+
+        assert!(
+            matches!(result, Err(DecryptError::UnsupportedCost(40))),
+            "expected unsupported cost, got {result:?}"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+
+use chrono::{DateTime, FixedOffset};
+use serde_derive::{Deserialize, Serialize};
+use tempfile::TempPath;
+
+use crate::bundle::hash;
+use crate::index::NoteKind;
+use crate::note;
+use crate::sidecar::{SidecarKind, WriteSidecarError};
+use crate::warning;
+
+/// The journal entry beside a draft, recording the process writing it and where it is meant to be
+/// stored.
+const JOURNAL_SIDECAR: SidecarKind = SidecarKind {
+    suffix: ".draft.toml",
+    description: "drafts journal entry",
+};
+
+/// A new note that was being written by a quicknotes process that is no longer running, e.g.
+/// because the terminal was closed while the editor was open.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Draft {
+    /// Where the draft is in the drafts directory.
+    pub path: PathBuf,
+    /// Where the note was meant to be stored.
+    pub destination: PathBuf,
+    pub kind: NoteKind,
+    /// When the note was started.
+    pub started_at: DateTime<FixedOffset>,
+    /// The hash of the contents the draft was seeded with, to tell if anything was written.
+    pub(crate) initial_hash: String,
+}
+
+impl Draft {
+    /// Check if the draft's preamble marks it as encrypted, in which case a passphrase is needed to
+    /// store it.
+    #[must_use]
+    pub fn is_encrypted(&self) -> bool {
+        fs::File::open(&self.path)
+            .is_ok_and(|file| note::extract_preamble(file).is_ok_and(|preamble| preamble.encrypted))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalEntry {
+    pid: u32,
+    destination: PathBuf,
+    kind: NoteKind,
+
+    #[serde(
+        serialize_with = "note::serialize_datetime",
+        deserialize_with = "note::deserialize_datetime"
+    )]
+    started_at: DateTime<FixedOffset>,
+
+    initial_sha256: String,
+}
+
+/// A draft that is being written by this process. It is listed in the journal until it is
+/// dropped. If the thread panics before then, the draft and its journal entry are left behind, so
+/// that the draft can be picked up again later.
+pub struct DraftGuard {
+    path: Option<TempPath>,
+    journal_path: Option<PathBuf>,
+}
+
+impl DraftGuard {
+    /// Start tracking the draft at `path` in the journal.
+    pub fn track(
+        path: TempPath,
+        destination: &Path,
+        kind: NoteKind,
+        started_at: DateTime<FixedOffset>,
+        initial_contents: &str,
+    ) -> Result<Self, WriteSidecarError> {
+        JOURNAL_SIDECAR.write(
+            &path,
+            &JournalEntry {
+                pid: process::id(),
+                destination: destination.to_owned(),
+                kind,
+                started_at,
+                initial_sha256: hash(initial_contents.as_bytes()),
+            },
+        )?;
+
+        Ok(Self {
+            journal_path: Some(JOURNAL_SIDECAR.path(&path)),
+            path: Some(path),
+        })
+    }
+
+    /// Guard a draft that is not listed in the journal, such as the plain text of a note that is
+    /// to be encrypted. It is deleted, rather than left behind, if the thread panics.
+    pub fn untracked(path: TempPath) -> Self {
+        Self {
+            path: Some(path),
+            journal_path: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path
+            .as_ref()
+            .expect("draft must not be used after it is taken")
+    }
+
+    /// Take the draft, to be stored. It stays in the journal until the guard is dropped.
+    pub fn take(&mut self) -> TempPath {
+        self.path
+            .take()
+            .expect("draft must not be taken more than once")
+    }
+}
+
+impl Drop for DraftGuard {
+    fn drop(&mut self) {
+        let Some(journal_path) = &self.journal_path else {
+            return;
+        };
+
+        if thread::panicking() {
+            if let Some(path) = self.path.take() {
+                // If this fails, there's nothing more we can do mid-panic
+                let _ = path.keep();
+            }
+
+            return;
+        }
+
+        if let Err(err) = fs::remove_file(journal_path) {
+            warning!(
+                "Could not remove {} from the drafts journal: {}",
+                journal_path.display(),
+                err
+            );
+        }
+    }
+}
+
+/// List the drafts whose process is no longer running, sorted by path. Drafts whose journal entry
+/// cannot be read are skipped with a warning. A missing drafts directory has no drafts.
+pub fn abandoned(directory: &Path) -> Result<Vec<Draft>, io::Error> {
+    let drafts = JOURNAL_SIDECAR
+        .list::<JournalEntry>(directory)?
+        .into_iter()
+        .filter(|(_path, entry)| entry.pid != process::id() && !is_process_alive(entry.pid))
+        .map(|(path, entry)| Draft {
+            path,
+            destination: entry.destination,
+            kind: entry.kind,
+            started_at: entry.started_at,
+            initial_hash: entry.initial_sha256,
+        })
+        .collect();
+
+    Ok(drafts)
+}
+
+/// Mark an abandoned draft as being written by this process, so that it is not picked up by
+/// another while it is being resumed.
+pub fn claim(draft: &Draft) -> Result<(), WriteSidecarError> {
+    JOURNAL_SIDECAR.write(
+        &draft.path,
+        &JournalEntry {
+            pid: process::id(),
+            destination: draft.destination.clone(),
+            kind: draft.kind,
+            started_at: draft.started_at,
+            initial_sha256: draft.initial_hash.clone(),
+        },
+    )
+}
+
+/// Remove a draft, and its journal entry.
+pub fn remove(draft: &Draft) -> Result<(), io::Error> {
+    JOURNAL_SIDECAR.remove(&draft.path)
+}
+
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+
+    // Sending signal 0 only checks that the process exists, and that we could signal it
+    // SAFETY: kill has no memory safety requirements
+    let result = unsafe { libc::kill(pid, 0) };
+
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_process_alive(_pid: u32) -> bool {
+    // There's no portable way to check, so err on the side of leaving the draft alone
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use tempfile::{NamedTempFile, TempDir};
+
+    use super::*;
+
+    fn started_at() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(-7 * 60 * 60)
+            .unwrap()
+            .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+            .single()
+            .unwrap()
+    }
+
+    #[test]
+    fn drafts_of_running_processes_are_not_abandoned() {
+        let directory = TempDir::new().expect("could not create directory");
+        let path = NamedTempFile::new_in(directory.path())
+            .expect("could not create draft")
+            .into_temp_path();
+
+        let guard = DraftGuard::track(
+            path,
+            Path::new("/notes/my-note.md"),
+            NoteKind::Note,
+            started_at(),
+            "",
+        )
+        .expect("could not track draft");
+
+        assert!(abandoned(directory.path())
+            .expect("could not list drafts")
+            .is_empty());
+
+        drop(guard);
+        assert_eq!(
+            fs::read_dir(directory.path())
+                .expect("could not read directory")
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn drafts_of_exited_processes_are_abandoned() {
+        let directory = TempDir::new().expect("could not create directory");
+        let path = directory.path().join("draft-abc123.md");
+        fs::write(&path, "hello, world!\n").expect("could not write draft");
+        let mut exited = process::Command::new("true")
+            .spawn()
+            .expect("could not start process");
+        exited.wait().expect("could not wait for process");
+        JOURNAL_SIDECAR
+            .write(
+                &path,
+                &JournalEntry {
+                    pid: exited.id(),
+                    destination: PathBuf::from("/notes/my-note.md"),
+                    kind: NoteKind::Note,
+                    started_at: started_at(),
+                    initial_sha256: hash(b""),
+                },
+            )
+            .expect("could not write journal");
+
+        let drafts = abandoned(directory.path()).expect("could not list drafts");
+
+        assert_eq!(
+            drafts,
+            vec![Draft {
+                path: path.clone(),
+                destination: PathBuf::from("/notes/my-note.md"),
+                kind: NoteKind::Note,
+                started_at: started_at(),
+                initial_hash: hash(b""),
+            }]
+        );
+    }
+}
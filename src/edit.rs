@@ -1,3 +1,4 @@
+use std::ffi::OsString;
 use std::io;
 use std::path::Path;
 use std::process::Command;
@@ -8,10 +9,59 @@ pub trait Editor {
     fn name(&self) -> &str;
     /// Edit the given note
     ///
+    /// Returns [`EditOutcome::Aborted`] if the user asked for their edits to be thrown away
+    /// (e.g. by exiting `vim` with `:cq`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the editor had a problem editing the note.
+    fn edit(&self, path: &Path) -> io::Result<EditOutcome>;
+
+    /// Edit the given note, starting at the given position in the file. Editors which cannot
+    /// be opened at a position are free to ignore it, which is the default behavior.
+    ///
     /// # Errors
     ///
     /// Returns an error if the editor had a problem editing the note.
-    fn edit(&self, path: &Path) -> io::Result<()>;
+    fn edit_at(&self, path: &Path, position: Position) -> io::Result<EditOutcome> {
+        let _ = position;
+
+        self.edit(path)
+    }
+}
+
+/// The result of a successful run of an [`Editor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditOutcome {
+    /// The editor exited normally, and the note should be kept.
+    Completed,
+    /// The editor signaled that the edit should be abandoned.
+    Aborted,
+}
+
+/// A position within a note. Both the line and column are 1-indexed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    #[must_use]
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    /// Get the position just after the last character of the given text.
+    #[must_use]
+    pub fn end_of(text: &str) -> Self {
+        let last_line = text.lines().last().unwrap_or_default();
+
+        Self {
+            line: text.lines().count().max(1),
+            column: last_line.chars().count() + 1,
+        }
+    }
 }
 
 impl<E: Editor> Editor for &E {
@@ -19,21 +69,90 @@ impl<E: Editor> Editor for &E {
         (*self).name()
     }
 
-    fn edit(&self, path: &Path) -> io::Result<()> {
+    fn edit(&self, path: &Path) -> io::Result<EditOutcome> {
         (*self).edit(path)
     }
+
+    fn edit_at(&self, path: &Path, position: Position) -> io::Result<EditOutcome> {
+        (*self).edit_at(path, position)
+    }
 }
 
 /// An editor that runs a command to launch. This is useful for CLI tools such as `vim`.
+///
+/// If the command exits with a non-zero status, the edit is considered to be aborted.
+///
+/// Positions are honored for editors that are known to accept them on the command line
+/// (e.g. `vim`, `emacs`, `nano`, and VS Code); for all others, the position is ignored.
 pub struct CommandEditor {
     command: String,
 }
 
+/// The style of command line arguments an editor uses to open a file at a given position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PositionArgStyle {
+    /// `+LINE path`, as used by `vi` and its descendants
+    PlusLine,
+    /// `+LINE:COLUMN path`, as used by `emacs`
+    PlusLineColonColumn,
+    /// `+LINE,COLUMN path`, as used by `nano`
+    PlusLineCommaColumn,
+    /// `--goto path:LINE:COLUMN`, as used by VS Code
+    Goto,
+}
+
 impl CommandEditor {
     #[must_use]
     pub fn new(command: String) -> Self {
         Self { command }
     }
+
+    fn run(&self, args: &[OsString]) -> io::Result<EditOutcome> {
+        let status = Command::new(&self.command).args(args).spawn()?.wait()?;
+
+        if status.success() {
+            Ok(EditOutcome::Completed)
+        } else {
+            Ok(EditOutcome::Aborted)
+        }
+    }
+
+    fn position_arg_style(&self) -> Option<PositionArgStyle> {
+        let program = Path::new(&self.command).file_stem()?.to_str()?;
+
+        match program {
+            "vi" | "vim" | "nvim" | "gvim" | "mvim" => Some(PositionArgStyle::PlusLine),
+            "emacs" | "emacsclient" => Some(PositionArgStyle::PlusLineColonColumn),
+            "nano" => Some(PositionArgStyle::PlusLineCommaColumn),
+            "code" | "code-insiders" | "codium" => Some(PositionArgStyle::Goto),
+            _ => None,
+        }
+    }
+
+    fn position_args(&self, path: &Path, position: Position) -> Vec<OsString> {
+        let Position { line, column } = position;
+
+        match self.position_arg_style() {
+            Some(PositionArgStyle::PlusLine) => vec![format!("+{line}").into(), path.into()],
+
+            Some(PositionArgStyle::PlusLineColonColumn) => {
+                vec![format!("+{line}:{column}").into(), path.into()]
+            }
+
+            Some(PositionArgStyle::PlusLineCommaColumn) => {
+                vec![format!("+{line},{column}").into(), path.into()]
+            }
+
+            Some(PositionArgStyle::Goto) => {
+                let mut location = OsString::from(path);
+                location.push(format!(":{line}:{column}"));
+
+                vec!["--goto".into(), location]
+            }
+
+            None => vec![path.into()],
+        }
+    }
 }
 
 impl Editor for CommandEditor {
@@ -41,11 +160,39 @@ impl Editor for CommandEditor {
         &self.command
     }
 
-    fn edit(&self, path: &Path) -> io::Result<()> {
-        Command::new(&self.command)
-            .arg(path)
-            .spawn()?
-            .wait()
-            .map(|_output| ())
+    fn edit(&self, path: &Path) -> io::Result<EditOutcome> {
+        self.run(&[path.into()])
+    }
+
+    fn edit_at(&self, path: &Path, position: Position) -> io::Result<EditOutcome> {
+        self.run(&self.position_args(path, position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("vim", &["+3", "note.md"]; "vim")]
+    #[test_case("/usr/bin/nvim", &["+3", "note.md"]; "nvim with full path")]
+    #[test_case("emacs", &["+3:7", "note.md"]; "emacs")]
+    #[test_case("nano", &["+3,7", "note.md"]; "nano")]
+    #[test_case("code", &["--goto", "note.md:3:7"]; "vs code")]
+    #[test_case("ed", &["note.md"]; "unknown editor")]
+    fn position_args_use_the_editors_style(command: &str, expected: &[&str]) {
+        let editor = CommandEditor::new(command.to_string());
+        let args = editor.position_args(Path::new("note.md"), Position::new(3, 7));
+
+        assert_eq!(args, expected);
+    }
+
+    #[test_case("", Position::new(1, 1); "empty text")]
+    #[test_case("hello", Position::new(1, 6); "single line")]
+    #[test_case("---\ntitle = \"a\"\n---\n\n", Position::new(4, 1); "trailing newline")]
+    #[test_case("---\n---\n\nsome words", Position::new(4, 11); "text on last line")]
+    fn end_of_is_after_last_character(text: &str, expected: Position) {
+        assert_eq!(Position::end_of(text), expected);
     }
 }
@@ -0,0 +1,503 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Timelike, Weekday};
+use chrono_english::Dialect;
+use thiserror::Error;
+
+const ISO_WEEK_SEPARATOR: &str = "-w";
+
+/// The order that the day and month are written in numeric dates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DateDialect {
+    /// Month first, such as `10/21/2015`.
+    #[default]
+    Us,
+    /// Day first, such as `21/10/2015`.
+    Uk,
+}
+
+impl DateDialect {
+    fn numeric_format(self) -> &'static str {
+        match self {
+            Self::Us => "%m/%d/%Y",
+            Self::Uk => "%d/%m/%Y",
+        }
+    }
+
+    fn numeric_format_description(self) -> &'static str {
+        match self {
+            Self::Us => "MM/DD/YYYY",
+            Self::Uk => "DD/MM/YYYY",
+        }
+    }
+}
+
+impl From<DateDialect> for Dialect {
+    fn from(dialect: DateDialect) -> Self {
+        match dialect {
+            DateDialect::Us => Dialect::Us,
+            DateDialect::Uk => Dialect::Uk,
+        }
+    }
+}
+
+/// Parse a "fuzzy" date, relative to `today`. In addition to absolute dates (e.g. `2015-10-21`,
+/// or `10/21/2015` in the given dialect), this understands
+///
+/// - `+N` and `-N`, for N days after or before today
+/// - ISO week dates, such as `2015-W43` (the Monday of that week) or `2015-W43-3`
+/// - `today`, `yesterday` and `tomorrow`
+/// - `N days ago` and `in N days`, along with weeks, months and years
+/// - `last week`, `next month`, `this year` and so on
+/// - weekday names: `monday` is the most recent Monday (which may be today), `last monday` and
+///   `next monday` are the closest Mondays before and after today, and `this monday` is the
+///   Monday of the current week
+/// - a weekday after a relative date, such as `2 weeks ago friday` or `last week monday`, for that
+///   day of the week the date falls in
+/// - `start of week` and `start of month`, optionally with `last`, `next` or `this`
+///
+/// Weeks start on Monday. Anything else is handed to `chrono_english`, as long as it does not
+/// refer to a time of day.
+///
+/// # Errors
+///
+/// Returns an error if the date could not be understood, or is out of the range of supported
+/// dates. If only the start of the input could be understood, the error says what it was
+/// understood as.
+pub fn parse_fuzzy_date(
+    input: &str,
+    today: NaiveDate,
+    dialect: DateDialect,
+) -> Result<NaiveDate, FuzzyDateError> {
+    let date = parse(input, today, dialect)?;
+
+    Ok(date)
+}
+
+/// An error that occurred during a call to [`parse_fuzzy_date`]. See its
+/// [errors section](`parse_fuzzy_date#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct FuzzyDateError {
+    #[from]
+    inner: InnerFuzzyDateError,
+}
+
+#[derive(Error, Debug)]
+enum InnerFuzzyDateError {
+    #[error("could not understand {input:?} as a date")]
+    Unrecognized { input: String },
+
+    #[error("understood {parsed:?} as {date}, but not {rest:?} after it")]
+    TrailingInput {
+        parsed: String,
+        date: NaiveDate,
+        rest: String,
+    },
+
+    #[error("{input:?} is not a valid date; numeric dates must be written as {expected}")]
+    InvalidNumericDate {
+        input: String,
+        expected: &'static str,
+    },
+
+    #[error("{input:?} is not a valid ISO week date, such as 2015-W43 or 2015-W43-3")]
+    InvalidIsoWeekDate { input: String },
+
+    #[error("{input:?} refers to a time of day, but only a date can be given")]
+    HasTime { input: String },
+
+    #[error("{input:?} is out of the range of supported dates")]
+    OutOfRange { input: String },
+}
+
+/// A date relative to today that was parsed from the start of the input.
+struct Relative {
+    date: NaiveDate,
+    words_used: usize,
+    /// Whether a weekday may follow this, to pick a day in the same week.
+    allows_weekday: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+fn parse(
+    input: &str,
+    today: NaiveDate,
+    dialect: DateDialect,
+) -> Result<NaiveDate, InnerFuzzyDateError> {
+    let normalized = input.to_lowercase();
+    let words = normalized.split_whitespace().collect::<Vec<_>>();
+    let out_of_range = || InnerFuzzyDateError::OutOfRange {
+        input: input.to_string(),
+    };
+
+    if words.len() == 1 {
+        if let Some(date) = parse_single_word(input.trim(), today, dialect)? {
+            return Ok(date);
+        }
+    }
+
+    let relative = parse_relative(&words, today).map_err(|()| out_of_range())?;
+    let (date, words_used) = match relative {
+        Some(Relative {
+            date,
+            words_used,
+            allows_weekday: true,
+        }) => match words.get(words_used).and_then(|word| parse_weekday(word)) {
+            Some(weekday) => (
+                weekday_in_week(date, weekday).ok_or_else(out_of_range)?,
+                words_used + 1,
+            ),
+            None => (date, words_used),
+        },
+        Some(Relative {
+            date, words_used, ..
+        }) => (date, words_used),
+        None => match words.first().and_then(|word| parse_weekday(word)) {
+            Some(weekday) => (
+                most_recent_weekday(today, weekday, true).ok_or_else(out_of_range)?,
+                1,
+            ),
+            None => return parse_with_chrono_english(input, today, dialect),
+        },
+    };
+
+    if words_used < words.len() {
+        return Err(InnerFuzzyDateError::TrailingInput {
+            parsed: words[..words_used].join(" "),
+            date,
+            rest: words[words_used..].join(" "),
+        });
+    }
+
+    Ok(date)
+}
+
+/// Parse the forms of date that are a single word, such as `+3` or `2015-W43`. Returns None if the
+/// word is not one of these forms.
+fn parse_single_word(
+    word: &str,
+    today: NaiveDate,
+    dialect: DateDialect,
+) -> Result<Option<NaiveDate>, InnerFuzzyDateError> {
+    let out_of_range = || InnerFuzzyDateError::OutOfRange {
+        input: word.to_string(),
+    };
+
+    if let Some(days) = word.strip_prefix('+').and_then(parse_digits) {
+        return today
+            .checked_add_days(Days::new(days.into()))
+            .map(Some)
+            .ok_or_else(out_of_range);
+    }
+
+    if let Some(days) = word.strip_prefix('-').and_then(parse_digits) {
+        return today
+            .checked_sub_days(Days::new(days.into()))
+            .map(Some)
+            .ok_or_else(out_of_range);
+    }
+
+    let lowercase_word = word.to_lowercase();
+    if lowercase_word.contains(ISO_WEEK_SEPARATOR) {
+        return parse_iso_week_date(&lowercase_word)
+            .map(Some)
+            .ok_or_else(|| InnerFuzzyDateError::InvalidIsoWeekDate {
+                input: word.to_string(),
+            });
+    }
+
+    // Dates with two digit years are left to chrono_english, which knows how to expand them
+    let parts = word.split('/').collect::<Vec<_>>();
+    let is_numeric_date = parts.len() == 3
+        && parts.iter().all(|part| parse_digits(part).is_some())
+        && parts[2].len() == 4;
+    if is_numeric_date {
+        return NaiveDate::parse_from_str(word, dialect.numeric_format())
+            .map(Some)
+            .map_err(|_err| InnerFuzzyDateError::InvalidNumericDate {
+                input: word.to_string(),
+                expected: dialect.numeric_format_description(),
+            });
+    }
+
+    Ok(None)
+}
+
+/// Parse an ISO week date, such as `2015-w43` or `2015-w43-3`. Without a day, the Monday of the
+/// week is used.
+fn parse_iso_week_date(word: &str) -> Option<NaiveDate> {
+    let (year, week_and_day) = word.split_once(ISO_WEEK_SEPARATOR)?;
+    let (week, day) = week_and_day
+        .split_once('-')
+        .map_or((week_and_day, None), |(week, day)| (week, Some(day)));
+
+    let year = year.parse().ok()?;
+    let week = parse_digits(week)?;
+    let weekday = match day {
+        Some(day) => {
+            let day_index = parse_digits(day)?.checked_sub(1)?;
+            Weekday::try_from(u8::try_from(day_index).ok()?).ok()?
+        }
+        None => Weekday::Mon,
+    };
+
+    NaiveDate::from_isoywd_opt(year, week, weekday)
+}
+
+/// Parse a date relative to today from the start of the given words. Returns None if the words
+/// do not start with a relative date, or an error if the date is out of range.
+fn parse_relative(words: &[&str], today: NaiveDate) -> Result<Option<Relative>, ()> {
+    let relative = |date: Option<NaiveDate>, words_used, allows_weekday| {
+        date.map(|date| {
+            Some(Relative {
+                date,
+                words_used,
+                allows_weekday,
+            })
+        })
+        .ok_or(())
+    };
+
+    match words {
+        ["today", ..] => relative(Some(today), 1, false),
+        ["yesterday", ..] => relative(today.checked_sub_days(Days::new(1)), 1, false),
+        ["tomorrow", ..] => relative(today.checked_add_days(Days::new(1)), 1, false),
+        ["start", "of", rest @ ..] => {
+            let (offset, unit_word, words_used) = match rest {
+                [relation, unit, ..] if relation_offset(relation).is_some() => {
+                    (relation_offset(relation), *unit, 4)
+                }
+                ["the", unit, ..] => (Some(0), *unit, 4),
+                [unit, ..] => (Some(0), *unit, 3),
+                [] => return Ok(None),
+            };
+
+            let start = match (offset, parse_unit(unit_word)) {
+                (Some(offset), Some(Unit::Week)) => {
+                    shift(today, Unit::Week, offset).and_then(start_of_week)
+                }
+                (Some(offset), Some(Unit::Month)) => {
+                    shift(today, Unit::Month, offset).and_then(|date| date.with_day(1))
+                }
+                _ => return Ok(None),
+            };
+
+            relative(start, words_used, false)
+        }
+        [amount, unit, "ago", ..] => match (parse_amount(amount), parse_unit(unit)) {
+            (Some(amount), Some(unit)) => relative(shift(today, unit, -amount), 3, true),
+            _ => Ok(None),
+        },
+        ["in", amount, unit, ..] => match (parse_amount(amount), parse_unit(unit)) {
+            (Some(amount), Some(unit)) => relative(shift(today, unit, amount), 3, true),
+            _ => Ok(None),
+        },
+        [relation, word, ..] => {
+            let Some(offset) = relation_offset(relation) else {
+                return Ok(None);
+            };
+
+            if let Some(unit) = parse_unit(word) {
+                return relative(shift(today, unit, offset), 2, true);
+            }
+
+            let Some(weekday) = parse_weekday(word) else {
+                return Ok(None);
+            };
+
+            let date = match offset {
+                -1 => most_recent_weekday(today, weekday, false),
+                1 => next_weekday(today, weekday),
+                _ => weekday_in_week(today, weekday),
+            };
+
+            relative(date, 2, false)
+        }
+        _ => Ok(None),
+    }
+}
+
+fn parse_with_chrono_english(
+    input: &str,
+    today: NaiveDate,
+    dialect: DateDialect,
+) -> Result<NaiveDate, InnerFuzzyDateError> {
+    let marker = today.and_time(chrono::NaiveTime::MIN).and_utc();
+    let parsed =
+        chrono_english::parse_date_string(input, marker, dialect.into()).map_err(|_err| {
+            InnerFuzzyDateError::Unrecognized {
+                input: input.to_string(),
+            }
+        })?;
+
+    if parsed.num_seconds_from_midnight() > 0 {
+        return Err(InnerFuzzyDateError::HasTime {
+            input: input.to_string(),
+        });
+    }
+
+    Ok(parsed.date_naive())
+}
+
+/// How many of a unit `last`, `next` and `this` refer to.
+fn relation_offset(word: &str) -> Option<i64> {
+    match word {
+        "last" => Some(-1),
+        "this" => Some(0),
+        "next" => Some(1),
+        _ => None,
+    }
+}
+
+fn parse_amount(word: &str) -> Option<i64> {
+    match word {
+        "a" | "an" | "one" => Some(1),
+        _ => parse_digits(word).map(i64::from),
+    }
+}
+
+fn parse_digits(word: &str) -> Option<u32> {
+    if word.is_empty() || !word.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    word.parse().ok()
+}
+
+fn parse_unit(word: &str) -> Option<Unit> {
+    match word.strip_suffix('s').unwrap_or(word) {
+        "day" => Some(Unit::Day),
+        "week" => Some(Unit::Week),
+        "month" => Some(Unit::Month),
+        "year" => Some(Unit::Year),
+        _ => None,
+    }
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    word.parse().ok()
+}
+
+/// Move the date by the given number of units, which may be negative.
+fn shift(date: NaiveDate, unit: Unit, amount: i64) -> Option<NaiveDate> {
+    let magnitude = amount.unsigned_abs();
+    let (days, months) = match unit {
+        Unit::Day => (magnitude, 0),
+        Unit::Week => (magnitude.checked_mul(7)?, 0),
+        Unit::Month => (0, magnitude),
+        Unit::Year => (0, magnitude.checked_mul(12)?),
+    };
+    let days = Days::new(days);
+    let months = Months::new(u32::try_from(months).ok()?);
+
+    if amount < 0 {
+        date.checked_sub_days(days)?.checked_sub_months(months)
+    } else {
+        date.checked_add_days(days)?.checked_add_months(months)
+    }
+}
+
+fn start_of_week(date: NaiveDate) -> Option<NaiveDate> {
+    date.checked_sub_days(Days::new(date.weekday().num_days_from_monday().into()))
+}
+
+/// Get the given day of the week in the same (Monday-first) week as the date.
+fn weekday_in_week(date: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    start_of_week(date)?.checked_add_days(Days::new(weekday.num_days_from_monday().into()))
+}
+
+/// Get the closest date before `date` that falls on the given weekday, or `date` itself if
+/// `inclusive` is set and it is that weekday.
+fn most_recent_weekday(date: NaiveDate, weekday: Weekday, inclusive: bool) -> Option<NaiveDate> {
+    let mut days_back =
+        (7 + date.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+    if days_back == 0 && !inclusive {
+        days_back = 7;
+    }
+
+    date.checked_sub_days(Days::new(days_back.into()))
+}
+
+/// Get the closest date after `date` that falls on the given weekday.
+fn next_weekday(date: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let days_forward =
+        match (7 + weekday.num_days_from_monday() - date.weekday().num_days_from_monday()) % 7 {
+            0 => 7,
+            days => days,
+        };
+
+    date.checked_add_days(Days::new(days_forward.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    // A Wednesday
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2015, 10, 21).unwrap()
+    }
+
+    #[test_case("2015-10-19", "2015-10-19"; "iso date")]
+    #[test_case("+3", "2015-10-24"; "days after shorthand")]
+    #[test_case("-3", "2015-10-18"; "days before shorthand")]
+    #[test_case("2015-W43", "2015-10-19"; "iso week")]
+    #[test_case("2015-w43-5", "2015-10-23"; "iso week with day")]
+    #[test_case("10/23/2015", "2015-10-23"; "numeric date")]
+    #[test_case("yesterday", "2015-10-20"; "yesterday")]
+    #[test_case("2 days ago", "2015-10-19"; "days ago")]
+    #[test_case("in a week", "2015-10-28"; "in a week")]
+    #[test_case("last week", "2015-10-14"; "last week")]
+    #[test_case("1 month ago", "2015-09-21"; "month ago")]
+    #[test_case("last month", "2015-09-21"; "last month")]
+    #[test_case("next year", "2016-10-21"; "next year")]
+    #[test_case("monday", "2015-10-19"; "bare weekday")]
+    #[test_case("Wednesday", "2015-10-21"; "bare weekday is today")]
+    #[test_case("friday", "2015-10-16"; "bare weekday is in the past")]
+    #[test_case("last wednesday", "2015-10-14"; "last weekday")]
+    #[test_case("next monday", "2015-10-26"; "next weekday")]
+    #[test_case("this friday", "2015-10-23"; "this weekday")]
+    #[test_case("2 weeks ago friday", "2015-10-09"; "weekday after relative date")]
+    #[test_case("last week mon", "2015-10-12"; "abbreviated weekday after relative date")]
+    #[test_case("start of week", "2015-10-19"; "start of week")]
+    #[test_case("start of the month", "2015-10-01"; "start of the month")]
+    #[test_case("start of last week", "2015-10-12"; "start of last week")]
+    #[test_case("start of next month", "2015-11-01"; "start of next month")]
+    #[test_case("April 1, 2015", "2015-04-01"; "falls back to chrono english")]
+    #[test_case("10/01/15", "2015-10-01"; "two digit years fall back to chrono english")]
+    fn parses_fuzzy_dates(input: &str, expected: &str) {
+        let date = parse_fuzzy_date(input, today(), DateDialect::Us).expect("could not parse date");
+
+        assert_eq!(
+            date,
+            NaiveDate::parse_from_str(expected, "%Y-%m-%d").unwrap()
+        );
+    }
+
+    #[test]
+    fn uk_dialect_puts_the_day_first() {
+        let date =
+            parse_fuzzy_date("21/10/2015", today(), DateDialect::Uk).expect("could not parse date");
+
+        assert_eq!(date, today());
+    }
+
+    #[test_case("21/10/2015", DateDialect::Us, "\"21/10/2015\" is not a valid date; numeric dates must be written as MM/DD/YYYY"; "wrong dialect")]
+    #[test_case("2 weeks ago frday", DateDialect::Us, "understood \"2 weeks ago\" as 2015-10-07, but not \"frday\" after it"; "trailing input")]
+    #[test_case("2015-W54", DateDialect::Us, "\"2015-W54\" is not a valid ISO week date, such as 2015-W43 or 2015-W43-3"; "bad iso week")]
+    #[test_case("3 hours ago", DateDialect::Us, "\"3 hours ago\" refers to a time of day, but only a date can be given"; "time based offset")]
+    #[test_case("whenever", DateDialect::Us, "could not understand \"whenever\" as a date"; "nonsense")]
+    fn errors_explain_what_was_understood(input: &str, dialect: DateDialect, expected: &str) {
+        let err = parse_fuzzy_date(input, today(), dialect).expect_err("should not have parsed");
+
+        assert_eq!(err.to_string(), expected);
+    }
+}
@@ -0,0 +1,414 @@
+use std::fmt::Write;
+use std::sync::LazyLock;
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use itertools::Itertools;
+use regex::{Captures, Regex};
+
+/// Matches the inline elements whose contents must not be treated as text: code spans,
+/// `[[wiki links]]` (optionally with a `|label`), and `[markdown](links)`.
+static INLINE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"`(?<code>[^`]+)`",
+        r"|\[\[(?<wiki_target>[^\]|]+)(?:\|(?<wiki_label>[^\]]+))?\]\]",
+        r"|\[(?<link_text>[^\]]+)\]\((?<link_url>[^)\s]+)\)",
+    ))
+    .expect("inline pattern is valid")
+});
+
+static STRONG_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\*\*(?<text>[^*]+)\*\*").expect("strong pattern is valid"));
+
+static EMPHASIS_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\*(?<text>[^*]+)\*").expect("emphasis pattern is valid"));
+
+const STYLESHEET: &str = concat!(
+    "body { font-family: sans-serif; max-width: 48em; margin: 2em auto; padding: 0 1em; line-height: 1.5; }\n",
+    "pre { background: #f4f4f4; padding: 1em; overflow-x: auto; }\n",
+    ".meta { color: #666; }\n",
+    ".missing-link { color: #a33; }\n",
+);
+
+/// Shown in place of the body of an encrypted note, which is never exported.
+pub const ENCRYPTED_BODY_HTML: &str = "<p class=\"meta\">This note is encrypted.</p>\n";
+
+/// A link to an exported page, as shown on the index and archive pages.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageLink {
+    pub title: String,
+    pub href: String,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+/// Render markdown as HTML.
+///
+/// Like [`crate::render_markdown`], this is not a complete markdown renderer. It handles headings,
+/// flat lists (including tasks), fenced code blocks, and paragraphs, along with inline code,
+/// emphasis and links. `[[wiki links]]` are passed to `resolve_wiki_link`, which gives the
+/// link's href, or None if the link cannot be resolved.
+pub fn markdown_to_html<F: Fn(&str) -> Option<String>>(text: &str, resolve_wiki_link: F) -> String {
+    let mut blocks = Vec::new();
+    let mut paragraph = Vec::new();
+    let mut list: Option<(&str, Vec<String>)> = None;
+    let mut code_block: Option<Vec<&str>> = None;
+
+    for line in text.lines() {
+        if let Some(code_lines) = &mut code_block {
+            if is_code_fence(line) {
+                let code = code_lines.iter().map(|line| escape(line)).join("\n");
+                blocks.push(format!("<pre><code>{code}</code></pre>"));
+                code_block = None;
+            } else {
+                code_lines.push(line);
+            }
+
+            continue;
+        }
+
+        let list_item = parse_list_item(line);
+        if list_item.is_none()
+            || list.as_ref().map(|(tag, _items)| *tag) != list_item.map(|item| item.0)
+        {
+            if let Some((tag, items)) = list.take() {
+                blocks.push(format!("<{tag}>\n{}\n</{tag}>", items.join("\n")));
+            }
+        }
+
+        if let Some((tag, item)) = list_item {
+            flush_paragraph(&mut blocks, &mut paragraph, &resolve_wiki_link);
+            let rendered_item = render_list_item(item, &resolve_wiki_link);
+            list.get_or_insert_with(|| (tag, Vec::new()))
+                .1
+                .push(rendered_item);
+        } else if is_code_fence(line) {
+            flush_paragraph(&mut blocks, &mut paragraph, &resolve_wiki_link);
+            code_block = Some(Vec::new());
+        } else if let Some((level, heading)) = parse_heading(line) {
+            flush_paragraph(&mut blocks, &mut paragraph, &resolve_wiki_link);
+            blocks.push(format!(
+                "<h{level}>{}</h{level}>",
+                render_inline(heading, &resolve_wiki_link)
+            ));
+        } else if line.trim().is_empty() {
+            flush_paragraph(&mut blocks, &mut paragraph, &resolve_wiki_link);
+        } else {
+            paragraph.push(line.trim());
+        }
+    }
+
+    flush_paragraph(&mut blocks, &mut paragraph, &resolve_wiki_link);
+    if let Some((tag, items)) = list {
+        blocks.push(format!("<{tag}>\n{}\n</{tag}>", items.join("\n")));
+    }
+    // An unterminated code block runs to the end of the note
+    if let Some(code_lines) = code_block {
+        let code = code_lines.iter().map(|line| escape(line)).join("\n");
+        blocks.push(format!("<pre><code>{code}</code></pre>"));
+    }
+
+    blocks.join("\n")
+}
+
+/// Build the page for a single note. `index_href` is the link back to the index page.
+pub fn note_page(
+    title: &str,
+    created_at: DateTime<FixedOffset>,
+    tags: &[String],
+    body_html: &str,
+    index_href: &str,
+) -> String {
+    let tags = tags
+        .iter()
+        .map(|tag| format!(" · #{}", escape(tag)))
+        .join("");
+    let content = format!(
+        "<nav><a href=\"{}\">All notes</a></nav>\n<h1>{}</h1>\n<p class=\"meta\">{}{tags}</p>\n{body_html}",
+        escape(index_href),
+        escape(title),
+        created_at.format("%Y-%m-%d %H:%M"),
+    );
+
+    page(title, &content)
+}
+
+/// Build the index page, which links to the given pages in the order they are given. If
+/// `archive_href` is given, the page links to the daily archive.
+pub fn index_page(links: &[PageLink], archive_href: Option<&str>) -> String {
+    let mut content = String::from("<h1>Notes</h1>\n");
+    if let Some(archive_href) = archive_href {
+        let _ = writeln!(
+            content,
+            "<nav><a href=\"{}\">Daily archive</a></nav>",
+            escape(archive_href)
+        );
+    }
+    content.push_str(&link_list(links));
+
+    page("Notes", &content)
+}
+
+/// Build the daily archive page, with a section for each of the given months.
+pub fn archive_page(months: &[(NaiveDate, Vec<PageLink>)], index_href: &str) -> String {
+    let mut content = format!(
+        "<nav><a href=\"{}\">All notes</a></nav>\n<h1>Daily archive</h1>\n",
+        escape(index_href)
+    );
+
+    for (month, links) in months {
+        let _ = writeln!(content, "<h2>{}</h2>", month.format("%B %Y"));
+        content.push_str(&link_list(links));
+    }
+
+    page("Daily archive", &content)
+}
+
+/// Escape text for use in HTML, both as element content and in quoted attributes.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn page(title: &str, content: &str) -> String {
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n",
+            "<html>\n",
+            "<head>\n",
+            "<meta charset=\"utf-8\">\n",
+            "<title>{}</title>\n",
+            "<style>\n{}</style>\n",
+            "</head>\n",
+            "<body>\n",
+            "{}\n",
+            "</body>\n",
+            "</html>\n",
+        ),
+        escape(title),
+        STYLESHEET,
+        content
+    )
+}
+
+fn link_list(links: &[PageLink]) -> String {
+    let items = links
+        .iter()
+        .map(|link| {
+            format!(
+                "<li><a href=\"{}\">{}</a> <span class=\"meta\">{}</span></li>",
+                escape(&link.href),
+                escape(&link.title),
+                link.created_at.format("%Y-%m-%d")
+            )
+        })
+        .join("\n");
+
+    format!("<ul>\n{items}\n</ul>\n")
+}
+
+fn flush_paragraph<F: Fn(&str) -> Option<String>>(
+    blocks: &mut Vec<String>,
+    paragraph: &mut Vec<&str>,
+    resolve_wiki_link: &F,
+) {
+    if paragraph.is_empty() {
+        return;
+    }
+
+    let text = paragraph.join("\n");
+    blocks.push(format!(
+        "<p>{}</p>",
+        render_inline(&text, resolve_wiki_link)
+    ));
+    paragraph.clear();
+}
+
+fn is_code_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let text = line[level..].strip_prefix(' ')?.trim();
+
+    Some((level, text))
+}
+
+/// Parse a list item, giving the tag of the list it belongs in and the item's text.
+fn parse_list_item(line: &str) -> Option<(&'static str, &str)> {
+    let content = line.trim_start();
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| content.strip_prefix(bullet))
+    {
+        return Some(("ul", item));
+    }
+
+    let (number, item) = content.split_once(". ")?;
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(("ol", item))
+}
+
+fn render_list_item<F: Fn(&str) -> Option<String>>(item: &str, resolve_wiki_link: &F) -> String {
+    let (checkbox, text) = if let Some(text) = item.strip_prefix("[ ] ") {
+        ("<input type=\"checkbox\" disabled> ", text)
+    } else if let Some(text) = item
+        .strip_prefix("[x] ")
+        .or_else(|| item.strip_prefix("[X] "))
+    {
+        ("<input type=\"checkbox\" checked disabled> ", text)
+    } else {
+        ("", item)
+    };
+
+    format!(
+        "<li>{checkbox}{}</li>",
+        render_inline(text, resolve_wiki_link)
+    )
+}
+
+fn render_inline<F: Fn(&str) -> Option<String>>(text: &str, resolve_wiki_link: &F) -> String {
+    let mut rendered = String::new();
+    let mut last_end = 0;
+
+    for captures in INLINE_PATTERN.captures_iter(text) {
+        let whole_match = captures.get(0).expect("capture group 0 is always present");
+        rendered.push_str(&render_text(&text[last_end..whole_match.start()]));
+        rendered.push_str(&render_inline_element(&captures, resolve_wiki_link));
+        last_end = whole_match.end();
+    }
+
+    rendered.push_str(&render_text(&text[last_end..]));
+
+    rendered
+}
+
+fn render_inline_element<F: Fn(&str) -> Option<String>>(
+    captures: &Captures,
+    resolve_wiki_link: &F,
+) -> String {
+    if let Some(code) = captures.name("code") {
+        return format!("<code>{}</code>", escape(code.as_str()));
+    }
+
+    if let Some(target) = captures.name("wiki_target") {
+        let target = target.as_str().trim();
+        let label = captures
+            .name("wiki_label")
+            .map_or(target, |label| label.as_str().trim());
+
+        return match resolve_wiki_link(target) {
+            Some(href) => format!("<a href=\"{}\">{}</a>", escape(&href), escape(label)),
+            None => format!("<span class=\"missing-link\">{}</span>", escape(label)),
+        };
+    }
+
+    let link_text = captures.name("link_text").map_or("", |text| text.as_str());
+    let link_url = captures.name("link_url").map_or("", |url| url.as_str());
+    if !is_safe_url(link_url) {
+        return render_text(link_text);
+    }
+
+    format!(
+        "<a href=\"{}\">{}</a>",
+        escape(link_url),
+        render_text(link_text)
+    )
+}
+
+/// Check if a link can be put in the exported site without running anything (e.g. a `javascript:`
+/// link). Only relative URLs and `http`, `https` and `mailto` URLs are allowed.
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters in the scheme, so they must be ignored
+    // here too
+    let url = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect::<String>();
+
+    let scheme_end = url.find([':', '/', '?', '#']);
+    match scheme_end {
+        Some(index) if url[index..].starts_with(':') => {
+            let scheme = url[..index].to_ascii_lowercase();
+
+            ["http", "https", "mailto"].contains(&scheme.as_str())
+        }
+        _ => true,
+    }
+}
+
+/// Render text that contains no inline elements other than emphasis.
+fn render_text(text: &str) -> String {
+    let escaped = escape(text);
+    let strong = STRONG_PATTERN.replace_all(&escaped, "<strong>$text</strong>");
+
+    EMPHASIS_PATTERN
+        .replace_all(&strong, "<em>$text</em>")
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn resolve(target: &str) -> Option<String> {
+        (target == "Flux Capacitor").then(|| "flux-capacitor.html".to_string())
+    }
+
+    #[test_case("# My Note", "<h1>My Note</h1>"; "heading")]
+    #[test_case("#hashtag", "<p>#hashtag</p>"; "hashtag is not a heading")]
+    #[test_case("a <b> & c", "<p>a &lt;b&gt; &amp; c</p>"; "text is escaped")]
+    #[test_case("some **bold** and *italic*", "<p>some <strong>bold</strong> and <em>italic</em></p>"; "emphasis")]
+    #[test_case("run `a <*b*>`", "<p>run <code>a &lt;*b*&gt;</code></p>"; "inline code is not formatted")]
+    #[test_case("see [the docs](https://example.com)", "<p>see <a href=\"https://example.com\">the docs</a></p>"; "markdown link")]
+    #[test_case("see [the notes](notes/flux-capacitor.html#design)", "<p>see <a href=\"notes/flux-capacitor.html#design\">the notes</a></p>"; "relative markdown link")]
+    #[test_case("[click](javascript:alert%281%29)", "<p>click</p>"; "javascript link is not linked")]
+    #[test_case("[click](JavaScript:void)", "<p>click</p>"; "uppercase javascript link is not linked")]
+    #[test_case("see [[Flux Capacitor]]", "<p>see <a href=\"flux-capacitor.html\">Flux Capacitor</a></p>"; "wiki link")]
+    #[test_case("see [[Flux Capacitor|the design]]", "<p>see <a href=\"flux-capacitor.html\">the design</a></p>"; "labelled wiki link")]
+    #[test_case("see [[Time Circuits]]", "<p>see <span class=\"missing-link\">Time Circuits</span></p>"; "unresolved wiki link")]
+    fn renders_inline_markdown(markdown: &str, expected: &str) {
+        assert_eq!(markdown_to_html(markdown, resolve), expected);
+    }
+
+    #[test]
+    fn renders_block_markdown() {
+        let markdown = [
+            "intro line",
+            "continues here",
+            "",
+            "- [ ] buy milk",
+            "- [x] call mom",
+            "1. first",
+            "2. second",
+            "```",
+            "# not a heading",
+            "```",
+            "after",
+        ]
+        .join("\n");
+
+        assert_eq!(
+            markdown_to_html(&markdown, resolve),
+            [
+                "<p>intro line\ncontinues here</p>",
+                "<ul>\n<li><input type=\"checkbox\" disabled> buy milk</li>\n<li><input type=\"checkbox\" checked disabled> call mom</li>\n</ul>",
+                "<ol>\n<li>first</li>\n<li>second</li>\n</ol>",
+                "<pre><code># not a heading</code></pre>",
+                "<p>after</p>",
+            ]
+            .join("\n")
+        );
+    }
+}
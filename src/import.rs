@@ -0,0 +1,428 @@
+use std::ffi::OsStr;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
+
+use crate::note::{self, Preamble, SerializeError};
+
+const FRONT_MATTER_DELIMITER: &str = "---";
+
+/// The front matter keys that are converted into the preamble. Any others are kept in the
+/// [`FRONT_MATTER_TABLE`] table.
+const KNOWN_KEYS: [&str; 9] = [
+    "title",
+    "created",
+    "created_at",
+    "date",
+    "updated",
+    "updated_at",
+    "modified",
+    "tags",
+    "tag",
+];
+
+/// The name of the preamble table that front matter quicknotes doesn't use is kept in.
+const FRONT_MATTER_TABLE: &str = "front_matter";
+
+/// The tool that a directory of notes being imported came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportSource {
+    /// An Obsidian vault, whose notes may have YAML front matter.
+    Obsidian,
+    /// A Joplin "Markdown + Front Matter" export.
+    JoplinMarkdown,
+    /// A folder of markdown files, without any front matter.
+    Plain,
+}
+
+/// A note from another tool, converted to have a quicknotes preamble.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConvertedNote {
+    pub preamble: Preamble,
+    /// Front matter that has no equivalent in the preamble, so that it isn't lost.
+    pub front_matter: toml::Table,
+    pub body: String,
+}
+
+impl ConvertedNote {
+    /// Get the contents of the note as it should be stored. Any front matter that quicknotes
+    /// doesn't use is kept in a `[front_matter]` table at the end of the preamble.
+    pub fn contents(&self) -> Result<String, SerializeError> {
+        let preamble = self.preamble.serialize()?;
+        if self.front_matter.is_empty() {
+            return Ok(format!("{preamble}\n\n{}", self.body));
+        }
+
+        let mut table = toml::Table::new();
+        table.insert(
+            FRONT_MATTER_TABLE.to_string(),
+            toml::Value::Table(self.front_matter.clone()),
+        );
+        let serialized_table =
+            toml::to_string(&table).expect("a table of strings must be serializable as TOML");
+        let unfenced_preamble = preamble
+            .strip_suffix(FRONT_MATTER_DELIMITER)
+            .expect("serialized preambles end with a fence");
+
+        Ok(format!(
+            "{unfenced_preamble}\n{serialized_table}{FRONT_MATTER_DELIMITER}\n\n{}",
+            self.body
+        ))
+    }
+}
+
+impl ImportSource {
+    /// Check if a file with the given extension should be imported.
+    #[must_use]
+    pub fn is_note_extension(self, extension: &OsStr) -> bool {
+        let extensions: &[&str] = match self {
+            Self::Obsidian | Self::JoplinMarkdown => &["md", "markdown"],
+            Self::Plain => &["md", "markdown", "txt"],
+        };
+
+        extensions
+            .iter()
+            .any(|note_extension| extension.eq_ignore_ascii_case(note_extension))
+    }
+
+    /// Check if the directory with the given name should be skipped over when importing. Hidden
+    /// directories (such as Obsidian's `.obsidian`) are always skipped.
+    #[must_use]
+    pub fn skips_directory(self, name: &OsStr) -> bool {
+        let name = name.to_string_lossy();
+
+        name.starts_with('.') || (self == Self::JoplinMarkdown && name == "_resources")
+    }
+
+    fn has_front_matter(self) -> bool {
+        match self {
+            Self::Obsidian | Self::JoplinMarkdown => true,
+            Self::Plain => false,
+        }
+    }
+}
+
+/// Convert a note from another tool. The title is taken from the front matter, the first heading,
+/// or the filename, in that order, and the creation time from the front matter, or else
+/// `modified_at`. Notes whose filename is a date (e.g. `2015-10-21`) are treated as daily notes,
+/// and have their `date` set.
+pub fn convert_note(
+    source: ImportSource,
+    file_stem: &str,
+    contents: &str,
+    modified_at: DateTime<FixedOffset>,
+) -> ConvertedNote {
+    let (front_matter, body) = if source.has_front_matter() {
+        split_front_matter(contents)
+    } else {
+        (Vec::new(), contents)
+    };
+    let body = body.trim_start_matches(['\n', '\r']);
+
+    let front_matter_value = |keys: &[&str]| {
+        keys.iter().find_map(|key| {
+            front_matter
+                .iter()
+                .find(|field| field.key.eq_ignore_ascii_case(key))
+        })
+    };
+
+    let title = front_matter_value(&["title"])
+        .and_then(|field| field.values.first())
+        .filter(|title| !title.is_empty())
+        .cloned()
+        .or_else(|| first_heading(body))
+        .unwrap_or_else(|| file_stem.to_string());
+
+    let timezone = modified_at.timezone();
+    let created_at = front_matter_value(&["created", "created_at", "date"])
+        .and_then(|field| field.values.first())
+        .and_then(|created| parse_datetime(created, timezone))
+        .unwrap_or(modified_at);
+
+    let mut preamble = Preamble::new(title, created_at);
+    preamble.date = note::date_for_filename_stem(file_stem);
+    preamble.updated_at = front_matter_value(&["updated", "updated_at", "modified"])
+        .and_then(|field| field.values.first())
+        .and_then(|updated| parse_datetime(updated, timezone));
+    preamble.tags = front_matter_value(&["tags", "tag"])
+        .map(|field| {
+            field
+                .values
+                .iter()
+                .flat_map(|value| value.split([',', ' ']))
+                .map(|tag| tag.trim().trim_start_matches('#'))
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let front_matter = front_matter
+        .into_iter()
+        .filter(|field| {
+            !KNOWN_KEYS
+                .iter()
+                .any(|known_key| field.key.eq_ignore_ascii_case(known_key))
+        })
+        .map(|field| {
+            let value = match <[String; 1]>::try_from(field.values) {
+                Ok([value]) if !field.is_list => toml::Value::String(value),
+                Ok(values) => toml::Value::Array(values.map(toml::Value::String).into()),
+                Err(values) => {
+                    toml::Value::Array(values.into_iter().map(toml::Value::String).collect())
+                }
+            };
+
+            (field.key.to_string(), value)
+        })
+        .collect();
+
+    ConvertedNote {
+        preamble,
+        front_matter,
+        body: body.to_string(),
+    }
+}
+
+/// A field from YAML front matter. Only the subset of YAML that these tools write is understood:
+/// `key: value`, `key: [a, b]`, and `key:` followed by `- value` lines.
+#[derive(Debug)]
+struct FrontMatterField<'a> {
+    key: &'a str,
+    values: Vec<String>,
+    /// Whether the values were given as a list, rather than a single `key: value`.
+    is_list: bool,
+}
+
+/// Split YAML front matter from the rest of a note. If the note has no front matter, there are no
+/// fields, and the body is the entire note.
+fn split_front_matter(contents: &str) -> (Vec<FrontMatterField<'_>>, &str) {
+    let mut lines = contents.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some(FRONT_MATTER_DELIMITER) {
+        return (Vec::new(), contents);
+    }
+
+    let mut fields: Vec<FrontMatterField> = Vec::new();
+    let mut offset = contents
+        .find('\n')
+        .map_or(contents.len(), |index| index + 1);
+    for line in lines {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == FRONT_MATTER_DELIMITER {
+            return (fields, &contents[offset..]);
+        }
+
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if let Some(field) = fields.last_mut() {
+                field.values.push(unquote(item));
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            let (values, is_list) = if let Some(list) = value
+                .strip_prefix('[')
+                .and_then(|value| value.strip_suffix(']'))
+            {
+                (list.split(',').map(unquote).collect(), true)
+            } else if value.is_empty() {
+                (Vec::new(), true)
+            } else {
+                (vec![unquote(value)], false)
+            };
+
+            fields.push(FrontMatterField {
+                key: key.trim(),
+                values,
+                is_list,
+            });
+        }
+    }
+
+    // There is no closing delimiter, so this was not front matter after all
+    (Vec::new(), contents)
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+        .unwrap_or(value)
+        .to_string()
+}
+
+fn first_heading(body: &str) -> Option<String> {
+    body.lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|heading| heading.trim().to_string())
+        .filter(|heading| !heading.is_empty())
+}
+
+/// Parse a timestamp as written by Obsidian or Joplin. Timestamps without an offset are taken to
+/// be in the given timezone.
+fn parse_datetime(value: &str, timezone: FixedOffset) -> Option<DateTime<FixedOffset>> {
+    let with_offset = value
+        .strip_suffix('Z')
+        .map_or_else(|| value.to_string(), |value| format!("{value}+00:00"));
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(&with_offset)
+        .or_else(|_err| DateTime::parse_from_str(&with_offset, "%Y-%m-%d %H:%M:%S%.f%:z"))
+    {
+        return Some(datetime);
+    }
+
+    let naive = [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .map(|date| date.and_time(chrono::NaiveTime::MIN))
+    })?;
+
+    timezone.from_local_datetime(&naive).single()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn modified_at() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(-7 * 60 * 60)
+            .unwrap()
+            .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+            .single()
+            .unwrap()
+    }
+
+    #[test]
+    fn obsidian_front_matter_is_converted() {
+        let contents = [
+            "---",
+            "created: 2015-10-20T16:29",
+            "tags:",
+            "  - time-travel",
+            "  - \"#inventions\"",
+            "---",
+            "",
+            "# Flux Capacitor",
+            "It's what makes time travel possible.",
+            "",
+        ]
+        .join("\n");
+
+        let converted = convert_note(
+            ImportSource::Obsidian,
+            "flux capacitor",
+            &contents,
+            modified_at(),
+        );
+
+        assert_eq!(converted.preamble.title, "Flux Capacitor");
+        assert_eq!(
+            converted.preamble.created_at,
+            modified_at() - chrono::TimeDelta::minutes(14 * 60 + 59)
+        );
+        assert_eq!(converted.preamble.tags, vec!["time-travel", "inventions"]);
+        assert_eq!(converted.preamble.date, None);
+        assert_eq!(
+            converted.body,
+            "# Flux Capacitor\nIt's what makes time travel possible.\n"
+        );
+    }
+
+    #[test]
+    fn joplin_front_matter_is_converted() {
+        let contents = [
+            "---",
+            "title: Flux Capacitor",
+            "updated: 2015-10-21 14:28:00Z",
+            "created: 2015-10-21 12:00:00Z",
+            "tags: [time-travel, inventions]",
+            "---",
+            "",
+            "It's what makes time travel possible.",
+        ]
+        .join("\n");
+
+        let converted = convert_note(
+            ImportSource::JoplinMarkdown,
+            "Flux Capacitor",
+            &contents,
+            modified_at(),
+        );
+
+        assert_eq!(converted.preamble.title, "Flux Capacitor");
+        assert_eq!(
+            converted.preamble.created_at,
+            modified_at() - chrono::TimeDelta::hours(2) - chrono::TimeDelta::minutes(28)
+        );
+        assert_eq!(converted.preamble.updated_at, Some(modified_at()));
+        assert_eq!(converted.preamble.tags, vec!["time-travel", "inventions"]);
+        assert_eq!(converted.body, "It's what makes time travel possible.");
+    }
+
+    #[test]
+    fn unknown_front_matter_is_kept() {
+        let contents = [
+            "---",
+            "title: Flux Capacitor",
+            "aliases: [flux, capacitor]",
+            "source: Doc Brown",
+            "---",
+            "It's what makes time travel possible.",
+        ]
+        .join("\n");
+
+        let converted = convert_note(
+            ImportSource::Obsidian,
+            "flux capacitor",
+            &contents,
+            modified_at(),
+        );
+
+        assert_eq!(
+            converted.contents().expect("could not serialize note"),
+            [
+                "---",
+                "title = \"Flux Capacitor\"",
+                "created_at = 2015-10-21T07:28:00-07:00",
+                "",
+                "[front_matter]",
+                "aliases = [\"flux\", \"capacitor\"]",
+                "source = \"Doc Brown\"",
+                "---",
+                "",
+                "It's what makes time travel possible.",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test_case(ImportSource::Plain, "---\ntitle: Not Parsed\n---\nhi"; "plain notes have no front matter")]
+    #[test_case(ImportSource::Obsidian, "---\ntitle: Not Parsed\nhi"; "unterminated front matter")]
+    fn text_that_is_not_front_matter_is_kept(source: ImportSource, contents: &str) {
+        let converted = convert_note(source, "2015-10-21", contents, modified_at());
+
+        assert_eq!(converted.preamble.title, "2015-10-21");
+        assert_eq!(converted.preamble.created_at, modified_at());
+        assert_eq!(
+            converted.preamble.date,
+            NaiveDate::from_ymd_opt(2015, 10, 21)
+        );
+        assert_eq!(converted.body, contents);
+    }
+}
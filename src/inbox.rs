@@ -0,0 +1,179 @@
+use std::ops::Range;
+
+use chrono::NaiveDateTime;
+
+const ENTRY_PREFIX: &str = "- [";
+const TIMESTAMP_END: &str = "] ";
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M";
+const CONTINUATION_INDENT: &str = "  ";
+
+/// A single thought captured into the inbox, such as `- [2015-10-21 07:28] buy milk`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InboxEntry {
+    pub captured_at: NaiveDateTime,
+    /// The captured text. Entries may span multiple lines.
+    pub text: String,
+}
+
+impl InboxEntry {
+    /// The first line of the entry's text.
+    #[must_use]
+    pub fn summary(&self) -> &str {
+        self.text.lines().next().unwrap_or_default()
+    }
+}
+
+/// Format an entry for appending to the inbox. Lines after the first are indented so that they
+/// stay part of the entry, and blank lines are dropped, as they would end it.
+pub fn format_entry(captured_at: NaiveDateTime, text: &str) -> String {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let first_line = lines.next().unwrap_or_default();
+    let mut formatted = format!(
+        "{ENTRY_PREFIX}{}{TIMESTAMP_END}{}\n",
+        captured_at.format(TIMESTAMP_FORMAT),
+        first_line.trim_end()
+    );
+
+    for line in lines {
+        formatted.push_str(CONTINUATION_INDENT);
+        formatted.push_str(line.trim_end());
+        formatted.push('\n');
+    }
+
+    formatted
+}
+
+/// Get the entries in the inbox's body, in the order they were written.
+pub fn entries(body: &str) -> Vec<InboxEntry> {
+    entries_with_lines(body)
+        .into_iter()
+        .map(|(_lines, entry)| entry)
+        .collect()
+}
+
+/// Remove the first entry equal to the given one from the inbox contents, leaving everything else
+/// as it was. Returns None if there is no such entry.
+pub fn remove_entry(contents: &str, entry: &InboxEntry) -> Option<String> {
+    let (lines, _entry) = entries_with_lines(contents)
+        .into_iter()
+        .find(|(_lines, candidate)| candidate == entry)?;
+
+    let remaining = contents
+        .split_inclusive('\n')
+        .enumerate()
+        .filter(|(index, _line)| !lines.contains(index))
+        .map(|(_index, line)| line)
+        .collect();
+
+    Some(remaining)
+}
+
+/// Get the entries in the given text, along with the (zero-indexed) lines they span.
+fn entries_with_lines(text: &str) -> Vec<(Range<usize>, InboxEntry)> {
+    let mut entries: Vec<(Range<usize>, InboxEntry)> = Vec::new();
+    let mut in_entry = false;
+
+    for (index, line) in text.lines().enumerate() {
+        if let Some(entry) = parse_entry_start(line) {
+            entries.push((index..index + 1, entry));
+            in_entry = true;
+            continue;
+        }
+
+        let continuation = line
+            .strip_prefix(CONTINUATION_INDENT)
+            .filter(|continuation| !continuation.trim().is_empty());
+
+        match (continuation, entries.last_mut()) {
+            (Some(continuation), Some((lines, entry))) if in_entry => {
+                lines.end = index + 1;
+                entry.text.push('\n');
+                entry.text.push_str(continuation.trim_end());
+            }
+            _ => in_entry = false,
+        }
+    }
+
+    entries
+}
+
+fn parse_entry_start(line: &str) -> Option<InboxEntry> {
+    let (timestamp, text) = line.strip_prefix(ENTRY_PREFIX)?.split_once(TIMESTAMP_END)?;
+    let captured_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+
+    Some(InboxEntry {
+        captured_at,
+        text: text.trim_end().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn captured_at(hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2015, 10, 21)
+            .unwrap()
+            .and_hms_opt(hour, 28, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn formatted_entries_can_be_read_back() {
+        let body = format!(
+            "\n{}{}",
+            format_entry(captured_at(7), "buy milk"),
+            format_entry(captured_at(8), "call mom\n\nabout the  \ndinner")
+        );
+
+        assert_eq!(
+            body,
+            "\n- [2015-10-21 07:28] buy milk\n- [2015-10-21 08:28] call mom\n  about the\n  dinner\n"
+        );
+        assert_eq!(
+            entries(&body),
+            vec![
+                InboxEntry {
+                    captured_at: captured_at(7),
+                    text: "buy milk".to_string()
+                },
+                InboxEntry {
+                    captured_at: captured_at(8),
+                    text: "call mom\nabout the\ndinner".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn text_between_entries_is_not_part_of_them() {
+        let body = "- [2015-10-21 07:28] buy milk\n\n  not part of it\n- [not a time] nope\n";
+
+        assert_eq!(
+            entries(body),
+            vec![InboxEntry {
+                captured_at: captured_at(7),
+                text: "buy milk".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn removing_an_entry_leaves_the_rest_of_the_inbox() {
+        let contents = "---\ntitle = \"Inbox\"\n---\n\n- [2015-10-21 07:28] buy milk\n- [2015-10-21 08:28] call mom\n  about dinner\n- [2015-10-21 09:28] walk the dog\n";
+        let entry = InboxEntry {
+            captured_at: captured_at(8),
+            text: "call mom\nabout dinner".to_string(),
+        };
+
+        let removed = remove_entry(contents, &entry).expect("entry was not found");
+
+        assert_eq!(
+            removed,
+            "---\ntitle = \"Inbox\"\n---\n\n- [2015-10-21 07:28] buy milk\n- [2015-10-21 09:28] walk the dog\n"
+        );
+        assert_eq!(remove_entry(&removed, &entry), None);
+    }
+}
@@ -1,18 +1,20 @@
 use std::collections::HashMap;
-use std::fs::OpenOptions;
-use std::io;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use itertools::Itertools;
 use rusqlite::{Connection, Params, Row, Statement};
 use rusqlite_migration::{Migrations, M};
+use serde_derive::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::note::Preamble;
+use crate::task::Task;
 use crate::warning;
 
 const DB_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+const DB_DAY_FORMAT: &str = "%Y-%m-%d";
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct IndexedNote {
@@ -20,10 +22,48 @@ pub struct IndexedNote {
     pub kind: NoteKind,
 }
 
+/// A task stored in the index, along with the note it belongs to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexedTask {
+    pub path: PathBuf,
+    pub note_title: String,
+    pub task: Task,
+}
+
+/// Information about how a note has been used, which is useful for ordering notes.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoteUsage {
+    pub last_opened_at: DateTime<Utc>,
+    pub open_count: u32,
+}
+
+impl NoteUsage {
+    /// A score combining how frequently and how recently the note has been opened. Higher
+    /// scores indicate notes that are more likely to be wanted.
+    #[must_use]
+    pub fn frecency(&self, now: DateTime<Utc>) -> f64 {
+        let age = now.signed_duration_since(self.last_opened_at);
+        let recency_weight = if age <= TimeDelta::hours(1) {
+            4.0
+        } else if age <= TimeDelta::days(1) {
+            2.0
+        } else if age <= TimeDelta::weeks(1) {
+            0.5
+        } else {
+            0.25
+        };
+
+        f64::from(self.open_count) * recency_weight
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum NoteKind {
     Note,
     Daily,
+    Weekly,
+    Monthly,
 }
 
 impl NoteKind {
@@ -31,6 +71,8 @@ impl NoteKind {
         match self {
             Self::Note => "note".to_string(),
             Self::Daily => "daily".to_string(),
+            Self::Weekly => "weekly".to_string(),
+            Self::Monthly => "monthly".to_string(),
         }
     }
 
@@ -38,6 +80,8 @@ impl NoteKind {
         match sql_enum {
             "note" => Ok(Self::Note),
             "daily" => Ok(Self::Daily),
+            "weekly" => Ok(Self::Weekly),
+            "monthly" => Ok(Self::Monthly),
             _ => Err(InvalidNoteKindString(sql_enum.to_owned())),
         }
     }
@@ -68,24 +112,22 @@ pub enum OpenError {
 #[error(transparent)]
 pub struct MigrationError(#[from] rusqlite_migration::Error);
 
-pub fn reset(path: &Path) -> Result<(), ResetError> {
-    OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(path)
-        .map(|_file| ())
-        .or_else(|err| {
-            if err.kind() == io::ErrorKind::NotFound {
-                Ok(())
-            } else {
-                Err(ResetError(err))
-            }
-        })
+/// Remove all notes from the index. Usage information is kept, so that it survives a reindex;
+/// see [`prune_usage`].
+pub fn reset(connection: &mut Connection) -> Result<(), ResetError> {
+    let transaction = connection.transaction()?;
+    transaction.execute("DELETE FROM note_tags;", [])?;
+    transaction.execute("DELETE FROM tasks;", [])?;
+    transaction.execute("DELETE FROM notes;", [])?;
+
+    transaction.commit()?;
+
+    Ok(())
 }
 
 #[derive(Error, Debug)]
 #[error("could not reset index database: {0}")]
-pub struct ResetError(io::Error);
+pub struct ResetError(#[from] rusqlite::Error);
 
 pub fn add_note(
     connection: &mut Connection,
@@ -97,14 +139,32 @@ pub fn add_note(
         .to_str()
         .ok_or_else(|| InsertError::BadPath(path.to_owned()))?;
 
-    connection
+    let transaction = connection
+        .transaction()
+        .map_err(InsertError::DatabaseError)?;
+
+    transaction
         .execute(
-            "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, ?5)
+            "INSERT INTO notes (
+                    filepath,
+                    title,
+                    created_at,
+                    utc_offset_seconds,
+                    kind,
+                    updated_at,
+                    updated_utc_offset_seconds,
+                    date,
+                    encrypted
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
                 ON CONFLICT(filepath) DO UPDATE SET
                     title=?2,
                     created_at=?3,
                     utc_offset_seconds=?4,
-                    kind=?5
+                    kind=?5,
+                    updated_at=?6,
+                    updated_utc_offset_seconds=?7,
+                    date=?8,
+                    encrypted=?9
             ;",
             (
                 &path_string,
@@ -112,10 +172,34 @@ pub fn add_note(
                 preamble.created_at.format(DB_DATE_FORMAT).to_string(),
                 preamble.created_at.offset().local_minus_utc(),
                 kind.to_sql_enum(),
+                preamble
+                    .updated_at
+                    .map(|updated_at| updated_at.format(DB_DATE_FORMAT).to_string()),
+                preamble
+                    .updated_at
+                    .map(|updated_at| updated_at.offset().local_minus_utc()),
+                preamble
+                    .date
+                    .map(|date| date.format(DB_DAY_FORMAT).to_string()),
+                preamble.encrypted,
             ),
         )
-        .map(|_rows| ())
-        .map_err(InsertError::DatabaseError)
+        .map_err(InsertError::DatabaseError)?;
+
+    transaction
+        .execute("DELETE FROM note_tags WHERE filepath = ?;", (&path_string,))
+        .map_err(InsertError::DatabaseError)?;
+
+    for tag in &preamble.tags {
+        transaction
+            .execute(
+                "INSERT OR IGNORE INTO note_tags VALUES (?1, ?2);",
+                (&path_string, tag),
+            )
+            .map_err(InsertError::DatabaseError)?;
+    }
+
+    transaction.commit().map_err(InsertError::DatabaseError)
 }
 
 #[derive(Error, Debug)]
@@ -130,21 +214,51 @@ pub enum InsertError {
 pub fn all_notes(
     connection: &mut Connection,
 ) -> Result<HashMap<PathBuf, IndexedNote>, LookupError> {
-    let mut query = connection
-        .prepare("SELECT filepath, title, created_at, utc_offset_seconds, kind FROM notes;")?;
+    let mut notes = lookup_notes(
+        &mut connection
+            .prepare("SELECT filepath, title, created_at, utc_offset_seconds, kind, updated_at, updated_utc_offset_seconds, date, encrypted FROM notes;")?,
+        [],
+    )?;
+
+    attach_tags(connection, &mut notes)?;
 
-    lookup_notes(&mut query, [])
+    Ok(notes)
 }
 
 pub fn notes_with_kind(
     connection: &mut Connection,
     kind: NoteKind,
 ) -> Result<HashMap<PathBuf, IndexedNote>, LookupError> {
-    let mut query = connection.prepare(
-        "SELECT filepath, title, created_at, utc_offset_seconds, kind FROM notes WHERE kind=?;",
+    let mut notes = lookup_notes(
+        &mut connection.prepare(
+            "SELECT filepath, title, created_at, utc_offset_seconds, kind, updated_at, updated_utc_offset_seconds, date, encrypted FROM notes WHERE kind=?;",
+        )?,
+        [kind.to_sql_enum()],
+    )?;
+
+    attach_tags(connection, &mut notes)?;
+
+    Ok(notes)
+}
+
+/// Get the kind of the note at the given path, if it is in the index.
+pub fn note_kind(
+    connection: &mut Connection,
+    path: &Path,
+) -> Result<Option<NoteKind>, LookupError> {
+    let Some(path_string) = path.to_str() else {
+        // Non-UTF-8 paths can't be inserted, so this can't be in the index
+        return Ok(None);
+    };
+
+    let notes = lookup_notes(
+        &mut connection.prepare(
+            "SELECT filepath, title, created_at, utc_offset_seconds, kind, updated_at, updated_utc_offset_seconds, date, encrypted FROM notes WHERE filepath=?;",
+        )?,
+        [path_string],
     )?;
 
-    lookup_notes(&mut query, [kind.to_sql_enum()])
+    Ok(notes.into_values().next().map(|note| note.kind))
 }
 
 fn lookup_notes<P: Params>(
@@ -168,17 +282,297 @@ fn lookup_notes<P: Params>(
     Ok(notes)
 }
 
+/// Fill in the tags for each of the given notes, which are stored separately from the notes
+/// themselves.
+fn attach_tags(
+    connection: &Connection,
+    notes: &mut HashMap<PathBuf, IndexedNote>,
+) -> Result<(), LookupError> {
+    let mut query = connection.prepare("SELECT filepath, tag FROM note_tags ORDER BY rowid;")?;
+    let tags = query.query_map([], |row| {
+        let raw_filepath: String = row.get(0)?;
+        let tag: String = row.get(1)?;
+
+        Ok((PathBuf::from(raw_filepath), tag))
+    })?;
+
+    for tag_res in tags {
+        let (path, tag) = tag_res?;
+        if let Some(note) = notes.get_mut(&path) {
+            note.preamble.tags.push(tag);
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Error, Debug)]
 #[error(transparent)]
 pub struct LookupError(#[from] rusqlite::Error);
 
+/// Replace all of the tasks stored for the note at the given path.
+pub fn replace_tasks(
+    connection: &mut Connection,
+    path: &Path,
+    tasks: &[Task],
+) -> Result<(), InsertError> {
+    let path_string = path
+        .to_str()
+        .ok_or_else(|| InsertError::BadPath(path.to_owned()))?;
+
+    let transaction = connection
+        .transaction()
+        .map_err(InsertError::DatabaseError)?;
+
+    transaction
+        .execute("DELETE FROM tasks WHERE filepath = ?;", (&path_string,))
+        .map_err(InsertError::DatabaseError)?;
+
+    for task in tasks {
+        transaction
+            .execute(
+                "INSERT INTO tasks VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+                (
+                    &path_string,
+                    task.line_number,
+                    &task.text,
+                    task.done,
+                    task.due.map(|due| due.format(DB_DAY_FORMAT).to_string()),
+                    // Tags can't contain whitespace, so they can be stored together
+                    task.tags.join(" "),
+                ),
+            )
+            .map_err(InsertError::DatabaseError)?;
+    }
+
+    transaction.commit().map_err(InsertError::DatabaseError)
+}
+
+/// Get every task in the index, ordered by the note they are in and their position in it.
+pub fn all_tasks(connection: &mut Connection) -> Result<Vec<IndexedTask>, LookupError> {
+    let mut query = connection.prepare(
+        "SELECT tasks.filepath, notes.title, line_number, text, done, due, tags
+            FROM tasks JOIN notes ON tasks.filepath = notes.filepath
+            ORDER BY tasks.filepath, line_number;",
+    )?;
+
+    let tasks = query
+        .query_map([], |row| {
+            let raw_filepath: String = row.get(0)?;
+            let raw_due: Option<String> = row.get(5)?;
+            let raw_tags: String = row.get(6)?;
+
+            Ok(IndexedTask {
+                path: PathBuf::from(raw_filepath),
+                note_title: row.get(1)?,
+                task: Task {
+                    line_number: row.get(2)?,
+                    text: row.get(3)?,
+                    done: row.get(4)?,
+                    // This was written by us, so an unparsable date is treated as no date at all
+                    due: raw_due
+                        .and_then(|due| NaiveDate::parse_from_str(&due, DB_DAY_FORMAT).ok()),
+                    tags: raw_tags.split_whitespace().map(str::to_string).collect(),
+                },
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(tasks)
+}
+
 pub fn delete_note(connection: &mut Connection, path: &Path) -> Result<(), DeleteError> {
     let path_string = path
         .to_str()
         .ok_or_else(|| DeleteError::BadPath(path.to_owned()))?;
 
-    connection
+    let transaction = connection
+        .transaction()
+        .map_err(DeleteError::DatabaseError)?;
+
+    transaction
+        .execute("DELETE FROM note_tags WHERE filepath = ?;", (&path_string,))
+        .map_err(DeleteError::DatabaseError)?;
+
+    transaction
+        .execute("DELETE FROM tasks WHERE filepath = ?;", (&path_string,))
+        .map_err(DeleteError::DatabaseError)?;
+
+    transaction
         .execute("DELETE FROM notes WHERE filepath = ?;", (&path_string,))
+        .map_err(DeleteError::DatabaseError)?;
+
+    transaction.commit().map_err(DeleteError::DatabaseError)
+}
+
+/// Record that the note at the given path was opened at the given time.
+pub fn record_open(
+    connection: &mut Connection,
+    path: &Path,
+    opened_at: DateTime<Utc>,
+) -> Result<(), InsertError> {
+    let path_string = path
+        .to_str()
+        .ok_or_else(|| InsertError::BadPath(path.to_owned()))?;
+
+    connection
+        .execute(
+            "INSERT INTO note_usage VALUES (?1, ?2, 1)
+                ON CONFLICT(filepath) DO UPDATE SET
+                    last_opened_at=?2,
+                    open_count=open_count + 1
+            ;",
+            (&path_string, opened_at.timestamp()),
+        )
+        .map(|_rows| ())
+        .map_err(InsertError::DatabaseError)
+}
+
+/// Get the usage information for all notes in the index that have been opened.
+pub fn note_usage(connection: &mut Connection) -> Result<HashMap<PathBuf, NoteUsage>, LookupError> {
+    let mut query = connection.prepare(
+        "SELECT note_usage.filepath, last_opened_at, open_count
+            FROM note_usage
+            JOIN notes ON notes.filepath = note_usage.filepath;",
+    )?;
+
+    let usage = query
+        .query_map([], |row| {
+            let raw_filepath: String = row.get(0)?;
+            let raw_last_opened_at: i64 = row.get(1)?;
+            let open_count: u32 = row.get(2)?;
+
+            Ok((PathBuf::from(raw_filepath), raw_last_opened_at, open_count))
+        })?
+        .filter_map_ok(|(path, raw_last_opened_at, open_count)| {
+            let Some(last_opened_at) = DateTime::from_timestamp(raw_last_opened_at, 0) else {
+                warning!("Invalid last opened time \"{raw_last_opened_at}\"; skipping entry");
+
+                return None;
+            };
+
+            Some((
+                path,
+                NoteUsage {
+                    last_opened_at,
+                    open_count,
+                },
+            ))
+        })
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+    Ok(usage)
+}
+
+/// Record that the file at `attachment_path` is attached to the note at `note_path`.
+pub fn add_attachment(
+    connection: &mut Connection,
+    note_path: &Path,
+    attachment_path: &Path,
+) -> Result<(), InsertError> {
+    let note_path_string = note_path
+        .to_str()
+        .ok_or_else(|| InsertError::BadPath(note_path.to_owned()))?;
+    let attachment_path_string = attachment_path
+        .to_str()
+        .ok_or_else(|| InsertError::BadPath(attachment_path.to_owned()))?;
+
+    connection
+        .execute(
+            "INSERT INTO attachments VALUES (?1, ?2)
+                ON CONFLICT(attachment_path) DO UPDATE SET filepath=?2
+            ;",
+            (&attachment_path_string, &note_path_string),
+        )
+        .map(|_rows| ())
+        .map_err(InsertError::DatabaseError)
+}
+
+/// Forget the file at `attachment_path`, such as when attaching it could not be finished.
+pub fn remove_attachment(
+    connection: &mut Connection,
+    attachment_path: &Path,
+) -> Result<(), DeleteError> {
+    let path_string = attachment_path
+        .to_str()
+        .ok_or_else(|| DeleteError::BadPath(attachment_path.to_owned()))?;
+
+    connection
+        .execute(
+            "DELETE FROM attachments WHERE attachment_path = ?;",
+            (&path_string,),
+        )
+        .map(|_rows| ())
+        .map_err(DeleteError::DatabaseError)
+}
+
+/// Get the paths of the files attached to the note at the given path, in the order they were
+/// attached.
+pub fn attachments(
+    connection: &mut Connection,
+    note_path: &Path,
+) -> Result<Vec<PathBuf>, LookupError> {
+    let mut query = connection
+        .prepare("SELECT attachment_path FROM attachments WHERE filepath = ? ORDER BY rowid;")?;
+
+    let attachments = query
+        .query_map((note_path.to_string_lossy(),), |row| {
+            let raw_attachment_path: String = row.get(0)?;
+
+            Ok(PathBuf::from(raw_attachment_path))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(attachments)
+}
+
+/// Move the attachments of the note at `from` to the note at `to`, such as when it is renamed.
+pub fn move_attachments(
+    connection: &mut Connection,
+    from: &Path,
+    to: &Path,
+) -> Result<(), InsertError> {
+    let from_string = from
+        .to_str()
+        .ok_or_else(|| InsertError::BadPath(from.to_owned()))?;
+    let to_string = to
+        .to_str()
+        .ok_or_else(|| InsertError::BadPath(to.to_owned()))?;
+
+    connection
+        .execute(
+            "UPDATE attachments SET filepath = ?2 WHERE filepath = ?1;",
+            (&from_string, &to_string),
+        )
+        .map(|_rows| ())
+        .map_err(InsertError::DatabaseError)
+}
+
+/// Forget the attachments of the note at the given path. The files themselves are left alone.
+pub fn delete_attachments(
+    connection: &mut Connection,
+    note_path: &Path,
+) -> Result<(), DeleteError> {
+    let path_string = note_path
+        .to_str()
+        .ok_or_else(|| DeleteError::BadPath(note_path.to_owned()))?;
+
+    connection
+        .execute(
+            "DELETE FROM attachments WHERE filepath = ?;",
+            (&path_string,),
+        )
+        .map(|_rows| ())
+        .map_err(DeleteError::DatabaseError)
+}
+
+/// Remove usage information for any notes which are no longer in the index.
+pub fn prune_usage(connection: &mut Connection) -> Result<(), DeleteError> {
+    connection
+        .execute(
+            "DELETE FROM note_usage WHERE filepath NOT IN (SELECT filepath FROM notes);",
+            [],
+        )
         .map(|_affected| ())
         .map_err(DeleteError::DatabaseError)
 }
@@ -204,17 +598,39 @@ fn unpack_row(row: &Row) -> Result<(PathBuf, IndexedNote), QueryFailure> {
     let raw_created_at: String = row.get(2)?;
     let raw_utc_offset: i32 = row.get(3)?;
     let raw_kind: String = row.get(4)?;
+    let raw_updated_at: Option<String> = row.get(5)?;
+    let raw_updated_utc_offset: Option<i32> = row.get(6)?;
+    let raw_date: Option<String> = row.get(7)?;
+    let encrypted: bool = row.get(8)?;
 
     let filepath = PathBuf::from_str(&raw_filepath).unwrap(); // infallible error type
     let created_at = datetime_from_database(&raw_created_at, raw_utc_offset)?;
     let kind = NoteKind::try_from_sql_enum(&raw_kind)
         .map_err(|err| QueryFailure::InvalidRow(err.to_string()))?;
+    let updated_at = raw_updated_at
+        .zip(raw_updated_utc_offset)
+        .map(|(timestamp, utc_offset)| datetime_from_database(&timestamp, utc_offset))
+        .transpose()?;
+    let date = raw_date
+        .map(|date| {
+            NaiveDate::parse_from_str(&date, DB_DAY_FORMAT)
+                .map_err(|err| QueryFailure::InvalidRow(format!("Invalid date \"{date}\", {err}")))
+        })
+        .transpose()?;
 
     Ok((
         filepath,
         IndexedNote {
             kind,
-            preamble: Preamble { title, created_at },
+            preamble: Preamble {
+                title,
+                created_at,
+                // Tags are stored separately, and must be filled in by the caller
+                tags: Vec::new(),
+                updated_at,
+                date,
+                encrypted,
+            },
         },
     ))
 }
@@ -283,6 +699,65 @@ fn migrations() -> Migrations<'static> {
             DROP TABLE intermediate_notes;
         ",
         ),
+        M::up(
+            "CREATE TABLE note_tags (
+                filepath TEXT NOT NULL REFERENCES notes(filepath) ON DELETE CASCADE,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (filepath, tag)
+            );",
+        ),
+        // Usage is deliberately not linked to the notes table, so that it is retained
+        // when the index is rebuilt.
+        M::up(
+            "CREATE TABLE note_usage (
+                filepath TEXT PRIMARY KEY,
+                last_opened_at INTEGER NOT NULL,
+                open_count INTEGER NOT NULL
+            );",
+        ),
+        M::up(
+            "ALTER TABLE notes ADD COLUMN updated_at DATETIME;
+            ALTER TABLE notes ADD COLUMN updated_utc_offset_seconds INTEGER;",
+        ),
+        // SQLite can't alter a CHECK constraint, so the table must be rebuilt to allow the
+        // periodic note kinds.
+        M::up(
+            r"
+            CREATE TABLE new_notes (
+                filepath TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                utc_offset_seconds INTEGER NOT NULL,
+                kind CHECK (kind IN ('note', 'daily', 'weekly', 'monthly')) NOT NULL,
+                updated_at DATETIME,
+                updated_utc_offset_seconds INTEGER
+            );
+            INSERT INTO new_notes SELECT * FROM notes;
+            DROP TABLE notes;
+            ALTER TABLE new_notes RENAME TO notes;
+        ",
+        ),
+        M::up(
+            "CREATE TABLE tasks (
+                filepath TEXT NOT NULL REFERENCES notes(filepath) ON DELETE CASCADE,
+                line_number INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                done INTEGER NOT NULL,
+                due DATE,
+                tags TEXT NOT NULL,
+                PRIMARY KEY (filepath, line_number)
+            );",
+        ),
+        M::up("ALTER TABLE notes ADD COLUMN date DATE;"),
+        M::up("ALTER TABLE notes ADD COLUMN encrypted INTEGER NOT NULL DEFAULT 0;"),
+        // Like usage, attachments are not linked to the notes table, as the index can be rebuilt
+        // from the notes, but there is no other record of which note a file was attached to.
+        M::up(
+            "CREATE TABLE attachments (
+                attachment_path TEXT PRIMARY KEY,
+                filepath TEXT NOT NULL
+            );",
+        ),
     ])
 }
 
@@ -292,6 +767,7 @@ mod tests {
     use std::str::FromStr;
 
     use chrono::{FixedOffset, TimeZone};
+    use test_case::test_case;
 
     use super::*;
 
@@ -314,6 +790,10 @@ mod tests {
                 .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
                 .single()
                 .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
         };
 
         add_note(
@@ -337,11 +817,15 @@ mod tests {
                 .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
                 .single()
                 .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
         };
 
         let preamble2 = Preamble {
             title: "Hello world!!".to_string(),
-            ..preamble1
+            ..preamble1.clone()
         };
 
         // insert the first note
@@ -381,6 +865,45 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn updated_at_is_stored_with_notes() {
+        let mut connection = Connection::open_in_memory().expect("could not open test database");
+        setup_database(&mut connection).expect("could not setup test database");
+
+        let offset = FixedOffset::east_opt(-7 * 60 * 60).unwrap();
+        let preamble = Preamble {
+            title: "Hello world".to_string(),
+            created_at: offset
+                .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                .single()
+                .unwrap(),
+            tags: vec![],
+            updated_at: Some(
+                offset
+                    .with_ymd_and_hms(2015, 10, 22, 9, 0, 0)
+                    .single()
+                    .unwrap(),
+            ),
+            date: None,
+            encrypted: false,
+        };
+
+        add_note(
+            &mut connection,
+            &preamble,
+            NoteKind::Note,
+            &PathBuf::from_str("/home/ferris/Documents/quicknotes/notes/hello-world.txt").unwrap(),
+        )
+        .unwrap();
+
+        let notes = all_notes(&mut connection).expect("Failed to query notes");
+        let stored =
+            &notes[&PathBuf::from_str("/home/ferris/Documents/quicknotes/notes/hello-world.txt")
+                .unwrap()];
+
+        assert_eq!(stored.preamble.updated_at, preamble.updated_at);
+    }
+
     #[test]
     pub fn cannot_insert_note_with_invalid_utf8_path() {
         let mut connection = Connection::open_in_memory().expect("could not open test database");
@@ -392,6 +915,10 @@ mod tests {
                 .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
                 .single()
                 .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
         };
 
         // construct an invalid path (this is platform dependent)
@@ -430,6 +957,10 @@ mod tests {
                 .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
                 .single()
                 .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
         };
 
         add_note(
@@ -447,6 +978,10 @@ mod tests {
                 .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
                 .single()
                 .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
         };
 
         add_note(
@@ -495,6 +1030,10 @@ mod tests {
                 .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
                 .single()
                 .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
         };
 
         add_note(
@@ -508,7 +1047,7 @@ mod tests {
 
         connection
             .execute(
-                r#"INSERT INTO notes VALUES (
+                r#"INSERT INTO notes (filepath, title, created_at, utc_offset_seconds, kind) VALUES (
                     "/home/ferris/Documents/quicknotes/notes/this-note-is-not-valid.txt",
                     "This note is not valid",
                     "malformed timestamp",
@@ -550,6 +1089,10 @@ mod tests {
                 .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
                 .single()
                 .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
         };
 
         add_note(
@@ -567,6 +1110,10 @@ mod tests {
                 .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
                 .single()
                 .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
         };
 
         add_note(
@@ -591,6 +1138,276 @@ mod tests {
         assert_eq!(notes.into_iter().collect::<Vec<_>>(), vec![expected_entry]);
     }
 
+    #[test_case(NoteKind::Weekly, "/home/ferris/Documents/quicknotes/weekly/2015-W43.txt"; "weekly")]
+    #[test_case(NoteKind::Monthly, "/home/ferris/Documents/quicknotes/monthly/2015-10.txt"; "monthly")]
+    pub fn can_store_periodic_notes(kind: NoteKind, path: &str) {
+        let mut connection = Connection::open_in_memory().expect("could not open test database");
+        setup_database(&mut connection).expect("could not setup test database");
+
+        let preamble = Preamble::new(
+            "2015-10".to_string(),
+            FixedOffset::east_opt(-7 * 60 * 60)
+                .unwrap()
+                .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                .single()
+                .unwrap(),
+        );
+
+        add_note(
+            &mut connection,
+            &preamble,
+            kind,
+            &PathBuf::from_str(path).unwrap(),
+        )
+        .expect("Failed to insert note");
+
+        let notes = notes_with_kind(&mut connection, kind).expect("Failed to query notes");
+
+        assert_eq!(
+            notes.into_iter().collect::<Vec<_>>(),
+            vec![(
+                PathBuf::from_str(path).unwrap(),
+                IndexedNote { preamble, kind }
+            )]
+        );
+    }
+
+    #[test]
+    pub fn tags_are_stored_with_notes() {
+        let mut connection = Connection::open_in_memory().expect("could not open test database");
+        setup_database(&mut connection).expect("could not setup test database");
+
+        let preamble = Preamble {
+            title: "Hello world".to_string(),
+            created_at: FixedOffset::east_opt(-7 * 60 * 60)
+                .unwrap()
+                .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                .single()
+                .unwrap(),
+            tags: vec!["work".to_string(), "ideas".to_string()],
+            updated_at: None,
+            date: None,
+            encrypted: false,
+        };
+
+        let path =
+            PathBuf::from_str("/home/ferris/Documents/quicknotes/notes/hello-world.txt").unwrap();
+
+        add_note(&mut connection, &preamble, NoteKind::Note, &path).unwrap();
+
+        let notes = all_notes(&mut connection).expect("Failed to query notes");
+
+        assert_eq!(
+            notes.get(&path).map(|note| &note.preamble.tags),
+            Some(&vec!["work".to_string(), "ideas".to_string()])
+        );
+    }
+
+    #[test]
+    pub fn reinserting_a_note_replaces_its_tags() {
+        let mut connection = Connection::open_in_memory().expect("could not open test database");
+        setup_database(&mut connection).expect("could not setup test database");
+
+        let preamble1 = Preamble {
+            title: "Hello world".to_string(),
+            created_at: FixedOffset::east_opt(-7 * 60 * 60)
+                .unwrap()
+                .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                .single()
+                .unwrap(),
+            tags: vec!["work".to_string()],
+            updated_at: None,
+            date: None,
+            encrypted: false,
+        };
+
+        let preamble2 = Preamble {
+            tags: vec!["play".to_string()],
+            ..preamble1.clone()
+        };
+
+        let path =
+            PathBuf::from_str("/home/ferris/Documents/quicknotes/notes/hello-world.txt").unwrap();
+
+        add_note(&mut connection, &preamble1, NoteKind::Note, &path).unwrap();
+        add_note(&mut connection, &preamble2, NoteKind::Note, &path).unwrap();
+
+        let notes =
+            notes_with_kind(&mut connection, NoteKind::Note).expect("Failed to query notes");
+
+        assert_eq!(
+            notes.get(&path).map(|note| &note.preamble.tags),
+            Some(&vec!["play".to_string()])
+        );
+    }
+
+    #[test]
+    pub fn recording_opens_tracks_count_and_last_opened_time() {
+        let mut connection = Connection::open_in_memory().expect("could not open test database");
+        setup_database(&mut connection).expect("could not setup test database");
+
+        let preamble = Preamble {
+            title: "Hello world".to_string(),
+            created_at: FixedOffset::east_opt(-7 * 60 * 60)
+                .unwrap()
+                .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                .single()
+                .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
+        };
+
+        let path =
+            PathBuf::from_str("/home/ferris/Documents/quicknotes/notes/hello-world.txt").unwrap();
+        add_note(&mut connection, &preamble, NoteKind::Note, &path).unwrap();
+
+        let first_open = Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap();
+        let second_open = Utc.with_ymd_and_hms(2015, 10, 22, 7, 28, 0).unwrap();
+        record_open(&mut connection, &path, first_open).unwrap();
+        record_open(&mut connection, &path, second_open).unwrap();
+
+        let usage = note_usage(&mut connection).expect("Failed to query usage");
+
+        assert_eq!(
+            usage.get(&path),
+            Some(&NoteUsage {
+                last_opened_at: second_open,
+                open_count: 2,
+            })
+        );
+    }
+
+    #[test]
+    pub fn usage_survives_reset_until_pruned() {
+        let mut connection = Connection::open_in_memory().expect("could not open test database");
+        setup_database(&mut connection).expect("could not setup test database");
+
+        let preamble = Preamble {
+            title: "Hello world".to_string(),
+            created_at: FixedOffset::east_opt(-7 * 60 * 60)
+                .unwrap()
+                .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                .single()
+                .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
+        };
+
+        let path =
+            PathBuf::from_str("/home/ferris/Documents/quicknotes/notes/hello-world.txt").unwrap();
+        add_note(&mut connection, &preamble, NoteKind::Note, &path).unwrap();
+        record_open(&mut connection, &path, Utc::now()).unwrap();
+
+        reset(&mut connection).expect("could not reset index");
+        add_note(&mut connection, &preamble, NoteKind::Note, &path).unwrap();
+        prune_usage(&mut connection).expect("could not prune usage");
+
+        assert!(note_usage(&mut connection).unwrap().contains_key(&path));
+
+        reset(&mut connection).expect("could not reset index");
+        prune_usage(&mut connection).expect("could not prune usage");
+        add_note(&mut connection, &preamble, NoteKind::Note, &path).unwrap();
+
+        assert!(note_usage(&mut connection).unwrap().is_empty());
+    }
+
+    #[test]
+    pub fn recently_opened_notes_have_higher_frecency() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap();
+        let recent = NoteUsage {
+            last_opened_at: now - TimeDelta::minutes(5),
+            open_count: 2,
+        };
+
+        let old = NoteUsage {
+            last_opened_at: now - TimeDelta::weeks(3),
+            open_count: 20,
+        };
+
+        assert!(recent.frecency(now) > old.frecency(now));
+    }
+
+    #[test]
+    pub fn tasks_are_stored_with_their_note_title() {
+        let mut connection = Connection::open_in_memory().expect("could not open test database");
+        setup_database(&mut connection).expect("could not setup test database");
+
+        let path =
+            PathBuf::from_str("/home/ferris/Documents/quicknotes/notes/hello-world.txt").unwrap();
+        let preamble = Preamble::new(
+            "Hello world".to_string(),
+            FixedOffset::east_opt(-7 * 60 * 60)
+                .unwrap()
+                .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                .single()
+                .unwrap(),
+        );
+        let task = Task {
+            line_number: 5,
+            text: "buy milk due:2015-10-22 @errands".to_string(),
+            done: false,
+            due: NaiveDate::from_ymd_opt(2015, 10, 22),
+            tags: vec!["errands".to_string()],
+        };
+
+        add_note(&mut connection, &preamble, NoteKind::Note, &path).unwrap();
+        replace_tasks(&mut connection, &path, std::slice::from_ref(&task))
+            .expect("could not insert tasks");
+
+        let tasks = all_tasks(&mut connection).expect("Failed to query tasks");
+
+        assert_eq!(
+            tasks,
+            vec![IndexedTask {
+                path: path.clone(),
+                note_title: "Hello world".to_string(),
+                task
+            }]
+        );
+
+        delete_note(&mut connection, &path).unwrap();
+
+        assert_eq!(all_tasks(&mut connection).unwrap(), vec![]);
+    }
+
+    #[test]
+    pub fn attachments_survive_reset_and_follow_renames() {
+        let mut connection = Connection::open_in_memory().expect("could not open test database");
+        setup_database(&mut connection).expect("could not setup test database");
+
+        let note_path = Path::new("/home/ferris/Documents/quicknotes/notes/incident.txt");
+        let renamed_path = Path::new("/home/ferris/Documents/quicknotes/notes/outage.txt");
+        let attachment_paths = [
+            PathBuf::from("/home/ferris/Documents/quicknotes/attachments/incident/screenshot.png"),
+            PathBuf::from("/home/ferris/Documents/quicknotes/attachments/incident/server.log"),
+        ];
+        for attachment_path in &attachment_paths {
+            add_attachment(&mut connection, note_path, attachment_path)
+                .expect("could not add attachment");
+        }
+
+        reset(&mut connection).expect("could not reset index");
+        move_attachments(&mut connection, note_path, renamed_path)
+            .expect("could not move attachments");
+
+        assert!(attachments(&mut connection, note_path)
+            .expect("could not look up attachments")
+            .is_empty());
+        assert_eq!(
+            attachments(&mut connection, renamed_path).expect("could not look up attachments"),
+            attachment_paths
+        );
+
+        delete_attachments(&mut connection, renamed_path).expect("could not delete attachments");
+        assert!(attachments(&mut connection, renamed_path)
+            .expect("could not look up attachments")
+            .is_empty());
+    }
+
     #[test]
     pub fn delete_note_is_idempotent() {
         let mut connection = Connection::open_in_memory().expect("could not open test database");
@@ -607,7 +1424,7 @@ mod tests {
 
         connection
             .execute(
-                r#"INSERT INTO notes VALUES (
+                r#"INSERT INTO notes (filepath, title, created_at, utc_offset_seconds, kind) VALUES (
                     "/home/ferris/Documents/quicknotes/notes/my-cool-note.txt",
                     "Hello, world!",
                     "2015-10-22T07:28:00.000",
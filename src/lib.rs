@@ -1,31 +1,74 @@
 #![warn(clippy::all, clippy::pedantic)]
 #![allow(clippy::enum_variant_names)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::{self, File, OpenOptions};
 use std::io;
-use std::path::{Path, PathBuf};
+use std::ops::Bound;
+use std::path::{Component, Path, PathBuf};
 
-use chrono::{DateTime, NaiveDate, TimeZone};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use draft::DraftGuard;
 use index::{LookupError as IndexLookupError, OpenError as IndexOpenError};
 use io::Write;
+use itertools::Itertools;
 use note::{Preamble, SerializeError};
 use rusqlite::Connection;
 use storage::{
-    store_if_different, StoreIfDifferentError, StoreNote, StoreNoteAt, StoreNoteIn, TempFileHandle,
+    store_if_different, Recovery, StoreIfDifferentError, StoreNote, StoreNoteAt, StoreNoteError,
+    StoreNoteIn, StoreNoteInBackend, StoreNoteOver, TempFileHandle,
 };
 use tempfile::{Builder as TempFileBuilder, NamedTempFile, TempPath};
 use thiserror::Error;
-use walkdir::{DirEntry, WalkDir};
+use walkdir::WalkDir;
 
-pub use edit::{CommandEditor, Editor};
-pub use index::{IndexedNote, NoteKind};
+pub use backend::{
+    create_unclobbered, Backend, FilesystemBackend, OpenSqliteBackendError, SqliteBackend,
+};
+pub use draft::Draft;
+pub use edit::{CommandEditor, EditOutcome, Editor, Position};
+pub use fuzzy_date::{parse_fuzzy_date, DateDialect, FuzzyDateError};
+pub use import::ImportSource;
+pub use inbox::InboxEntry;
+pub use index::{IndexedNote, IndexedTask, NoteKind, NoteUsage};
 pub use note::Preamble as NotePreamble;
+pub use period::Period;
+pub use recovery::RecoverableNote;
+pub use render::{render_calendar, render_markdown};
+pub use task::Task;
 
+mod backend;
+mod bundle;
+mod crypt;
+mod draft;
 mod edit;
+mod fuzzy_date;
+mod html;
+mod import;
+mod inbox;
 mod index;
 mod note;
+mod period;
+mod recovery;
+mod render;
+mod sidecar;
 mod storage;
+mod task;
+
+/// The default format for the filenames and titles of daily notes.
+pub const DEFAULT_DAILY_FORMAT: &str = "%Y-%m-%d";
+
+/// The default filename of the inbox note, relative to the root directory.
+pub const DEFAULT_INBOX_FILENAME: &str = "inbox";
+
+const INBOX_TITLE: &str = "Inbox";
+
+/// The prefix of the tempfiles that notes are written to before they are stored. This is the
+/// `tempfile` default, but is set explicitly so that preserved notes can be found by name.
+const TEMPFILE_PREFIX: &str = ".tmp";
+
+/// The prefix of the files that new notes are written to in the drafts directory.
+const DRAFT_PREFIX: &str = "draft-";
 
 macro_rules! warning {
         ($($arg:tt)*) => {{
@@ -38,10 +81,75 @@ macro_rules! warning {
 
 pub(crate) use warning;
 
+/// The contents of a note, split into its preamble and everything after it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteContents {
+    pub preamble: Preamble,
+    pub body: String,
+}
+
 pub struct NoteConfig {
     pub root_dir: PathBuf,
     pub file_extension: String,
     pub temp_root_override: Option<PathBuf>,
+    /// How numeric dates given to [`parse_fuzzy_date`] are read.
+    pub date_dialect: DateDialect,
+    pub daily: DailyConfig,
+    pub inbox: InboxConfig,
+}
+
+/// Settings that only apply to daily notes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DailyConfig {
+    /// When creating a daily note, copy the unchecked tasks from the most recent daily note
+    /// before it into the new note.
+    pub carry_over_tasks: bool,
+    /// The strftime format used for the path of a daily note, relative to the daily directory
+    /// and without an extension. `/` can be used to store notes in subdirectories, such as
+    /// `%Y/%m/%d`.
+    pub filename_format: String,
+    /// The strftime format used for the title of a new daily note.
+    pub title_format: String,
+    /// The time of day at which the next day's daily note begins. For instance, if this is 04:00,
+    /// notes written at 1am are still part of the previous day.
+    pub day_starts_at: NaiveTime,
+}
+
+/// Settings for the inbox that [`capture`] adds entries to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InboxConfig {
+    /// The path of the inbox note, relative to the root directory and without an extension.
+    pub filename: String,
+}
+
+impl Default for InboxConfig {
+    fn default() -> Self {
+        Self {
+            filename: DEFAULT_INBOX_FILENAME.to_string(),
+        }
+    }
+}
+
+impl DailyConfig {
+    /// Get the date of the daily note that is current at the given time, taking
+    /// [`day_starts_at`](`DailyConfig::day_starts_at`) into account.
+    #[must_use]
+    pub fn date_at<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> NaiveDate {
+        let since_day_start = self.day_starts_at - NaiveTime::MIN;
+
+        (time.naive_local() - since_day_start).date()
+    }
+}
+
+impl Default for DailyConfig {
+    fn default() -> Self {
+        Self {
+            carry_over_tasks: false,
+            filename_format: DEFAULT_DAILY_FORMAT.to_string(),
+            title_format: DEFAULT_DAILY_FORMAT.to_string(),
+            day_starts_at: NaiveTime::MIN,
+        }
+    }
 }
 
 impl NoteConfig {
@@ -52,7 +160,58 @@ impl NoteConfig {
 
     #[must_use]
     pub fn daily_directory_path(&self) -> PathBuf {
-        self.root_dir.join(Path::new("daily"))
+        self.periodic_directory_path(Period::Daily)
+    }
+
+    #[must_use]
+    pub fn weekly_directory_path(&self) -> PathBuf {
+        self.periodic_directory_path(Period::Weekly)
+    }
+
+    #[must_use]
+    pub fn monthly_directory_path(&self) -> PathBuf {
+        self.periodic_directory_path(Period::Monthly)
+    }
+
+    #[must_use]
+    pub fn periodic_directory_path(&self, period: Period) -> PathBuf {
+        self.root_dir.join(Path::new(period.directory_name()))
+    }
+
+    /// The directory that notes of the given kind are stored in.
+    #[must_use]
+    pub fn kind_directory_path(&self, kind: NoteKind) -> PathBuf {
+        match kind {
+            NoteKind::Note => self.notes_directory_path(),
+            NoteKind::Daily => self.periodic_directory_path(Period::Daily),
+            NoteKind::Weekly => self.periodic_directory_path(Period::Weekly),
+            NoteKind::Monthly => self.periodic_directory_path(Period::Monthly),
+        }
+    }
+
+    #[must_use]
+    pub fn inbox_path(&self) -> PathBuf {
+        self.root_dir
+            .join(format!("{}.{}", self.inbox.filename, self.file_extension))
+    }
+
+    /// The directory that new notes are written to while they are being edited. See
+    /// [`abandoned_drafts`].
+    #[must_use]
+    pub fn drafts_directory_path(&self) -> PathBuf {
+        self.root_dir.join(Path::new("drafts"))
+    }
+
+    /// The directory that files attached to notes are copied into. See [`attach_file`].
+    #[must_use]
+    pub fn attachments_directory_path(&self) -> PathBuf {
+        self.root_dir.join(Path::new("attachments"))
+    }
+
+    /// The directory that notes are moved to if they cannot be stored. See [`recover_note`].
+    #[must_use]
+    pub fn recovery_directory_path(&self) -> PathBuf {
+        self.root_dir.join(Path::new("recovery"))
     }
 
     #[must_use]
@@ -71,22 +230,63 @@ impl NoteConfig {
 /// # Errors
 ///
 /// Returns an error if there is an I/O failure creating the note, the editor fails to launch, or
-/// if there is a problem adding the note to the index.
+/// if there is a problem adding the note to the index. If the editor exits unsuccessfully, the
+/// note is discarded and an error is returned.
 pub fn make_note<E: Editor, Tz: TimeZone>(
     config: &NoteConfig,
     editor: E,
     title: String,
     creation_time: &DateTime<Tz>,
 ) -> Result<Option<PathBuf>, MakeNoteError> {
-    let filename_stem = note::filename_stem_for_title(&title);
-    let store = StoreNoteIn {
-        storage_directory: config.notes_directory_path(),
-        preferred_file_stem: filename_stem,
-        file_extension: config.file_extension.clone(),
-    };
+    let store = notes_directory_store(config, &title);
+
+    let maybe_written_path = make_note_with_store(
+        &FilesystemBackend::default(),
+        config,
+        store,
+        editor,
+        &Preamble::new(title, creation_time.fixed_offset()),
+        "",
+        NoteKind::Note,
+        CursorPlacement::Start,
+        None,
+    )?;
+
+    Ok(maybe_written_path)
+}
+
+/// Create a new note whose body is encrypted with the given passphrase.
+///
+/// This behaves like [`make_note`], but the note is marked `encrypted = true`. The body is written
+/// to the editor in plain text, and encrypted before the note is stored; only the preamble is
+/// ever added to the index.
+///
+/// # Errors
+///
+/// See [`make_note`]. An error is also returned if the note could not be encrypted, in which case
+/// it is left where it was edited.
+pub fn make_encrypted_note<E: Editor, Tz: TimeZone>(
+    config: &NoteConfig,
+    editor: E,
+    passphrase: &str,
+    title: String,
+    creation_time: &DateTime<Tz>,
+) -> Result<Option<PathBuf>, MakeNoteError> {
+    let store = notes_directory_store(config, &title);
+    let mut preamble = Preamble::new(title, creation_time.fixed_offset());
+    preamble.encrypted = true;
 
-    let maybe_written_path =
-        make_note_with_store(config, store, editor, title, creation_time, NoteKind::Note)?;
+    let maybe_written_path = make_note_with_store(
+        &FilesystemBackend::default(),
+        config,
+        store,
+        editor,
+        &preamble,
+        "",
+        NoteKind::Note,
+        CursorPlacement::Start,
+        Some(passphrase),
+    )?;
 
     Ok(maybe_written_path)
 }
@@ -100,29 +300,81 @@ pub struct MakeNoteError {
     inner: MakeNoteAtError,
 }
 
+/// Create a new note, and store it in the given backend.
+///
+/// This behaves like [`make_note`], but the note is stored through `backend` rather than in a file
+/// of its own, e.g. to keep a whole vault in a [`SqliteBackend`]. The note is still written in the
+/// editor through a temporary file, and added to the index.
+///
+/// # Errors
+///
+/// See [`make_note`].
+pub fn make_note_in<E: Editor, Tz: TimeZone>(
+    backend: &dyn Backend,
+    config: &NoteConfig,
+    editor: E,
+    title: String,
+    creation_time: &DateTime<Tz>,
+) -> Result<Option<PathBuf>, MakeNoteError> {
+    let store = StoreNoteInBackend {
+        backend,
+        destination: notes_directory_store(config, &title).destination(),
+    };
+
+    let maybe_written_path = make_note_with_store(
+        backend,
+        config,
+        store,
+        editor,
+        &Preamble::new(title, creation_time.fixed_offset()),
+        "",
+        NoteKind::Note,
+        CursorPlacement::Start,
+        None,
+    )?;
+
+    Ok(maybe_written_path)
+}
+
 /// Create or open a daily note for the given date.
 ///
+/// This is equivalent to calling [`make_or_open_periodic`] with [`Period::Daily`].
+///
+/// # Errors
+///
+/// See [`make_or_open_periodic`].
+pub fn make_or_open_daily<E: Editor, Tz: TimeZone>(
+    config: &NoteConfig,
+    editor: E,
+    for_day: NaiveDate,
+    creation_time: &DateTime<Tz>,
+) -> Result<Option<PathBuf>, MakeOrOpenPeriodicNoteError> {
+    make_or_open_periodic(config, editor, Period::Daily, for_day, creation_time)
+}
+
+/// Create or open the periodic note (e.g. the weekly note) that covers the given date.
+///
 /// This operates very similarly to [`make_note`], but the title of the note will be the
-/// date part of the creation time. If one already exists, it will be opened instead of
-/// creating a new one.
+/// period containing `for_day` (e.g. `2025-02-05`, `2025-W06` or `2025-02`). If one already
+/// exists, it will be opened instead of creating a new one. The filename and title of daily
+/// notes can be changed with [`DailyConfig`].
 ///
 /// Returns the path of the note, or None if nothing was written to the note.
 ///
 /// # Errors
 ///
-/// Returns an error if there is an I/O failure creating the note, the editor fails to launch, or
-/// if there is a problem adding the note to the index.
-pub fn make_or_open_daily<E: Editor, Tz: TimeZone>(
+/// Returns an error if the configured daily note formats are invalid, there is an I/O failure
+/// creating the note, the editor fails to launch, or if there is a problem adding the note to the
+/// index. If the editor exits unsuccessfully, the note is discarded and an error is returned.
+pub fn make_or_open_periodic<E: Editor, Tz: TimeZone>(
     config: &NoteConfig,
     editor: E,
+    period: Period,
     for_day: NaiveDate,
     creation_time: &DateTime<Tz>,
-) -> Result<Option<PathBuf>, MakeOrOpenDailyNoteError> {
-    let filename_stem = note::filename_stem_for_date(for_day);
-    let destination_path = config
-        .daily_directory_path()
-        .join(filename_stem)
-        .with_extension(&config.file_extension);
+) -> Result<Option<PathBuf>, MakeOrOpenPeriodicNoteError> {
+    let destination_path = periodic_note_path(config, period, for_day)
+        .map_err(InnerMakeOrOpenPeriodicNoteError::from)?;
 
     let destination_exists = ensure_note_exists(&destination_path)
         .map(|()| true)
@@ -130,7 +382,7 @@ pub fn make_or_open_daily<E: Editor, Tz: TimeZone>(
             if err.kind() == io::ErrorKind::NotFound {
                 Ok(false)
             } else {
-                Err(InnerMakeOrOpenDailyNoteError::NoteLookupError {
+                Err(InnerMakeOrOpenPeriodicNoteError::NoteLookupError {
                     destination: destination_path.display().to_string(),
                     err,
                 })
@@ -138,48 +390,212 @@ pub fn make_or_open_daily<E: Editor, Tz: TimeZone>(
         })?;
 
     if destination_exists {
-        open_existing_note_in_editor(config, editor, NoteKind::Daily, &destination_path)
-            .map_err(InnerMakeOrOpenDailyNoteError::from)?;
+        open_existing_note_in_editor(
+            config,
+            editor,
+            period.note_kind(),
+            &destination_path,
+            CursorPlacement::End,
+            creation_time.fixed_offset(),
+        )
+        .map_err(|err| InnerMakeOrOpenPeriodicNoteError::OpenNoteError { period, err })?;
 
         Ok(Some(destination_path))
     } else {
-        // We should be able to store the note with the date's name.
+        // We should be able to store the note with the period's name.
         //
         // Technically someone could come in and put a file there while we are
         // editing this note, but that is not behavior we really support.
         // That file will not be overwritten.
         //
-        // Plus, the dailies directory is separate from the notes directory,
+        // Plus, each period's directory is separate from the notes directory,
         // so without manual intervention, one cannot enter this scenario.
+        let mut preamble = Preamble::new(
+            periodic_note_title(config, period, for_day)
+                .map_err(InnerMakeOrOpenPeriodicNoteError::from)?,
+            creation_time.fixed_offset(),
+        );
+
+        if period == Period::Daily {
+            preamble.date = Some(for_day);
+        }
+
+        let initial_body = if period == Period::Daily && config.daily.carry_over_tasks {
+            carried_over_tasks(config, for_day, &destination_path).unwrap_or_default()
+        } else {
+            String::new()
+        };
+
+        // The daily filename format may place notes in subdirectories
+        if let Some(parent) = destination_path.parent() {
+            fs::create_dir_all(parent).map_err(|err| {
+                InnerMakeOrOpenPeriodicNoteError::CreateDirectoryError {
+                    directory: parent.display().to_string(),
+                    err,
+                }
+            })?;
+        }
+
         let store = StoreNoteAt {
             destination: destination_path,
         };
 
         let maybe_actual_path = make_note_with_store(
+            &FilesystemBackend::default(),
             config,
             store,
             editor,
-            for_day.format("%Y-%m-%d").to_string(),
-            creation_time,
-            NoteKind::Daily,
+            &preamble,
+            &initial_body,
+            period.note_kind(),
+            CursorPlacement::End,
+            None,
         )
-        .map_err(InnerMakeOrOpenDailyNoteError::from)?;
+        .map_err(|err| InnerMakeOrOpenPeriodicNoteError::MakeNoteAtError { period, err })?;
 
         Ok(maybe_actual_path)
     }
 }
 
-/// An error that occurred during a call to [`make_or_open_daily`]. See its
-/// [errors section](`make_or_open_daily#Errors`) for more details.
+fn notes_directory_store(config: &NoteConfig, title: &str) -> StoreNoteIn {
+    StoreNoteIn {
+        storage_directory: config.notes_directory_path(),
+        preferred_file_stem: note::filename_stem_for_title(title),
+        file_extension: config.file_extension.clone(),
+    }
+}
+
+/// Get the path of the periodic note that covers the given date.
+fn periodic_note_path(
+    config: &NoteConfig,
+    period: Period,
+    for_day: NaiveDate,
+) -> Result<PathBuf, PeriodicNoteNameError> {
+    let relative_path = if period == Period::Daily {
+        let format = &config.daily.filename_format;
+
+        note::format_date(for_day, format)
+            .map(PathBuf::from)
+            .filter(|path| is_plain_relative_path(path))
+            .ok_or_else(|| PeriodicNoteNameError::InvalidFilenameFormat {
+                format: format.clone(),
+            })?
+    } else {
+        PathBuf::from(period.filename_stem(for_day))
+    };
+
+    // Not using `with_extension`, as the formatted name may contain a `.` of its own
+    let mut filename = relative_path.into_os_string();
+    filename.push(".");
+    filename.push(&config.file_extension);
+
+    Ok(config.periodic_directory_path(period).join(filename))
+}
+
+/// Get the title for a new periodic note that covers the given date.
+fn periodic_note_title(
+    config: &NoteConfig,
+    period: Period,
+    for_day: NaiveDate,
+) -> Result<String, PeriodicNoteNameError> {
+    if period == Period::Daily {
+        let format = &config.daily.title_format;
+
+        note::format_date(for_day, format).ok_or_else(|| {
+            PeriodicNoteNameError::InvalidTitleFormat {
+                format: format.clone(),
+            }
+        })
+    } else {
+        Ok(period.filename_stem(for_day))
+    }
+}
+
+/// Check that the path is relative, non-empty, and does not leave the directory it is relative
+/// to (e.g. with `..`).
+fn is_plain_relative_path(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Build the text that carries over the unchecked tasks from the most recent daily note before
+/// `for_day` into the daily note at `destination_path`. Returns None if there are no tasks to
+/// carry over.
+///
+/// This is best-effort; if the previous daily note can't be found or read, a warning is logged and
+/// nothing is carried over.
+fn carried_over_tasks(
+    config: &NoteConfig,
+    for_day: NaiveDate,
+    destination_path: &Path,
+) -> Option<String> {
+    let dailies = indexed_dailies_by_date(config)
+        .inspect_err(|err| {
+            warning!("Could not find previous daily note to carry over tasks from: {err}");
+        })
+        .ok()?;
+
+    let (_date, previous_path) = dailies.range(..for_day).next_back()?;
+
+    let previous_contents = read_note(previous_path)
+        .inspect_err(|err| {
+            warning!("Could not carry over tasks from previous daily note: {err}");
+        })
+        .ok()?;
+
+    let tasks = task::unchecked_tasks(&previous_contents.body).join("\n");
+    if tasks.is_empty() {
+        return None;
+    }
+
+    let link = destination_path
+        .parent()
+        .and_then(|directory| relative_link(directory, previous_path))
+        .unwrap_or_else(|| previous_path.display().to_string());
+
+    Some(format!(
+        "Unfinished tasks from [{}]({link}):\n{tasks}\n\n",
+        previous_contents.preamble.title
+    ))
+}
+
+/// Build a relative link, suitable for markdown, from a note in `from_directory` to the note at
+/// `to`. Returns None if the link can't be represented.
+fn relative_link(from_directory: &Path, to: &Path) -> Option<String> {
+    let common_prefix = from_directory
+        .ancestors()
+        .find(|ancestor| to.starts_with(ancestor))?;
+
+    let parents = from_directory
+        .strip_prefix(common_prefix)
+        .ok()?
+        .components()
+        .map(|_component| Some(".."));
+    let descendants = to
+        .strip_prefix(common_prefix)
+        .ok()?
+        .components()
+        .map(|component| component.as_os_str().to_str());
+
+    parents
+        .chain(descendants)
+        .collect::<Option<Vec<_>>>()
+        .map(|parts| parts.join("/"))
+}
+
+/// An error that occurred during a call to [`make_or_open_periodic`]. See its
+/// [errors section](`make_or_open_periodic#Errors`) for more details.
 #[derive(Error, Debug)]
 #[error(transparent)]
-pub struct MakeOrOpenDailyNoteError {
+pub struct MakeOrOpenPeriodicNoteError {
     #[from]
-    inner: InnerMakeOrOpenDailyNoteError,
+    inner: InnerMakeOrOpenPeriodicNoteError,
 }
 
 #[derive(Error, Debug)]
-enum InnerMakeOrOpenDailyNoteError {
+enum InnerMakeOrOpenPeriodicNoteError {
     #[error("could not check if note exists at {destination:?}: {err}")]
     NoteLookupError {
         destination: String,
@@ -187,19 +603,48 @@ enum InnerMakeOrOpenDailyNoteError {
         err: io::Error,
     },
 
-    #[error("could not open daily note: {0}")]
-    OpenNoteError(#[from] OpenExistingNoteInEditorError),
+    #[error("could not open {period} note: {err}")]
+    OpenNoteError {
+        period: Period,
+        #[source]
+        err: OpenExistingNoteInEditorError,
+    },
+
+    #[error("could not create new {period} note: {err}")]
+    MakeNoteAtError {
+        period: Period,
+        #[source]
+        err: MakeNoteAtError,
+    },
+
+    #[error("could not create directory {directory:?}: {err}")]
+    CreateDirectoryError {
+        directory: String,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error(transparent)]
+    NameError(#[from] PeriodicNoteNameError),
+}
+
+#[derive(Error, Debug)]
+enum PeriodicNoteNameError {
+    #[error("invalid daily filename format {format:?}; it must be a relative path")]
+    InvalidFilenameFormat { format: String },
 
-    #[error("could not create new daily note: {0}")]
-    MakeNoteAtError(#[from] MakeNoteAtError),
+    #[error("invalid daily title format {format:?}")]
+    InvalidTitleFormat { format: String },
 }
 
-/// Open an existing note at the given path in the editor.
+/// Open an existing note at the given path in the editor. If the note is changed, its
+/// `updated_at` is set to the current time.
 ///
 /// # Errors
 ///
 /// Returns an error if there was an I/O problem locating the existing note, the editor
-/// fails to launch, or there is a problem updating the note's entry in the index.
+/// fails to launch, or there is a problem updating the note's entry in the index. If the editor
+/// exits unsuccessfully, the note is not reindexed and an error is returned.
 pub fn open_note<E: Editor>(
     config: &NoteConfig,
     editor: E,
@@ -218,6 +663,97 @@ pub struct OpenNoteError {
     inner: OpenExistingNoteError,
 }
 
+/// Read the note at the given path, without opening it in the editor.
+///
+/// # Errors
+///
+/// Returns an error if the note could not be read, or if its preamble is invalid.
+pub fn read_note(path: &Path) -> Result<NoteContents, ReadNoteError> {
+    let contents = read_note_contents(&FilesystemBackend::default(), path)?;
+
+    Ok(contents)
+}
+
+/// Read the note at the given path from the given backend.
+///
+/// # Errors
+///
+/// See [`read_note`].
+pub fn read_note_in(backend: &dyn Backend, path: &Path) -> Result<NoteContents, ReadNoteError> {
+    let contents = read_note_contents(backend, path)?;
+
+    Ok(contents)
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct ReadNoteError {
+    #[from]
+    inner: ReadNoteContentsError,
+}
+
+/// Open an encrypted note in the editor.
+///
+/// The body of the note is decrypted with the given passphrase into a temporary file for editing,
+/// and encrypted again when it is written back. A note that is marked `encrypted = true` but
+/// whose body is still plain text is encrypted, and one whose mark was removed while editing is
+/// written back in plain text.
+///
+/// # Errors
+///
+/// Returns an error if the note could not be read or decrypted (such as when the passphrase is
+/// wrong), the editor fails to launch or exits unsuccessfully, or the note could not be encrypted,
+/// written back, or reindexed. If the edited note cannot be written back, it is left in the
+/// temporary file, whose path is part of the error.
+pub fn open_encrypted_note<E: Editor>(
+    config: &NoteConfig,
+    editor: E,
+    kind: NoteKind,
+    path: &Path,
+    passphrase: &str,
+) -> Result<(), OpenEncryptedNoteError> {
+    open_encrypted_note_in_editor(
+        config,
+        editor,
+        kind,
+        path,
+        passphrase,
+        Local::now().fixed_offset(),
+    )?;
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct OpenEncryptedNoteError {
+    #[from]
+    inner: OpenEncryptedNoteInEditorError,
+}
+
+/// Read the note at the given path, decrypting its body with the given passphrase if it is
+/// encrypted.
+///
+/// # Errors
+///
+/// Returns an error if the note could not be read, its preamble is invalid, or its body could not
+/// be decrypted.
+pub fn read_encrypted_note(
+    path: &Path,
+    passphrase: &str,
+) -> Result<NoteContents, ReadEncryptedNoteError> {
+    let contents = read_decrypted_note_contents(path, passphrase)?;
+
+    Ok(contents)
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct ReadEncryptedNoteError {
+    #[from]
+    inner: ReadDecryptedNoteContentsError,
+}
+
 /// Index all notes in the notes and dailies directories. This will also remove deleted files
 /// from the index.
 ///
@@ -228,7 +764,19 @@ pub struct OpenNoteError {
 /// Note that this will return `Ok` if there is a problem indexing an individual note, but a
 /// warning will be printed to stderr.
 pub fn index_notes(config: &NoteConfig) -> Result<(), IndexNotesError> {
-    index_all_notes(config)?;
+    index_all_notes(&FilesystemBackend::new(config), config)?;
+
+    Ok(())
+}
+
+/// Index all notes in the given backend that are within the notes and dailies directories. This
+/// will also remove notes from the index that are no longer in the backend.
+///
+/// # Errors
+///
+/// See [`index_notes`].
+pub fn index_notes_in(backend: &dyn Backend, config: &NoteConfig) -> Result<(), IndexNotesError> {
+    index_all_notes(backend, config)?;
 
     Ok(())
 }
@@ -285,211 +833,3209 @@ pub struct IndexedNotesWithKindError {
     inner: KindedIndexedNotesError,
 }
 
-fn make_note_with_store<E: Editor, Tz: TimeZone, S: StoreNote>(
+/// Get the dates that have a daily note in the index.
+///
+/// # Errors
+///
+/// Returns an error if there was a problem opening or reading from the index.
+pub fn indexed_daily_dates(
     config: &NoteConfig,
-    store: S,
-    editor: E,
-    title: String,
-    creation_time: &DateTime<Tz>,
-    kind: NoteKind,
-) -> Result<Option<PathBuf>, MakeNoteAtError> {
-    let tempfile = make_tempfile(config).map_err(MakeNoteAtError::CreateTempfileError)?;
-    let preamble = Preamble::new(title, creation_time.fixed_offset());
+) -> Result<BTreeSet<NaiveDate>, IndexedDailyDatesError> {
+    let dates = indexed_dailies_by_date(config)?.into_keys().collect();
 
-    let serialized_preamble = write_preamble(&preamble, &tempfile)?;
-    open_in_editor(editor, &tempfile)?;
-
-    let handle = TempFileHandle::open(tempfile).map_err(MakeNoteAtError::OpenNoteError)?;
-    let maybe_actual_path = store_if_different(store, handle, &serialized_preamble)?;
+    Ok(dates)
+}
 
-    match maybe_actual_path {
-        Some(actual_destination_path) => {
-            let mut index_connection = open_index_database(config)?;
-            index_note(&mut index_connection, kind, &actual_destination_path)?;
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct IndexedDailyDatesError {
+    #[from]
+    inner: KindedIndexedNotesError,
+}
 
-            Ok(Some(actual_destination_path))
-        }
+/// Get the path of each daily note in the index, by the date it is for.
+///
+/// # Errors
+///
+/// Returns an error if there was a problem opening or reading from the index.
+pub fn indexed_dailies(
+    config: &NoteConfig,
+) -> Result<BTreeMap<NaiveDate, PathBuf>, IndexedDailiesError> {
+    let dailies = indexed_dailies_by_date(config)?;
 
-        None => Ok(None),
-    }
+    Ok(dailies)
 }
 
 #[derive(Error, Debug)]
 #[error(transparent)]
-enum MakeNoteAtError {
-    #[error("could not create temporary file: {0}")]
-    CreateTempfileError(io::Error),
+pub struct IndexedDailiesError {
+    #[from]
+    inner: KindedIndexedNotesError,
+}
 
-    #[error("could not write preamble to file: {0}")]
-    WritePreambleError(#[from] WritePreambleError),
+/// Which way to look for a note, relative to some date.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Previous,
+    Next,
+}
 
-    #[error("could not open note for storage: {0}")]
-    OpenNoteError(io::Error),
+/// Find the nearest daily note in the index before or after (but not on) the given date.
+///
+/// Returns the date of that daily note and its path, or None if there is no such note.
+///
+/// # Errors
+///
+/// Returns an error if there was a problem opening or reading from the index.
+pub fn adjacent_daily(
+    config: &NoteConfig,
+    date: NaiveDate,
+    direction: Direction,
+) -> Result<Option<(NaiveDate, PathBuf)>, AdjacentDailyError> {
+    let mut dailies = indexed_dailies_by_date(config)?;
+    let adjacent_date = match direction {
+        Direction::Previous => dailies.range(..date).next_back(),
+        Direction::Next => dailies
+            .range((Bound::Excluded(date), Bound::Unbounded))
+            .next(),
+    }
+    .map(|(adjacent_date, _path)| *adjacent_date);
+
+    Ok(adjacent_date.and_then(|adjacent_date| dailies.remove_entry(&adjacent_date)))
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct AdjacentDailyError {
+    #[from]
+    inner: KindedIndexedNotesError,
+}
+
+/// Get information about how often and how recently notes in the index have been opened. Notes
+/// which have never been opened with quicknotes will not be present.
+///
+/// # Errors
+///
+/// Returns an error if there was a problem opening or reading from the index.
+pub fn note_usage(config: &NoteConfig) -> Result<HashMap<PathBuf, NoteUsage>, NoteUsageError> {
+    let usage = indexed_note_usage(config)?;
+
+    Ok(usage)
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct NoteUsageError {
+    #[from]
+    inner: IndexedNoteUsageError,
+}
+
+/// Get all of the tasks (checklist items such as `- [ ] buy milk`) in the notes in the index.
+///
+/// # Errors
+///
+/// Returns an error if there was a problem opening or reading from the index.
+pub fn indexed_tasks(config: &NoteConfig) -> Result<Vec<IndexedTask>, IndexedTasksError> {
+    let tasks = all_indexed_tasks(config)?;
+
+    Ok(tasks)
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct IndexedTasksError {
+    #[from]
+    inner: AllIndexedTasksError,
+}
+
+/// Check or uncheck the task on the given line (starting at 1) of the note at the given path.
+/// The rest of the note is left as it was, aside from its `updated_at`.
+///
+/// Returns the task, as it is after being toggled.
+///
+/// # Errors
+///
+/// Returns an error if the note is not in the index, if it can't be read or written, if there is
+/// no task on the given line, or if there is a problem updating the note's entry in the index.
+/// The note is left untouched if it is not in the index.
+pub fn toggle_task(
+    config: &NoteConfig,
+    path: &Path,
+    line_number: usize,
+) -> Result<Task, ToggleTaskError> {
+    let task = toggle_task_in_note(config, path, line_number, Local::now().fixed_offset())?;
+
+    Ok(task)
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct ToggleTaskError {
+    #[from]
+    inner: ToggleTaskInNoteError,
+}
+
+/// Add an entry with the given text to the inbox, stamped with the time it was captured. If the
+/// inbox does not exist yet, it is created. The editor is not opened.
+///
+/// Returns the path of the inbox.
+///
+/// # Errors
+///
+/// Returns an error if the text is blank, the configured inbox path is invalid, or there is an
+/// I/O failure creating or writing to the inbox.
+pub fn capture<Tz: TimeZone>(
+    config: &NoteConfig,
+    text: &str,
+    captured_at: &DateTime<Tz>,
+) -> Result<PathBuf, CaptureError> {
+    let path = capture_to_inbox(config, text, captured_at.fixed_offset())?;
+
+    Ok(path)
+}
+
+/// An error that occurred during a call to [`capture`]. See its
+/// [errors section](`capture#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct CaptureError {
+    #[from]
+    inner: CaptureToInboxError,
+}
+
+/// Get the entries in the inbox, oldest first. If there is no inbox, there are no entries.
+///
+/// # Errors
+///
+/// Returns an error if the inbox could not be read.
+pub fn inbox_entries(config: &NoteConfig) -> Result<Vec<InboxEntry>, InboxEntriesError> {
+    let entries = read_inbox_entries(config)?;
+
+    Ok(entries)
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct InboxEntriesError {
+    #[from]
+    inner: ReadInboxEntriesError,
+}
+
+/// Remove an entry from the inbox, leaving the rest of the inbox as it was.
+///
+/// # Errors
+///
+/// Returns an error if the entry is no longer in the inbox, or there is an I/O failure reading or
+/// writing the inbox.
+pub fn delete_inbox_entry(
+    config: &NoteConfig,
+    entry: &InboxEntry,
+) -> Result<(), DeleteInboxEntryError> {
+    remove_inbox_entry(config, entry)?;
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct DeleteInboxEntryError {
+    #[from]
+    inner: RemoveInboxEntryError,
+}
+
+/// Move an entry from the inbox into a new note, titled with the entry's first line. The editor
+/// is not opened.
+///
+/// Returns the path of the new note.
+///
+/// # Errors
+///
+/// Returns an error if there is an I/O failure creating the note or updating the inbox, or if
+/// there is a problem adding the note to the index. The entry is only removed from the inbox once
+/// the note has been created.
+pub fn move_inbox_entry_to_note<Tz: TimeZone>(
+    config: &NoteConfig,
+    entry: &InboxEntry,
+    creation_time: &DateTime<Tz>,
+) -> Result<PathBuf, MoveInboxEntryError> {
+    let title = entry.summary().to_string();
+    let store = StoreNoteIn {
+        storage_directory: config.notes_directory_path(),
+        preferred_file_stem: note::filename_stem_for_title(&title),
+        file_extension: config.file_extension.clone(),
+    };
+
+    let path = write_note_with_store(
+        store,
+        config,
+        NoteKind::Note,
+        &Preamble::new(title, creation_time.fixed_offset()),
+        &format!("{}\n", entry.text),
+    )
+    .map_err(InnerMoveInboxEntryError::from)?;
+
+    finish_inbox_move(config, entry, NoteKind::Note, &path)?;
+
+    Ok(path)
+}
+
+/// Move an entry from the inbox to the end of the daily note for the given date, creating the
+/// daily note if it does not exist. The editor is not opened.
+///
+/// Returns the path of the daily note.
+///
+/// # Errors
+///
+/// Returns an error if the configured daily note formats are invalid, there is an I/O failure
+/// writing the daily note or updating the inbox, or if there is a problem adding the note to the
+/// index. The entry is only removed from the inbox once it has been added to the daily note.
+pub fn move_inbox_entry_to_daily<Tz: TimeZone>(
+    config: &NoteConfig,
+    entry: &InboxEntry,
+    for_day: NaiveDate,
+    time: &DateTime<Tz>,
+) -> Result<PathBuf, MoveInboxEntryError> {
+    let path = append_to_daily(config, for_day, &entry.text, time.fixed_offset())?;
+
+    finish_inbox_move(config, entry, NoteKind::Daily, &path)?;
+
+    Ok(path)
+}
+
+/// An error that occurred during a call to [`move_inbox_entry_to_note`] or
+/// [`move_inbox_entry_to_daily`]. See their errors sections for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct MoveInboxEntryError {
+    #[from]
+    inner: InnerMoveInboxEntryError,
+}
+
+#[derive(Error, Debug)]
+enum InnerMoveInboxEntryError {
+    #[error("could not create note: {0}")]
+    WriteNoteError(#[from] WriteNoteError),
+
+    #[error(transparent)]
+    NameError(#[from] PeriodicNoteNameError),
+
+    #[error("could not create directory {directory:?}: {err}")]
+    CreateDirectoryError {
+        directory: String,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("could not read daily note: {0}")]
+    ReadError(io::Error),
+
+    #[error("the daily note at {0:?} is encrypted, so entries cannot be added to it")]
+    EncryptedDailyError(PathBuf),
+
+    #[error("could not update the daily note's preamble: {0}")]
+    StampError(#[from] note::InvalidPreambleError),
+
+    #[error("could not write daily note: {0}")]
+    WriteError(WriteNoteError),
+
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error(transparent)]
+    IndexNoteError(#[from] IndexNoteError),
+
+    #[error("the entry was added to {path:?}, but could not be removed from the inbox: {err}")]
+    RemoveError {
+        path: PathBuf,
+        #[source]
+        err: RemoveInboxEntryError,
+    },
+}
+
+/// Export the given notes (e.g. from [`indexed_notes`]) to a static HTML site in `out_dir`.
+///
+/// Each note's markdown is rendered to its own page, at the same path relative to `out_dir` as the
+/// note has relative to the root directory. `[[links]]` to the title or filename of another
+/// exported note link to its page. An `index.html` page links to every note, newest first, and
+/// an `archive.html` page lists the daily notes by month.
+///
+/// Returns the number of notes that were exported.
+///
+/// # Errors
+///
+/// Returns an error if a note cannot be read, or if there is an I/O failure writing the site.
+pub fn export_html(
+    config: &NoteConfig,
+    notes: impl IntoIterator<Item = (PathBuf, IndexedNote)>,
+    out_dir: &Path,
+) -> Result<usize, ExportHtmlError> {
+    let exported = export_html_site(config, notes, out_dir)?;
+
+    Ok(exported)
+}
+
+/// An error that occurred during a call to [`export_html`]. See its
+/// [errors section](`export_html#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct ExportHtmlError {
+    #[from]
+    inner: ExportHtmlSiteError,
+}
+
+/// Package every indexed note into a single gzipped tarball at `out_path`, along with a manifest
+/// of their paths, hashes, and kinds. The bundle can be loaded with [`import_bundle`].
+///
+/// Returns the number of notes that were bundled.
+///
+/// # Errors
+///
+/// Returns an error if there is a problem reading from the index, a note cannot be read, or there
+/// is an I/O failure writing the bundle.
+pub fn export_bundle(config: &NoteConfig, out_path: &Path) -> Result<usize, ExportBundleError> {
+    let bundled = write_bundle(config, out_path)?;
+
+    Ok(bundled)
+}
+
+/// An error that occurred during a call to [`export_bundle`]. See its
+/// [errors section](`export_bundle#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct ExportBundleError {
+    #[from]
+    inner: WriteBundleError,
+}
+
+/// The outcome of a call to [`import_bundle`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Notes that were added at the same path they had in the bundle.
+    pub imported: Vec<PathBuf>,
+    /// Notes that were already present with identical contents, and so were left alone.
+    pub unchanged: Vec<PathBuf>,
+    /// Notes whose path was already taken by a different note, and so were stored elsewhere.
+    pub conflicts: Vec<ImportConflict>,
+}
+
+/// A note from a bundle that could not be imported at its original path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportConflict {
+    /// The note that was already at the bundled note's path.
+    pub existing_path: PathBuf,
+    /// Where the bundled note was stored instead.
+    pub imported_path: PathBuf,
+}
+
+/// Import the notes from a bundle created by [`export_bundle`] into the root directory, and add
+/// them to the index. Existing notes are never overwritten; a bundled note whose path is taken by
+/// a different note is stored under a new name, and reported as a conflict.
+///
+/// # Errors
+///
+/// Returns an error if the bundle cannot be read or does not match its manifest, in which case
+/// nothing is imported. An error is also returned if there is an I/O failure storing a note, or
+/// if the index cannot be opened.
+pub fn import_bundle(
+    config: &NoteConfig,
+    bundle_path: &Path,
+) -> Result<ImportReport, ImportBundleError> {
+    let report = read_bundle_into_root(config, bundle_path)?;
+
+    Ok(report)
+}
+
+/// An error that occurred during a call to [`import_bundle`]. See its
+/// [errors section](`import_bundle#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct ImportBundleError {
+    #[from]
+    inner: ReadBundleIntoRootError,
+}
+
+/// Import the notes in a directory written by another tool, giving each a quicknotes preamble
+/// (see [`ImportSource`]) and adding it to the index. Notes whose filename is a date (e.g.
+/// `2015-10-21.md`) are imported as daily notes; everything else is stored in the notes directory.
+///
+/// Existing notes are never overwritten. If the daily note for an imported daily already exists,
+/// it is imported as a regular note instead, and if a regular note's filename is taken, it is
+/// given a new one. Both cases are reported as conflicts. Notes that are identical to one that
+/// already exists (as when importing the same directory again) are skipped, and reported as
+/// unchanged. Front matter that has no place in the preamble is kept in a `[front_matter]` table.
+///
+/// # Errors
+///
+/// Returns an error if the configured daily note formats are invalid, there is an I/O failure
+/// storing a note, or if the index cannot be opened. Files that cannot be read are skipped with a
+/// warning.
+pub fn import_directory(
+    config: &NoteConfig,
+    source: ImportSource,
+    directory: &Path,
+) -> Result<ImportReport, ImportDirectoryError> {
+    let report = import_foreign_notes(config, source, directory)?;
+
+    Ok(report)
+}
+
+/// An error that occurred during a call to [`import_directory`]. See its
+/// [errors section](`import_directory#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct ImportDirectoryError {
+    #[from]
+    inner: ImportForeignNotesError,
+}
+
+/// A file in one of the note directories that has the note file extension, but no preamble, and
+/// so is not in the index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrphanedNote {
+    pub path: PathBuf,
+    /// The kind of note it would be, based on the directory it is in.
+    pub kind: NoteKind,
+}
+
+/// Find the files in the note directories that are not indexed because they have no preamble, e.g.
+/// because they were created by hand. They can be adopted with [`adopt_note`]. Files with a
+/// preamble that cannot be parsed are not orphaned; [`check_health`] reports them instead. Files
+/// that cannot be read are skipped with a warning.
+#[must_use]
+pub fn orphaned_notes(config: &NoteConfig) -> Vec<OrphanedNote> {
+    find_orphaned_notes(config)
+}
+
+/// Give an orphaned note a preamble, so that it can be indexed, and add it to the index. The
+/// title is guessed from the filename (e.g. `my-cool-note` becomes `my cool note`), and the
+/// creation time is taken from the file's modification time. Daily notes named after a date are
+/// titled like any other daily note, and have their `date` set.
+///
+/// Returns the preamble that was added.
+///
+/// # Errors
+///
+/// Returns an error if the note already has a preamble, the configured daily title format is
+/// invalid, there is an I/O failure reading or writing the note, or if there is a problem adding it
+/// to the index.
+pub fn adopt_note(
+    config: &NoteConfig,
+    orphan: &OrphanedNote,
+) -> Result<NotePreamble, AdoptNoteError> {
+    let preamble = add_preamble_to_orphan(config, orphan)?;
+
+    Ok(preamble)
+}
+
+/// An error that occurred during a call to [`adopt_note`]. See its
+/// [errors section](`adopt_note#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct AdoptNoteError {
+    #[from]
+    inner: AddPreambleToOrphanError,
+}
+
+/// A problem with the notes, found by [`check_health`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HealthIssue {
+    /// The index has an entry for a note that no longer exists.
+    StaleIndexEntry { path: PathBuf },
+
+    /// More than one note has the same title (ignoring case), so links to that title are
+    /// ambiguous.
+    DuplicateTitle { title: String, paths: Vec<PathBuf> },
+
+    /// A note has no preamble, so it is not indexed. See [`orphaned_notes`].
+    Orphaned(OrphanedNote),
+
+    /// A note has a preamble that cannot be parsed, so it is not indexed. It must be fixed by
+    /// hand, as adopting it could lose what is in the preamble.
+    MalformedPreamble { path: PathBuf, error: String },
+
+    /// A daily note's title is for a different day than its filename.
+    DailyDateMismatch {
+        path: PathBuf,
+        title_date: NaiveDate,
+        filename_date: NaiveDate,
+    },
+
+    /// A file in one of the note directories does not have the configured file extension.
+    /// `renamed_path` is where it will be moved by [`fix_health_issue`], if it has a preamble and
+    /// nothing is in the way.
+    WrongExtension {
+        path: PathBuf,
+        kind: NoteKind,
+        renamed_path: Option<PathBuf>,
+    },
+
+    /// A note was left in the temporary directory, most likely because it could not be saved.
+    PreservedTempfile { path: PathBuf },
+
+    /// A note could not be saved, and is waiting in the recovery directory. See
+    /// [`recover_note`].
+    RecoverableNote { path: PathBuf },
+}
+
+impl HealthIssue {
+    /// A short, stable name for the kind of issue, for use in machine-readable output.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::StaleIndexEntry { .. } => "stale-index-entry",
+            Self::DuplicateTitle { .. } => "duplicate-title",
+            Self::Orphaned(_) => "orphaned-note",
+            Self::MalformedPreamble { .. } => "malformed-preamble",
+            Self::DailyDateMismatch { .. } => "daily-date-mismatch",
+            Self::WrongExtension { .. } => "wrong-extension",
+            Self::PreservedTempfile { .. } => "preserved-tempfile",
+            Self::RecoverableNote { .. } => "recoverable-note",
+        }
+    }
+
+    /// Check if the issue can be fixed with [`fix_health_issue`]. Only issues that can be fixed
+    /// without losing or overwriting anything are fixable.
+    #[must_use]
+    pub fn is_fixable(&self) -> bool {
+        match self {
+            Self::StaleIndexEntry { .. } => true,
+            Self::WrongExtension { renamed_path, .. } => renamed_path.is_some(),
+            Self::DuplicateTitle { .. }
+            | Self::Orphaned(_)
+            | Self::MalformedPreamble { .. }
+            | Self::DailyDateMismatch { .. }
+            | Self::PreservedTempfile { .. }
+            | Self::RecoverableNote { .. } => false,
+        }
+    }
+}
+
+/// Check the notes and the index for problems. Issues are grouped by kind, and sorted by path
+/// within each kind.
+///
+/// Preserved tempfiles are found by looking for notes named like quicknotes' tempfiles in the
+/// temporary directory, so a note that is currently being edited will also be reported.
+///
+/// # Errors
+///
+/// Returns an error if the index or the recovery directory cannot be read.
+pub fn check_health(config: &NoteConfig) -> Result<Vec<HealthIssue>, CheckHealthError> {
+    let issues = find_health_issues(config)?;
+
+    Ok(issues)
+}
+
+/// An error that occurred during a call to [`check_health`]. See its
+/// [errors section](`check_health#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct CheckHealthError {
+    #[from]
+    inner: FindHealthIssuesError,
+}
+
+/// Fix an issue found by [`check_health`]. Stale index entries are removed from the index, and
+/// files with the wrong extension are renamed and indexed.
+///
+/// # Errors
+///
+/// Returns an error if the issue is not fixable (see [`HealthIssue::is_fixable`]), a renamed file
+/// would overwrite another, or there is an I/O failure or problem updating the index.
+pub fn fix_health_issue(
+    config: &NoteConfig,
+    issue: &HealthIssue,
+) -> Result<(), FixHealthIssueError> {
+    fix_issue(config, issue)?;
+
+    Ok(())
+}
+
+/// An error that occurred during a call to [`fix_health_issue`]. See its
+/// [errors section](`fix_health_issue#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct FixHealthIssueError {
+    #[from]
+    inner: FixIssueError,
+}
+
+/// List the notes that were moved to the recovery directory because they could not be stored,
+/// sorted by path.
+///
+/// # Errors
+///
+/// Returns an error if the recovery directory cannot be read.
+pub fn recoverable_notes(
+    config: &NoteConfig,
+) -> Result<Vec<RecoverableNote>, RecoverableNotesError> {
+    let notes = recovery::list(&config.recovery_directory_path())
+        .map_err(|err| RecoverableNotesError { inner: err })?;
+
+    Ok(notes)
+}
+
+/// An error that occurred during a call to [`recoverable_notes`]. See its
+/// [errors section](`recoverable_notes#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error("could not read recovery directory: {inner}")]
+pub struct RecoverableNotesError {
+    #[source]
+    inner: io::Error,
+}
+
+/// Store a note from the recovery directory where it was originally meant to go, index it, and
+/// remove it from the recovery directory. If a different note has since been stored there, the
+/// recovered note is stored alongside it under a new name, rather than overwriting it.
+///
+/// Returns the path the note was stored at.
+///
+/// # Errors
+///
+/// Returns an error if the note cannot be read or stored, if it was meant to go somewhere other than
+/// a note directory or the inbox, or if there is a problem adding it to the index. If the note
+/// cannot be stored, it is kept in the recovery directory.
+pub fn recover_note(
+    config: &NoteConfig,
+    note: &RecoverableNote,
+) -> Result<PathBuf, RecoverNoteError> {
+    let path = restore_recovered_note(config, note)?;
+
+    Ok(path)
+}
+
+/// An error that occurred during a call to [`recover_note`]. See its
+/// [errors section](`recover_note#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct RecoverNoteError {
+    #[from]
+    inner: RestoreRecoveredNoteError,
+}
+
+/// List the drafts of new notes that were left behind when quicknotes last exited, e.g. because
+/// the terminal was closed while the editor was open. Drafts being written by a quicknotes process
+/// that is still running are not listed. On platforms other than Unix, there is no way to tell if
+/// that process is still running, so no drafts are listed.
+///
+/// # Errors
+///
+/// Returns an error if the drafts directory cannot be read.
+pub fn abandoned_drafts(config: &NoteConfig) -> Result<Vec<Draft>, AbandonedDraftsError> {
+    let drafts = draft::abandoned(&config.drafts_directory_path())
+        .map_err(|err| AbandonedDraftsError { inner: err })?;
+
+    Ok(drafts)
+}
+
+/// An error that occurred during a call to [`abandoned_drafts`]. See its
+/// [errors section](`abandoned_drafts#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error("could not read drafts directory: {inner}")]
+pub struct AbandonedDraftsError {
+    #[source]
+    inner: io::Error,
+}
+
+/// Open an abandoned draft in the editor, and then store it like [`store_draft`]. If the editor
+/// fails, the draft is kept so that it can be resumed again.
+///
+/// Returns the path the note was stored at, or None if nothing was written to the draft.
+///
+/// # Errors
+///
+/// Returns an error if the editor fails, or for any of the reasons [`store_draft`] can.
+pub fn resume_draft<E: Editor>(
+    config: &NoteConfig,
+    editor: E,
+    draft: &Draft,
+    passphrase: Option<&str>,
+) -> Result<Option<PathBuf>, ResumeDraftError> {
+    check_draft_destination(config, draft).map_err(InnerResumeDraftError::from)?;
+    draft::claim(draft).map_err(InnerResumeDraftError::from)?;
+    open_in_editor(editor, &draft.path, CursorPlacement::End)
+        .map_err(InnerResumeDraftError::from)?;
+    let path =
+        store_abandoned_draft(config, draft, passphrase).map_err(InnerResumeDraftError::from)?;
+
+    Ok(path)
+}
+
+/// An error that occurred during a call to [`resume_draft`]. See its
+/// [errors section](`resume_draft#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct ResumeDraftError {
+    #[from]
+    inner: InnerResumeDraftError,
+}
+
+/// Store an abandoned draft where it was meant to go, index it, and remove it from the drafts
+/// directory. If a different note has since been stored there, the draft is stored alongside it
+/// under a new name, rather than overwriting it. Drafts that were never written to are removed
+/// without being stored. A draft marked `encrypted = true` has its body encrypted with the given
+/// passphrase before it is stored (see [`Draft::is_encrypted`]).
+///
+/// Returns the path the note was stored at, or None if nothing was written to the draft.
+///
+/// # Errors
+///
+/// Returns an error if the draft cannot be read or stored, if it was meant to go somewhere other
+/// than a note directory or the inbox, if it is marked as encrypted but no passphrase is given or
+/// it cannot be encrypted, or if there is a problem adding it to the index. The draft is kept if it
+/// cannot be stored.
+pub fn store_draft(
+    config: &NoteConfig,
+    draft: &Draft,
+    passphrase: Option<&str>,
+) -> Result<Option<PathBuf>, StoreDraftError> {
+    let path = store_abandoned_draft(config, draft, passphrase)?;
+
+    Ok(path)
+}
+
+/// An error that occurred during a call to [`store_draft`]. See its
+/// [errors section](`store_draft#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct StoreDraftError {
+    #[from]
+    inner: StoreAbandonedDraftError,
+}
+
+/// Delete an abandoned draft.
+///
+/// # Errors
+///
+/// Returns an error if the draft cannot be deleted.
+pub fn discard_draft(draft: &Draft) -> Result<(), DiscardDraftError> {
+    draft::remove(draft).map_err(|err| DiscardDraftError { inner: err })?;
+
+    Ok(())
+}
+
+/// An error that occurred during a call to [`discard_draft`]. See its
+/// [errors section](`discard_draft#Errors`) for more details.
+#[derive(Error, Debug)]
+#[error("could not delete draft: {inner}")]
+pub struct DiscardDraftError {
+    #[source]
+    inner: io::Error,
+}
+
+/// Attach a file to the note at the given path.
+///
+/// The file is copied into the note's own directory within the attachments directory (e.g.
+/// `attachments/my-note` for `notes/my-note.md`), keeping its filename unless that would overwrite
+/// an earlier attachment. A markdown link to the copy is added to the end of the note, and the
+/// attachment is recorded in the index, so that it can be removed along with the note by
+/// [`delete_note`].
+///
+/// Returns the path of the copy.
+///
+/// # Errors
+///
+/// Returns an error if the note could not be read or is encrypted, the file could not be copied,
+/// or the note or index could not be updated. If the link could not be added to the note, the
+/// copy is removed.
+pub fn attach_file<Tz: TimeZone>(
+    config: &NoteConfig,
+    note_path: &Path,
+    kind: NoteKind,
+    file: &Path,
+    attached_at: &DateTime<Tz>,
+) -> Result<PathBuf, AttachFileError> {
+    let attachment_path =
+        attach_to_note(config, note_path, kind, file, attached_at.fixed_offset())?;
+
+    Ok(attachment_path)
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct AttachFileError {
+    #[from]
+    inner: AttachToNoteError,
+}
+
+/// Get the paths of the files attached to the note at the given path with [`attach_file`].
+///
+/// # Errors
+///
+/// Returns an error if there is a problem reading the index.
+pub fn note_attachments(
+    config: &NoteConfig,
+    note_path: &Path,
+) -> Result<Vec<PathBuf>, NoteAttachmentsError> {
+    let attachment_paths = indexed_attachments(config, note_path)?;
+
+    Ok(attachment_paths)
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct NoteAttachmentsError {
+    #[from]
+    inner: IndexedAttachmentsError,
+}
+
+/// Delete the note at the given path, along with its attachments, and remove it from the index.
+///
+/// # Errors
+///
+/// Returns an error if the note could not be deleted, or there is a problem updating the index.
+/// Attachments that cannot be deleted are reported as warnings, and left in place.
+pub fn delete_note(config: &NoteConfig, path: &Path) -> Result<(), DeleteNoteError> {
+    delete_note_and_attachments(&FilesystemBackend::new(config), config, path)?;
+
+    Ok(())
+}
+
+/// Delete the note at the given path from the given backend, along with its attachments, and
+/// remove it from the index.
+///
+/// # Errors
+///
+/// See [`delete_note`].
+pub fn delete_note_in(
+    backend: &dyn Backend,
+    config: &NoteConfig,
+    path: &Path,
+) -> Result<(), DeleteNoteError> {
+    delete_note_and_attachments(backend, config, path)?;
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct DeleteNoteError {
+    #[from]
+    inner: DeleteNoteAndAttachmentsError,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn make_note_with_store<E: Editor, S: StoreNote>(
+    backend: &dyn Backend,
+    config: &NoteConfig,
+    store: S,
+    editor: E,
+    preamble: &Preamble,
+    initial_body: &str,
+    kind: NoteKind,
+    placement: CursorPlacement,
+    passphrase: Option<&str>,
+) -> Result<Option<PathBuf>, MakeNoteAtError> {
+    // Notes that will be encrypted are edited in plain text, so they are kept out of the drafts
+    // directory, where they would outlive a crash.
+    let tempfile = match passphrase {
+        Some(_) => make_tempfile(config),
+        None => make_draft_tempfile(config),
+    }
+    .map_err(MakeNoteAtError::CreateTempfileError)?;
+
+    let initial_contents = write_initial_contents(preamble, initial_body, &tempfile)?;
+    let mut draft = match passphrase {
+        Some(_) => DraftGuard::untracked(tempfile),
+        None => DraftGuard::track(
+            tempfile,
+            &store.destination(),
+            kind,
+            preamble.created_at,
+            &initial_contents,
+        )?,
+    };
+    open_in_editor(editor, draft.path(), placement)?;
+
+    if let Some(passphrase) = passphrase {
+        if let Err(err) = encrypt_edited_note(draft.path(), &initial_contents, passphrase) {
+            // Keep the plain text rather than lose the note; the user can decide what to do with it
+            let path = draft.path().to_owned();
+            draft
+                .take()
+                .keep()
+                .map_err(|err| MakeNoteAtError::KeepDraftError {
+                    path: path.clone(),
+                    err: err.error,
+                })?;
+
+            return Err(MakeNoteAtError::EncryptError {
+                path,
+                err: Box::new(err),
+            });
+        }
+    }
+
+    // If the note was left as we seeded it, nothing was actually written.
+    let handle = TempFileHandle::open(draft.take(), recovery_for(config, kind))
+        .map_err(MakeNoteAtError::OpenNoteError)?;
+    let maybe_actual_path = store_if_different(store, handle, &initial_contents)?;
+
+    match maybe_actual_path {
+        Some(actual_destination_path) => {
+            let mut index_connection = open_index_database(config)?;
+            index_note_in(
+                &mut index_connection,
+                backend,
+                kind,
+                &actual_destination_path,
+            )?;
+            record_note_open(&mut index_connection, &actual_destination_path);
+
+            Ok(Some(actual_destination_path))
+        }
+
+        None => Ok(None),
+    }
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+enum MakeNoteAtError {
+    #[error("could not create temporary file: {0}")]
+    CreateTempfileError(io::Error),
+
+    #[error("could not write initial contents to file: {0}")]
+    WriteInitialContentsError(#[from] WriteInitialContentsError),
+
+    #[error(transparent)]
+    TrackDraftError(#[from] sidecar::WriteSidecarError),
+
+    #[error("could not encrypt note; it has been left unencrypted at {path}: {err}")]
+    EncryptError {
+        path: PathBuf,
+        #[source]
+        err: Box<EncryptNoteError>,
+    },
+
+    #[error("could not encrypt note, nor keep it at {path}: {err}")]
+    KeepDraftError {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("could not open note for storage: {0}")]
+    OpenNoteError(io::Error),
+
+    #[error(transparent)]
+    StoreNoteError(#[from] StoreIfDifferentError),
+
+    #[error(transparent)]
+    EditorSpawnError(#[from] OpenInEditorError),
+
+    #[error(transparent)]
+    IndexNoteError(#[from] IndexNoteError),
+
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+}
+
+fn capture_to_inbox(
+    config: &NoteConfig,
+    text: &str,
+    captured_at: DateTime<FixedOffset>,
+) -> Result<PathBuf, CaptureToInboxError> {
+    if text.trim().is_empty() {
+        return Err(CaptureToInboxError::EmptyText);
+    }
+
+    if !is_plain_relative_path(Path::new(&config.inbox.filename)) {
+        return Err(CaptureToInboxError::InvalidInboxPath {
+            filename: config.inbox.filename.clone(),
+        });
+    }
+
+    let inbox_path = config.inbox_path();
+    let entry = inbox::format_entry(captured_at.naive_local(), text);
+
+    match fs::read_to_string(&inbox_path) {
+        Ok(contents) => {
+            let separator = if contents.is_empty() || contents.ends_with('\n') {
+                ""
+            } else {
+                "\n"
+            };
+
+            OpenOptions::new()
+                .append(true)
+                .open(&inbox_path)
+                .and_then(|mut file| file.write_all(format!("{separator}{entry}").as_bytes()))
+                .map_err(CaptureToInboxError::WriteError)?;
+
+            Ok(inbox_path)
+        }
+
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            if let Some(parent) = inbox_path.parent() {
+                fs::create_dir_all(parent).map_err(CaptureToInboxError::WriteError)?;
+            }
+
+            let store = StoreNoteAt {
+                destination: inbox_path,
+            };
+            let path = write_note_with_store(
+                store,
+                config,
+                NoteKind::Note,
+                &Preamble::new(INBOX_TITLE.to_string(), captured_at),
+                &entry,
+            )?;
+
+            Ok(path)
+        }
+
+        Err(err) => Err(CaptureToInboxError::ReadError(err)),
+    }
+}
+
+#[derive(Error, Debug)]
+enum CaptureToInboxError {
+    #[error("there is nothing to capture")]
+    EmptyText,
+
+    #[error("invalid inbox filename {filename:?}; it must be a relative path")]
+    InvalidInboxPath { filename: String },
+
+    #[error("could not read inbox: {0}")]
+    ReadError(io::Error),
+
+    #[error("could not write to inbox: {0}")]
+    WriteError(io::Error),
+
+    #[error("could not create inbox: {0}")]
+    CreateError(#[from] WriteNoteError),
+}
+
+fn read_inbox_entries(config: &NoteConfig) -> Result<Vec<InboxEntry>, ReadInboxEntriesError> {
+    let contents = match fs::read_to_string(config.inbox_path()) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(ReadInboxEntriesError::ReadError(err)),
+    };
+
+    let (_preamble, body) = note::extract_preamble_and_body(contents.as_bytes())?;
+
+    Ok(inbox::entries(&body))
+}
+
+#[derive(Error, Debug)]
+enum ReadInboxEntriesError {
+    #[error("could not read inbox: {0}")]
+    ReadError(io::Error),
+
+    #[error("could not read inbox preamble: {0}")]
+    PreambleError(#[from] note::InvalidPreambleError),
+}
+
+fn remove_inbox_entry(
+    config: &NoteConfig,
+    entry: &InboxEntry,
+) -> Result<(), RemoveInboxEntryError> {
+    let inbox_path = config.inbox_path();
+    let contents = fs::read_to_string(&inbox_path).map_err(RemoveInboxEntryError::ReadError)?;
+    let remaining =
+        inbox::remove_entry(&contents, entry).ok_or(RemoveInboxEntryError::EntryNotFound)?;
+
+    let store = StoreNoteOver {
+        destination: inbox_path,
+    };
+    write_contents_with_store(store, config, NoteKind::Note, &remaining)?;
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+enum RemoveInboxEntryError {
+    #[error("could not read inbox: {0}")]
+    ReadError(io::Error),
+
+    #[error("the entry is no longer in the inbox")]
+    EntryNotFound,
+
+    #[error("could not write inbox: {0}")]
+    WriteError(#[from] WriteNoteError),
+}
+
+/// Index a note that an inbox entry was moved into, and then remove the entry from the inbox.
+fn finish_inbox_move(
+    config: &NoteConfig,
+    entry: &InboxEntry,
+    kind: NoteKind,
+    path: &Path,
+) -> Result<(), InnerMoveInboxEntryError> {
+    let mut index_connection = open_index_database(config)?;
+    index_note(&mut index_connection, kind, path)?;
+
+    remove_inbox_entry(config, entry).map_err(|err| InnerMoveInboxEntryError::RemoveError {
+        path: path.to_owned(),
+        err,
+    })
+}
+
+/// Add the given text to the end of the daily note for the given date, creating it if it does not
+/// exist. Returns the path of the daily note.
+fn append_to_daily(
+    config: &NoteConfig,
+    for_day: NaiveDate,
+    text: &str,
+    time: DateTime<FixedOffset>,
+) -> Result<PathBuf, InnerMoveInboxEntryError> {
+    let path = periodic_note_path(config, Period::Daily, for_day)?;
+
+    match fs::read_to_string(&path) {
+        Ok(contents) if is_encrypted_note(&contents) => {
+            Err(InnerMoveInboxEntryError::EncryptedDailyError(path))
+        }
+
+        Ok(contents) => {
+            let stamped = note::stamp_updated_at(&appended_contents(&contents, text), time)?;
+
+            let store = StoreNoteOver { destination: path };
+            let path = write_contents_with_store(store, config, NoteKind::Daily, &stamped)
+                .map_err(InnerMoveInboxEntryError::WriteError)?;
+
+            Ok(path)
+        }
+
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|err| {
+                    InnerMoveInboxEntryError::CreateDirectoryError {
+                        directory: parent.display().to_string(),
+                        err,
+                    }
+                })?;
+            }
+
+            let mut preamble =
+                Preamble::new(periodic_note_title(config, Period::Daily, for_day)?, time);
+            preamble.date = Some(for_day);
+
+            let store = StoreNoteAt { destination: path };
+            let path = write_note_with_store(
+                store,
+                config,
+                NoteKind::Daily,
+                &preamble,
+                &format!("{text}\n"),
+            )?;
+
+            Ok(path)
+        }
+
+        Err(err) => Err(InnerMoveInboxEntryError::ReadError(err)),
+    }
+}
+
+/// Write a note with the given contents straight to the store, without opening the editor. The
+/// note is not indexed.
+fn write_note_with_store<S: StoreNote>(
+    store: S,
+    config: &NoteConfig,
+    kind: NoteKind,
+    preamble: &Preamble,
+    body: &str,
+) -> Result<PathBuf, WriteNoteError> {
+    let tempfile = make_tempfile(config).map_err(WriteNoteError::CreateTempfileError)?;
+    write_initial_contents(preamble, body, &tempfile)?;
+
+    let handle = TempFileHandle::open(tempfile, recovery_for(config, kind))
+        .map_err(WriteNoteError::OpenNoteError)?;
+    let path = store.store(handle)?;
+
+    Ok(path)
+}
+
+/// Store a note with exactly the given contents, such as one converted from another tool.
+fn write_contents_with_store<S: StoreNote>(
+    store: S,
+    config: &NoteConfig,
+    kind: NoteKind,
+    contents: &str,
+) -> Result<PathBuf, WriteNoteError> {
+    let tempfile = make_tempfile(config).map_err(WriteNoteError::CreateTempfileError)?;
+    fs::write(&tempfile, contents).map_err(WriteNoteError::WriteContentsError)?;
+
+    let handle = TempFileHandle::open(tempfile, recovery_for(config, kind))
+        .map_err(WriteNoteError::OpenNoteError)?;
+    let path = store.store(handle)?;
+
+    Ok(path)
+}
+
+#[derive(Error, Debug)]
+enum WriteNoteError {
+    #[error("could not create temporary file: {0}")]
+    CreateTempfileError(io::Error),
+
+    #[error("could not write contents to file: {0}")]
+    WriteContentsError(io::Error),
+
+    #[error("could not write contents to file: {0}")]
+    WriteInitialContentsError(#[from] WriteInitialContentsError),
+
+    #[error("could not open note for storage: {0}")]
+    OpenNoteError(io::Error),
+
+    #[error(transparent)]
+    StoreNoteError(#[from] StoreNoteError),
+}
+
+fn export_html_site(
+    config: &NoteConfig,
+    notes: impl IntoIterator<Item = (PathBuf, IndexedNote)>,
+    out_dir: &Path,
+) -> Result<usize, ExportHtmlSiteError> {
+    let index_path = out_dir.join("index.html");
+    let archive_path = out_dir.join("archive.html");
+    let pages = notes
+        .into_iter()
+        .filter_map(|(path, note)| {
+            let Some(page_path) = export_page_path(config, out_dir, &path) else {
+                warning!(
+                    "Not exporting {}, as it is not in the notes directory",
+                    path.display()
+                );
+                return None;
+            };
+
+            Some((path, page_path, note))
+        })
+        .sorted_by(|(_path_a, _page_a, note_a), (_path_b, _page_b, note_b)| {
+            note_b
+                .preamble
+                .created_at
+                .cmp(&note_a.preamble.created_at)
+                .then_with(|| note_a.preamble.title.cmp(&note_b.preamble.title))
+        })
+        .collect::<Vec<_>>();
+
+    // Titles take precedence over filenames, so they are inserted last
+    let mut link_targets = HashMap::new();
+    for (path, page_path, _note) in &pages {
+        if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            link_targets.insert(stem.to_lowercase(), page_path);
+        }
+    }
+    for (_path, page_path, note) in &pages {
+        link_targets.insert(note.preamble.title.to_lowercase(), page_path);
+    }
+
+    for (path, page_path, note) in &pages {
+        let page_directory = page_path.parent().unwrap_or(out_dir);
+        let contents = read_note_contents(&FilesystemBackend::default(), path)?;
+        let body_html = if contents.preamble.encrypted || crypt::is_encrypted(&contents.body) {
+            html::ENCRYPTED_BODY_HTML.to_string()
+        } else {
+            html::markdown_to_html(&contents.body, |target| {
+                link_targets
+                    .get(&target.to_lowercase())
+                    .and_then(|target_path| relative_link(page_directory, target_path))
+            })
+        };
+        let page = html::note_page(
+            &note.preamble.title,
+            note.preamble.created_at,
+            &note.preamble.tags,
+            &body_html,
+            &relative_link(page_directory, &index_path).unwrap_or_default(),
+        );
+
+        write_export_page(page_path, &page)?;
+    }
+
+    let page_link = |page_path: &Path, note: &IndexedNote| html::PageLink {
+        title: note.preamble.title.clone(),
+        href: relative_link(out_dir, page_path).unwrap_or_default(),
+        created_at: note.preamble.created_at,
+    };
+
+    let mut dailies_by_month = BTreeMap::<NaiveDate, Vec<(NaiveDate, html::PageLink)>>::new();
+    for (path, page_path, note) in &pages {
+        if note.kind != NoteKind::Daily {
+            continue;
+        }
+
+        if let Some(date) = daily_note_date(path, note) {
+            let month = date - chrono::Days::new(u64::from(date.day0()));
+            dailies_by_month
+                .entry(month)
+                .or_default()
+                .push((date, page_link(page_path, note)));
+        }
+    }
+
+    let archive_href = if dailies_by_month.is_empty() {
+        None
+    } else {
+        let months = dailies_by_month
+            .into_iter()
+            .rev()
+            .map(|(month, dailies)| {
+                let links = dailies
+                    .into_iter()
+                    .sorted_by(|(date_a, _link_a), (date_b, _link_b)| date_b.cmp(date_a))
+                    .map(|(_date, link)| link)
+                    .collect();
+
+                (month, links)
+            })
+            .collect::<Vec<_>>();
+
+        write_export_page(&archive_path, &html::archive_page(&months, "index.html"))?;
+
+        Some("archive.html")
+    };
+
+    let links = pages
+        .iter()
+        .map(|(_path, page_path, note)| page_link(page_path, note))
+        .collect::<Vec<_>>();
+    write_export_page(&index_path, &html::index_page(&links, archive_href))?;
+
+    Ok(pages.len())
+}
+
+#[derive(Error, Debug)]
+enum ExportHtmlSiteError {
+    #[error(transparent)]
+    ReadNoteError(#[from] ReadNoteContentsError),
+
+    #[error("could not create directory {directory:?}: {err}")]
+    CreateDirectoryError {
+        directory: String,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("could not write {path:?}: {err}")]
+    WriteError {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+}
+
+/// Get the path that a note's page will be exported to, or None if the note is not in the root
+/// directory.
+fn export_page_path(config: &NoteConfig, out_dir: &Path, note_path: &Path) -> Option<PathBuf> {
+    let relative_path = note_path.strip_prefix(&config.root_dir).ok()?;
+    let stem = relative_path.file_stem()?.to_str()?;
+
+    Some(out_dir.join(relative_path.with_file_name(format!("{stem}.html"))))
+}
+
+fn write_export_page(path: &Path, page: &str) -> Result<(), ExportHtmlSiteError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| ExportHtmlSiteError::CreateDirectoryError {
+            directory: parent.display().to_string(),
+            err,
+        })?;
+    }
+
+    fs::write(path, page).map_err(|err| ExportHtmlSiteError::WriteError {
+        path: path.to_owned(),
+        err,
+    })
+}
+
+fn write_bundle(config: &NoteConfig, out_path: &Path) -> Result<usize, WriteBundleError> {
+    let notes = all_indexed_notes(config)?
+        .into_iter()
+        .sorted_by(|(path_a, _note_a), (path_b, _note_b)| path_a.cmp(path_b))
+        .filter_map(|(path, note)| {
+            let Ok(relative_path) = path.strip_prefix(&config.root_dir) else {
+                warning!(
+                    "Not bundling {}, as it is not in the notes directory",
+                    path.display()
+                );
+                return None;
+            };
+
+            Some(
+                fs::read(&path)
+                    .map(|contents| bundle::BundledNote {
+                        path: relative_path.to_owned(),
+                        kind: note.kind,
+                        contents,
+                    })
+                    .map_err(|err| WriteBundleError::ReadNoteError {
+                        path: path.clone(),
+                        err,
+                    }),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let file = File::create(out_path).map_err(WriteBundleError::CreateError)?;
+    bundle::write(file, &notes)?;
+
+    Ok(notes.len())
+}
+
+#[derive(Error, Debug)]
+enum WriteBundleError {
+    #[error("could not load notes from index: {0}")]
+    IndexError(#[from] AllIndexedNotesError),
+
+    #[error("could not read note at {path:?}: {err}")]
+    ReadNoteError {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("could not create bundle: {0}")]
+    CreateError(io::Error),
+
+    #[error(transparent)]
+    WriteError(#[from] bundle::WriteError),
+}
+
+fn read_bundle_into_root(
+    config: &NoteConfig,
+    bundle_path: &Path,
+) -> Result<ImportReport, ReadBundleIntoRootError> {
+    let file = File::open(bundle_path).map_err(ReadBundleIntoRootError::OpenError)?;
+    let notes = bundle::read(file)?;
+
+    // A bundle may only put notes in the directory for their kind, so that it can't write to
+    // drafts/ or attachments/, or have a daily note indexed as a regular one
+    if let Some(note) = notes.iter().find(|note| {
+        let kind_directory = config.kind_directory_path(note.kind);
+        let destination = config.root_dir.join(&note.path);
+
+        !destination
+            .parent()
+            .is_some_and(|parent| parent.starts_with(&kind_directory))
+    }) {
+        return Err(ReadBundleIntoRootError::WrongDirectoryError {
+            path: note.path.clone(),
+            kind: note.kind,
+        });
+    }
+
+    let mut index_connection = open_index_database(config)?;
+    let mut report = ImportReport::default();
+
+    for note in notes {
+        let storage_directory = config
+            .root_dir
+            .join(note.path.parent().unwrap_or(Path::new("")));
+        let preferred_file_stem = note
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let preferred_path =
+            storage_directory.join(format!("{preferred_file_stem}.{}", config.file_extension));
+
+        let stored_path = match fs::read(&preferred_path) {
+            Ok(existing) if bundle::hash(&existing) == bundle::hash(&note.contents) => {
+                report.unchanged.push(preferred_path.clone());
+
+                preferred_path
+            }
+
+            Ok(_existing) => {
+                let stored_path = store_bundled_note(
+                    config,
+                    note.kind,
+                    storage_directory,
+                    preferred_file_stem,
+                    &note.contents,
+                )?;
+                report.conflicts.push(ImportConflict {
+                    existing_path: preferred_path,
+                    imported_path: stored_path.clone(),
+                });
+
+                stored_path
+            }
+
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let stored_path = store_bundled_note(
+                    config,
+                    note.kind,
+                    storage_directory,
+                    preferred_file_stem,
+                    &note.contents,
+                )?;
+                report.imported.push(stored_path.clone());
+
+                stored_path
+            }
+
+            Err(err) => {
+                return Err(ReadBundleIntoRootError::ReadExistingError {
+                    path: preferred_path,
+                    err,
+                })
+            }
+        };
+
+        if let Err(err) = index_note(&mut index_connection, note.kind, &stored_path) {
+            warning!("could not index note at {}: {}", stored_path.display(), err);
+        }
+    }
+
+    Ok(report)
+}
+
+#[derive(Error, Debug)]
+enum ReadBundleIntoRootError {
+    #[error("could not open bundle: {0}")]
+    OpenError(io::Error),
+
+    #[error(transparent)]
+    ReadError(#[from] bundle::ReadError),
+
+    #[error("bundle has {path:?}, which is not in the directory for {kind:?} notes")]
+    WrongDirectoryError { path: PathBuf, kind: NoteKind },
+
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not read existing note at {path:?}: {err}")]
+    ReadExistingError {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("could not create directory {directory:?}: {err}")]
+    CreateDirectoryError {
+        directory: String,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("could not create temporary file: {0}")]
+    CreateTempfileError(io::Error),
+
+    #[error("could not write note to temporary file: {0}")]
+    WriteTempfileError(io::Error),
+
+    #[error(transparent)]
+    StoreNoteError(#[from] StoreNoteError),
+}
+
+/// Store a note from a bundle in the given directory, without clobbering any existing note.
+fn store_bundled_note(
+    config: &NoteConfig,
+    kind: NoteKind,
+    storage_directory: PathBuf,
+    preferred_file_stem: String,
+    contents: &[u8],
+) -> Result<PathBuf, ReadBundleIntoRootError> {
+    fs::create_dir_all(&storage_directory).map_err(|err| {
+        ReadBundleIntoRootError::CreateDirectoryError {
+            directory: storage_directory.display().to_string(),
+            err,
+        }
+    })?;
+
+    let tempfile = make_tempfile(config).map_err(ReadBundleIntoRootError::CreateTempfileError)?;
+    fs::write(&tempfile, contents).map_err(ReadBundleIntoRootError::WriteTempfileError)?;
+    let handle = TempFileHandle::open(tempfile, recovery_for(config, kind))
+        .map_err(ReadBundleIntoRootError::WriteTempfileError)?;
+
+    let store = StoreNoteIn {
+        storage_directory,
+        preferred_file_stem,
+        file_extension: config.file_extension.clone(),
+    };
+    let stored_path = store.store(handle)?;
+
+    Ok(stored_path)
+}
+
+fn import_foreign_notes(
+    config: &NoteConfig,
+    source: ImportSource,
+    directory: &Path,
+) -> Result<ImportReport, ImportForeignNotesError> {
+    let mut index_connection = open_index_database(config)?;
+    let mut report = ImportReport::default();
+    // Notes that were imported before are skipped, so that importing again doesn't duplicate them
+    let mut existing_hashes = note_file_paths(&FilesystemBackend::new(config), config)
+        .filter_map(|(_kind, path)| Some((bundle::hash(&fs::read(&path).ok()?), path)))
+        .collect::<HashMap<_, _>>();
+
+    let files = WalkDir::new(directory)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !source.skips_directory(entry.file_name())
+        })
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                warning!("Cannot traverse {}: {}", directory.display(), err);
+                None
+            }
+        })
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && entry
+                    .path()
+                    .extension()
+                    .is_some_and(|extension| source.is_note_extension(extension))
+        });
+
+    for entry in files {
+        let path = entry.path();
+        let (contents, modified_at) = match read_foreign_note(path) {
+            Ok(read) => read,
+            Err(err) => {
+                warning!("Skipping {}: {}", path.display(), err);
+                continue;
+            }
+        };
+
+        let file_stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let converted = import::convert_note(source, &file_stem, &contents, modified_at);
+
+        let Some((kind, stored_path)) =
+            store_foreign_note(config, converted, &mut existing_hashes, &mut report)?
+        else {
+            continue;
+        };
+        if let Err(err) = index_note(&mut index_connection, kind, &stored_path) {
+            warning!("could not index note at {}: {}", stored_path.display(), err);
+        }
+    }
+
+    Ok(report)
+}
+
+#[derive(Error, Debug)]
+enum ImportForeignNotesError {
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error(transparent)]
+    NameError(#[from] PeriodicNoteNameError),
+
+    #[error("could not create directory {directory:?}: {err}")]
+    CreateDirectoryError {
+        directory: String,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("could not serialize note: {0}")]
+    SerializeError(#[from] SerializeError),
+
+    #[error("could not store note: {0}")]
+    WriteNoteError(#[from] WriteNoteError),
+}
+
+/// Read a note from another tool, along with when it was last modified.
+fn read_foreign_note(path: &Path) -> Result<(String, DateTime<FixedOffset>), io::Error> {
+    let contents = fs::read_to_string(path)?;
+    let modified_at = DateTime::<Local>::from(fs::metadata(path)?.modified()?).fixed_offset();
+
+    Ok((contents, modified_at))
+}
+
+/// Store a converted note, recording where it went in the report. Returns the kind the note was
+/// stored as, and its path, or None if an identical note was already present (as when importing
+/// the same directory twice).
+fn store_foreign_note(
+    config: &NoteConfig,
+    mut converted: import::ConvertedNote,
+    existing_hashes: &mut HashMap<String, PathBuf>,
+    report: &mut ImportReport,
+) -> Result<Option<(NoteKind, PathBuf)>, ImportForeignNotesError> {
+    let mut contents = converted.contents()?;
+    if let Some(existing_path) = existing_hashes.get(&bundle::hash(contents.as_bytes())) {
+        report.unchanged.push(existing_path.clone());
+
+        return Ok(None);
+    }
+
+    let mut conflicting_daily = None;
+    if let Some(date) = converted.preamble.date {
+        let daily_path = periodic_note_path(config, Period::Daily, date)?;
+        if daily_path.exists() {
+            // The date is only meaningful for dailies
+            converted.preamble.date = None;
+            conflicting_daily = Some(daily_path);
+
+            contents = converted.contents()?;
+            if let Some(existing_path) = existing_hashes.get(&bundle::hash(contents.as_bytes())) {
+                report.unchanged.push(existing_path.clone());
+
+                return Ok(None);
+            }
+        } else {
+            create_parent_directory(&daily_path)?;
+
+            let store = StoreNoteAt {
+                destination: daily_path,
+            };
+            let stored_path = write_contents_with_store(store, config, NoteKind::Daily, &contents)?;
+            existing_hashes.insert(bundle::hash(contents.as_bytes()), stored_path.clone());
+            report.imported.push(stored_path.clone());
+
+            return Ok(Some((NoteKind::Daily, stored_path)));
+        }
+    }
+
+    let notes_directory = config.notes_directory_path();
+    let preferred_file_stem = note::filename_stem_for_title(&converted.preamble.title);
+    let preferred_path =
+        notes_directory.join(format!("{preferred_file_stem}.{}", config.file_extension));
+    let preferred_path_exists = preferred_path.exists();
+    fs::create_dir_all(&notes_directory).map_err(|err| {
+        ImportForeignNotesError::CreateDirectoryError {
+            directory: notes_directory.display().to_string(),
+            err,
+        }
+    })?;
+
+    let store = StoreNoteIn {
+        storage_directory: notes_directory,
+        preferred_file_stem,
+        file_extension: config.file_extension.clone(),
+    };
+    let stored_path = write_contents_with_store(store, config, NoteKind::Note, &contents)?;
+    existing_hashes.insert(bundle::hash(contents.as_bytes()), stored_path.clone());
+
+    match conflicting_daily {
+        Some(existing_path) => report.conflicts.push(ImportConflict {
+            existing_path,
+            imported_path: stored_path.clone(),
+        }),
+        None if preferred_path_exists => report.conflicts.push(ImportConflict {
+            existing_path: preferred_path,
+            imported_path: stored_path.clone(),
+        }),
+        None => report.imported.push(stored_path.clone()),
+    }
+
+    Ok(Some((NoteKind::Note, stored_path)))
+}
+
+fn create_parent_directory(path: &Path) -> Result<(), ImportForeignNotesError> {
+    let Some(parent) = path.parent() else {
+        return Ok(());
+    };
+
+    fs::create_dir_all(parent).map_err(|err| ImportForeignNotesError::CreateDirectoryError {
+        directory: parent.display().to_string(),
+        err,
+    })
+}
+
+fn find_orphaned_notes(config: &NoteConfig) -> Vec<OrphanedNote> {
+    find_unparsable_notes(config)
+        .into_iter()
+        .filter(|(_kind, _path, err)| err.is_missing_preamble())
+        .map(|(kind, path, _err)| OrphanedNote { path, kind })
+        .collect()
+}
+
+/// Find the files with the note file extension whose preamble is missing or cannot be parsed,
+/// sorted by path.
+fn find_unparsable_notes(
+    config: &NoteConfig,
+) -> Vec<(NoteKind, PathBuf, note::InvalidPreambleError)> {
+    note_file_paths(&FilesystemBackend::new(config), config)
+        .filter(|(_kind, path)| {
+            path.extension()
+                .is_some_and(|extension| *extension == *config.file_extension)
+        })
+        .filter_map(|(kind, path)| match File::open(&path) {
+            Ok(file) => note::extract_preamble(file)
+                .err()
+                .map(|err| (kind, path, err)),
+            Err(err) => {
+                warning!("could not read {}: {}", path.display(), err);
+                None
+            }
+        })
+        .sorted_by(|(_kind_a, path_a, _err_a), (_kind_b, path_b, _err_b)| path_a.cmp(path_b))
+        .collect()
+}
+
+fn add_preamble_to_orphan(
+    config: &NoteConfig,
+    orphan: &OrphanedNote,
+) -> Result<Preamble, AddPreambleToOrphanError> {
+    let contents = fs::read_to_string(&orphan.path).map_err(AddPreambleToOrphanError::ReadError)?;
+    if !note::extract_preamble(contents.as_bytes()).is_err_and(|err| err.is_missing_preamble()) {
+        return Err(AddPreambleToOrphanError::NotOrphaned(orphan.path.clone()));
+    }
+
+    let modified_at = fs::metadata(&orphan.path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(AddPreambleToOrphanError::ReadError)?;
+    let stem = orphan
+        .path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let date = (orphan.kind == NoteKind::Daily)
+        .then(|| daily_note_filename_date(config, &orphan.path))
+        .flatten();
+    let title = match date {
+        Some(date) => periodic_note_title(config, Period::Daily, date)?,
+        None => note::title_for_filename_stem(&stem),
+    };
+
+    let mut preamble = Preamble::new(title, DateTime::<Local>::from(modified_at).fixed_offset());
+    preamble.date = date;
+
+    let serialized_preamble = preamble.serialize()?;
+    write_contents_with_store(
+        StoreNoteOver {
+            destination: orphan.path.clone(),
+        },
+        config,
+        orphan.kind,
+        &format!("{serialized_preamble}\n\n{contents}"),
+    )?;
+
+    let mut index_connection = open_index_database(config)?;
+    index_note(&mut index_connection, orphan.kind, &orphan.path)?;
+
+    Ok(preamble)
+}
+
+#[derive(Error, Debug)]
+enum AddPreambleToOrphanError {
+    #[error("{0:?} already has a preamble")]
+    NotOrphaned(PathBuf),
+
+    #[error("could not read note: {0}")]
+    ReadError(io::Error),
+
+    #[error(transparent)]
+    NameError(#[from] PeriodicNoteNameError),
+
+    #[error("could not serialize preamble: {0}")]
+    SerializeError(#[from] SerializeError),
+
+    #[error("could not write note: {0}")]
+    WriteError(#[from] WriteNoteError),
+
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not index note: {0}")]
+    IndexNoteError(#[from] IndexNoteError),
+}
+
+fn find_health_issues(config: &NoteConfig) -> Result<Vec<HealthIssue>, FindHealthIssuesError> {
+    let indexed_notes = all_indexed_notes(config)?;
+    let (existing_notes, missing_notes): (Vec<_>, Vec<_>) = indexed_notes
+        .into_iter()
+        .sorted_by(|(path_a, _note_a), (path_b, _note_b)| path_a.cmp(path_b))
+        .partition(|(path, _note)| path.exists());
+
+    let mut issues = missing_notes
+        .into_iter()
+        .map(|(path, _note)| HealthIssue::StaleIndexEntry { path })
+        .collect::<Vec<_>>();
+
+    issues.extend(
+        existing_notes
+            .iter()
+            .into_group_map_by(|(_path, note)| note.preamble.title.to_lowercase())
+            .into_iter()
+            .filter(|(_title, notes)| notes.len() > 1)
+            .sorted_by(|(title_a, _notes_a), (title_b, _notes_b)| title_a.cmp(title_b))
+            .map(|(_title, notes)| HealthIssue::DuplicateTitle {
+                title: notes[0].1.preamble.title.clone(),
+                paths: notes
+                    .into_iter()
+                    .map(|(path, _note)| path.clone())
+                    .collect(),
+            }),
+    );
+
+    let (orphaned_notes, malformed_notes): (Vec<_>, Vec<_>) = find_unparsable_notes(config)
+        .into_iter()
+        .partition(|(_kind, _path, err)| err.is_missing_preamble());
+    issues.extend(
+        orphaned_notes
+            .into_iter()
+            .map(|(kind, path, _err)| HealthIssue::Orphaned(OrphanedNote { path, kind })),
+    );
+    issues.extend(malformed_notes.into_iter().map(|(_kind, path, err)| {
+        HealthIssue::MalformedPreamble {
+            path,
+            // Parse errors quote the line they are on; the first line alone says where
+            error: err
+                .to_string()
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }));
+
+    issues.extend(
+        existing_notes
+            .iter()
+            .filter(|(_path, note)| note.kind == NoteKind::Daily)
+            .filter_map(|(path, note)| {
+                let title_date = note::parse_date(&note.preamble.title, &config.daily.title_format)
+                    .or(note.preamble.date)?;
+                let filename_date = daily_note_filename_date(config, path)?;
+
+                (title_date != filename_date).then(|| HealthIssue::DailyDateMismatch {
+                    path: path.clone(),
+                    title_date,
+                    filename_date,
+                })
+            }),
+    );
+
+    issues.extend(
+        note_file_paths(&FilesystemBackend::new(config), config)
+            .filter(|(_kind, path)| {
+                let is_hidden = path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+                let has_note_extension = path
+                    .extension()
+                    .is_some_and(|extension| *extension == *config.file_extension);
+
+                !is_hidden && !has_note_extension
+            })
+            .sorted_by(|(_kind_a, path_a), (_kind_b, path_b)| path_a.cmp(path_b))
+            .map(|(kind, path)| {
+                let has_preamble =
+                    File::open(&path).is_ok_and(|file| note::extract_preamble(file).is_ok());
+                let renamed_path = Some(path.with_extension(&config.file_extension))
+                    .filter(|renamed_path| has_preamble && !renamed_path.exists());
+
+                HealthIssue::WrongExtension {
+                    path,
+                    kind,
+                    renamed_path,
+                }
+            }),
+    );
+
+    issues.extend(
+        preserved_tempfile_paths(config)
+            .into_iter()
+            .map(|path| HealthIssue::PreservedTempfile { path }),
+    );
+
+    issues.extend(
+        recovery::list(&config.recovery_directory_path())
+            .map_err(FindHealthIssuesError::RecoveryListError)?
+            .into_iter()
+            .map(|note| HealthIssue::RecoverableNote { path: note.path }),
+    );
+
+    Ok(issues)
+}
+
+#[derive(Error, Debug)]
+enum FindHealthIssuesError {
+    #[error(transparent)]
+    IndexLookupError(#[from] AllIndexedNotesError),
+
+    #[error("could not read recovery directory: {0}")]
+    RecoveryListError(io::Error),
+}
+
+/// Get the date of a daily note from its path, using the configured filename format, or else the
+/// default format.
+fn daily_note_filename_date(config: &NoteConfig, path: &Path) -> Option<NaiveDate> {
+    let relative_path = path
+        .strip_prefix(config.periodic_directory_path(Period::Daily))
+        .ok()?
+        .to_str()?;
+    let relative_stem = relative_path
+        .strip_suffix(&format!(".{}", config.file_extension))
+        .unwrap_or(relative_path);
+
+    note::parse_date(relative_stem, &config.daily.filename_format)
+        .or_else(|| note::date_for_filename_stem(path.file_stem()?.to_str()?))
+}
+
+/// Find notes that were left in the temporary directory. These are named like the tempfiles made
+/// by [`make_tempfile`], and have a preamble.
+fn preserved_tempfile_paths(config: &NoteConfig) -> Vec<PathBuf> {
+    let temp_dir = config
+        .temp_root_override
+        .clone()
+        .unwrap_or_else(std::env::temp_dir);
+    let entries = match fs::read_dir(&temp_dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warning!("could not read {}: {}", temp_dir.display(), err);
+            return Vec::new();
+        }
+    };
+
+    let file_extension_suffix = format!(".{}", config.file_extension);
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(TEMPFILE_PREFIX) && name.ends_with(&file_extension_suffix)
+                })
+        })
+        .filter(|path| File::open(path).is_ok_and(|file| note::extract_preamble(file).is_ok()))
+        .sorted()
+        .collect()
+}
+
+fn fix_issue(config: &NoteConfig, issue: &HealthIssue) -> Result<(), FixIssueError> {
+    match issue {
+        HealthIssue::StaleIndexEntry { path } => {
+            let mut connection = open_index_database(config)?;
+            index::delete_note(&mut connection, path)?;
+            index::prune_usage(&mut connection)?;
+
+            Ok(())
+        }
+
+        HealthIssue::WrongExtension {
+            path,
+            kind,
+            renamed_path: Some(renamed_path),
+        } => {
+            if renamed_path.exists() {
+                return Err(FixIssueError::DestinationExists(renamed_path.clone()));
+            }
+
+            fs::rename(path, renamed_path).map_err(FixIssueError::RenameError)?;
+
+            let mut connection = open_index_database(config)?;
+            index::delete_note(&mut connection, path)?;
+            index::move_attachments(&mut connection, path, renamed_path)?;
+            index_note(&mut connection, *kind, renamed_path)?;
+
+            Ok(())
+        }
+
+        _ => Err(FixIssueError::NotFixable(issue.name())),
+    }
+}
+
+#[derive(Error, Debug)]
+enum FixIssueError {
+    #[error("{0} issues cannot be fixed automatically")]
+    NotFixable(&'static str),
+
+    #[error("{0:?} already exists")]
+    DestinationExists(PathBuf),
+
+    #[error("could not rename note: {0}")]
+    RenameError(io::Error),
+
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not remove note from index: {0}")]
+    IndexDeleteError(#[from] index::DeleteError),
+
+    #[error("could not move the note's attachments in the index: {0}")]
+    MoveAttachmentsError(#[from] index::InsertError),
+
+    #[error("could not index note: {0}")]
+    IndexNoteError(#[from] IndexNoteError),
+}
+
+fn restore_recovered_note(
+    config: &NoteConfig,
+    note: &RecoverableNote,
+) -> Result<PathBuf, RestoreRecoveredNoteError> {
+    // The sidecar is in the notes root, where it may have been synced or edited, so it's not
+    // trusted to say where the note goes
+    if !is_note_destination(config, &note.destination) {
+        return Err(RestoreRecoveredNoteError::OutsideNotesError(
+            note.destination.clone(),
+        ));
+    }
+
+    let contents = fs::read(&note.path).map_err(RestoreRecoveredNoteError::ReadError)?;
+    let stored_path = store_at_or_beside(config, &note.destination, note.kind, &contents)?;
+
+    if let Err(err) = recovery::remove(note) {
+        warning!(
+            "{} was recovered, but could not be removed from the recovery directory: {}",
+            note.path.display(),
+            err
+        );
+    }
+
+    Ok(stored_path)
+}
+
+#[derive(Error, Debug)]
+enum RestoreRecoveredNoteError {
+    #[error("{0:?} is not in a note directory or the inbox, so the note will not be stored there")]
+    OutsideNotesError(PathBuf),
+
+    #[error("could not read note: {0}")]
+    ReadError(io::Error),
+
+    #[error(transparent)]
+    StoreError(#[from] StoreAtOrBesideError),
+}
+
+/// Store the contents of a note at `destination`, or if a different note has been stored there,
+/// alongside it under a new name. The stored note is indexed if it is in one of the note
+/// directories. Callers must check `destination` with [`is_note_destination`] if it was read from
+/// somewhere that could have been edited.
+fn store_at_or_beside(
+    config: &NoteConfig,
+    destination: &Path,
+    kind: NoteKind,
+    contents: &[u8],
+) -> Result<PathBuf, StoreAtOrBesideError> {
+    let (Some(storage_directory), Some(stem)) = (
+        destination.parent(),
+        destination.file_stem().and_then(|stem| stem.to_str()),
+    ) else {
+        return Err(StoreAtOrBesideError::InvalidDestination(
+            destination.to_owned(),
+        ));
+    };
+
+    let stored_path = match fs::read(destination) {
+        // The note made it there after all, so there is nothing to store
+        Ok(existing) if existing == contents => destination.to_owned(),
+
+        Ok(_existing) => {
+            let store = StoreNoteIn {
+                storage_directory: storage_directory.to_owned(),
+                preferred_file_stem: stem.to_string(),
+                file_extension: destination.extension().map_or_else(
+                    || config.file_extension.clone(),
+                    |extension| extension.to_string_lossy().into_owned(),
+                ),
+            };
+
+            store_contents(store, config, kind, contents)?
+        }
+
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            fs::create_dir_all(storage_directory).map_err(|err| {
+                StoreAtOrBesideError::CreateDirectoryError {
+                    directory: storage_directory.display().to_string(),
+                    err,
+                }
+            })?;
+
+            let store = StoreNoteAt {
+                destination: destination.to_owned(),
+            };
+
+            store_contents(store, config, kind, contents)?
+        }
+
+        Err(err) => return Err(StoreAtOrBesideError::ReadExistingError(err)),
+    };
+
+    // The inbox is stored outside of the note directories, and isn't indexed
+    let is_in_note_directory = note_directory_paths(config)
+        .iter()
+        .any(|directory| stored_path.starts_with(directory));
+    if is_in_note_directory {
+        let mut index_connection = open_index_database(config)?;
+        index_note(&mut index_connection, kind, &stored_path)?;
+    }
+
+    Ok(stored_path)
+}
+
+fn store_contents<S: StoreNote>(
+    store: S,
+    config: &NoteConfig,
+    kind: NoteKind,
+    contents: &[u8],
+) -> Result<PathBuf, StoreAtOrBesideError> {
+    let tempfile = make_tempfile(config).map_err(StoreAtOrBesideError::CreateTempfileError)?;
+    fs::write(&tempfile, contents).map_err(StoreAtOrBesideError::WriteTempfileError)?;
+    let handle = TempFileHandle::open(tempfile, recovery_for(config, kind))
+        .map_err(StoreAtOrBesideError::WriteTempfileError)?;
+
+    let stored_path = store.store(handle)?;
+
+    Ok(stored_path)
+}
+
+#[derive(Error, Debug)]
+enum StoreAtOrBesideError {
+    #[error("{0:?} is not a valid destination for a note")]
+    InvalidDestination(PathBuf),
+
+    #[error("could not read existing note: {0}")]
+    ReadExistingError(io::Error),
+
+    #[error("could not create directory {directory:?}: {err}")]
+    CreateDirectoryError {
+        directory: String,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("could not create temporary file: {0}")]
+    CreateTempfileError(io::Error),
+
+    #[error("could not write note to temporary file: {0}")]
+    WriteTempfileError(io::Error),
+
+    #[error(transparent)]
+    StoreNoteError(#[from] StoreNoteError),
+
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not index note: {0}")]
+    IndexNoteError(#[from] IndexNoteError),
+}
+
+/// Get where a note of the given kind is moved to if it cannot be stored.
+fn recovery_for(config: &NoteConfig, kind: NoteKind) -> Recovery {
+    Recovery {
+        directory: config.recovery_directory_path(),
+        kind,
+    }
+}
+
+fn store_abandoned_draft(
+    config: &NoteConfig,
+    draft: &Draft,
+    passphrase: Option<&str>,
+) -> Result<Option<PathBuf>, StoreAbandonedDraftError> {
+    check_draft_destination(config, draft)?;
+
+    let contents = fs::read(&draft.path).map_err(StoreAbandonedDraftError::ReadError)?;
+    if bundle::hash(&contents) == draft.initial_hash {
+        draft::remove(draft).map_err(StoreAbandonedDraftError::RemoveError)?;
+
+        return Ok(None);
+    }
+
+    let contents = match (draft.is_encrypted(), passphrase) {
+        (false, _) => contents,
+        (true, None) => {
+            return Err(StoreAbandonedDraftError::PassphraseRequiredError(
+                draft.path.clone(),
+            ))
+        }
+        (true, Some(passphrase)) => {
+            let contents = String::from_utf8(contents)?;
+
+            encrypted_contents(&contents, passphrase)?
+                .unwrap_or(contents)
+                .into_bytes()
+        }
+    };
+
+    let stored_path = store_at_or_beside(config, &draft.destination, draft.kind, &contents)?;
+
+    if let Err(err) = draft::remove(draft) {
+        warning!(
+            "{} was stored, but could not be removed from the drafts directory: {}",
+            draft.path.display(),
+            err
+        );
+    }
+
+    Ok(Some(stored_path))
+}
+
+/// Check that an abandoned draft is meant for a note directory or the inbox. The journal is in the
+/// notes root, where it may have been synced or edited, so it's not trusted to say where the note
+/// goes.
+fn check_draft_destination(
+    config: &NoteConfig,
+    draft: &Draft,
+) -> Result<(), StoreAbandonedDraftError> {
+    if is_note_destination(config, &draft.destination) {
+        Ok(())
+    } else {
+        Err(StoreAbandonedDraftError::OutsideNotesError(
+            draft.destination.clone(),
+        ))
+    }
+}
+
+#[derive(Error, Debug)]
+enum StoreAbandonedDraftError {
+    #[error(
+        "{0:?} is not in a note directory or the inbox, so the draft will not be stored there"
+    )]
+    OutsideNotesError(PathBuf),
+
+    #[error("could not read draft: {0}")]
+    ReadError(io::Error),
+
+    #[error("could not remove draft: {0}")]
+    RemoveError(io::Error),
+
+    #[error("{0:?} is marked as encrypted, so a passphrase is needed to store it")]
+    PassphraseRequiredError(PathBuf),
+
+    #[error("draft is marked as encrypted, but is not text: {0}")]
+    NotTextError(#[from] std::string::FromUtf8Error),
+
+    #[error("could not encrypt draft: {0}")]
+    EncryptError(#[from] EncryptNoteError),
+
+    #[error(transparent)]
+    StoreError(#[from] StoreAtOrBesideError),
+}
+
+#[derive(Error, Debug)]
+enum InnerResumeDraftError {
+    #[error(transparent)]
+    ClaimError(#[from] sidecar::WriteSidecarError),
+
+    #[error(transparent)]
+    EditorError(#[from] OpenInEditorError),
+
+    #[error(transparent)]
+    StoreError(#[from] StoreAbandonedDraftError),
+}
+
+fn attach_to_note(
+    config: &NoteConfig,
+    note_path: &Path,
+    kind: NoteKind,
+    file: &Path,
+    attached_at: DateTime<FixedOffset>,
+) -> Result<PathBuf, AttachToNoteError> {
+    let contents =
+        fs::read_to_string(note_path).map_err(|err| AttachToNoteError::ReadNoteError {
+            path: note_path.to_owned(),
+            err,
+        })?;
+
+    // The link would be added in plain text, and the attachment itself is not encrypted
+    if is_encrypted_note(&contents) {
+        return Err(AttachToNoteError::EncryptedNoteError(note_path.to_owned()));
+    }
+
+    let directory = note_attachments_directory(config, note_path);
+    fs::create_dir_all(&directory).map_err(|err| AttachToNoteError::CreateDirectoryError {
+        directory: directory.display().to_string(),
+        err,
+    })?;
+
+    let attachment_path = storage::copy_unclobbered(file, &directory)?;
+    let mut index_connection = match link_attachment(
+        config,
+        note_path,
+        kind,
+        &contents,
+        &attachment_path,
+        attached_at,
+    ) {
+        Ok(index_connection) => index_connection,
+        Err(err) => {
+            if let Err(remove_err) = fs::remove_file(&attachment_path) {
+                warning!(
+                    "Could not remove {} after failing to attach it: {remove_err}",
+                    attachment_path.display()
+                );
+            }
+
+            return Err(err);
+        }
+    };
+
+    // The note links to the attachment by now, so it's kept even if the note can't be reindexed
+    index_note(&mut index_connection, kind, note_path)?;
+
+    Ok(attachment_path)
+}
+
+/// Record the attachment in the index, and then add a link to it to the note. If the link cannot
+/// be added, the attachment is removed from the index again. Returns the open index connection.
+fn link_attachment(
+    config: &NoteConfig,
+    note_path: &Path,
+    kind: NoteKind,
+    contents: &str,
+    attachment_path: &Path,
+    attached_at: DateTime<FixedOffset>,
+) -> Result<Connection, AttachToNoteError> {
+    let mut index_connection = open_index_database(config)?;
+    index::add_attachment(&mut index_connection, note_path, attachment_path)?;
+
+    let note_directory = note_path.parent().unwrap_or(&config.root_dir);
+    let link = attachment_link(note_directory, attachment_path);
+    let linked = note::stamp_updated_at(&appended_contents(contents, &link), attached_at)
+        .map_err(|err| AttachToNoteError::LinkError {
+            path: note_path.to_owned(),
+            err,
+        })
+        .and_then(|stamped| {
+            let store = StoreNoteOver {
+                destination: note_path.to_owned(),
+            };
+
+            write_contents_with_store(store, config, kind, &stamped)
+                .map_err(AttachToNoteError::WriteNoteError)
+        });
+
+    if let Err(err) = linked {
+        if let Err(remove_err) = index::remove_attachment(&mut index_connection, attachment_path) {
+            warning!(
+                "Could not remove {} from the index after failing to link it: {remove_err}",
+                attachment_path.display()
+            );
+        }
+
+        return Err(err);
+    }
+
+    Ok(index_connection)
+}
+
+#[derive(Error, Debug)]
+enum AttachToNoteError {
+    #[error("could not read note at {path}: {err}")]
+    ReadNoteError {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("note at {0} is encrypted, so files cannot be attached to it")]
+    EncryptedNoteError(PathBuf),
+
+    #[error("could not create directory {directory:?}: {err}")]
+    CreateDirectoryError {
+        directory: String,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("could not copy attachment: {0}")]
+    CopyError(#[from] storage::CopyUnclobberedError),
+
+    #[error("could not add a link to the attachment to {path}: {err}")]
+    LinkError {
+        path: PathBuf,
+        #[source]
+        err: note::InvalidPreambleError,
+    },
+
+    #[error("could not write link to the attachment: {0}")]
+    WriteNoteError(#[from] WriteNoteError),
 
     #[error(transparent)]
-    StoreNoteError(#[from] StoreIfDifferentError),
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not record attachment in the index: {0}")]
+    RecordError(#[from] index::InsertError),
+
+    #[error(transparent)]
+    IndexNoteError(#[from] IndexNoteError),
+}
+
+/// The directory that files attached to the note at `note_path` are copied into. It's named after
+/// the note's path within the notes root, so `notes/my-note.md` has its attachments in
+/// `attachments/notes/my-note`, apart from those of `daily/my-note.md`.
+fn note_attachments_directory(config: &NoteConfig, note_path: &Path) -> PathBuf {
+    let relative_path = note_path
+        .strip_prefix(&config.root_dir)
+        .unwrap_or_else(|_err| Path::new(note_path.file_name().unwrap_or_default()));
+
+    config
+        .attachments_directory_path()
+        .join(relative_path.with_extension(""))
+}
+
+/// Build a markdown link to an attachment, from a note in `note_directory`.
+fn attachment_link(note_directory: &Path, attachment_path: &Path) -> String {
+    let name = attachment_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let target = relative_link(note_directory, attachment_path)
+        .unwrap_or_else(|| attachment_path.to_string_lossy().into_owned());
+
+    format!("[{name}]({})", target.replace(' ', "%20"))
+}
+
+fn indexed_attachments(
+    config: &NoteConfig,
+    note_path: &Path,
+) -> Result<Vec<PathBuf>, IndexedAttachmentsError> {
+    let mut connection = open_index_database(config)?;
+    let attachment_paths = index::attachments(&mut connection, note_path)?;
+
+    Ok(attachment_paths)
+}
+
+#[derive(Error, Debug)]
+enum IndexedAttachmentsError {
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not look up attachments: {0}")]
+    LookupError(#[from] IndexLookupError),
+}
+
+fn delete_note_and_attachments(
+    backend: &dyn Backend,
+    config: &NoteConfig,
+    path: &Path,
+) -> Result<(), DeleteNoteAndAttachmentsError> {
+    let mut connection = open_index_database(config)?;
+    let attachment_paths = index::attachments(&mut connection, path)
+        .map_err(DeleteNoteAndAttachmentsError::LookupError)?;
+
+    match backend.delete(path) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(DeleteNoteAndAttachmentsError::RemoveNoteError {
+                path: path.to_owned(),
+                err,
+            })
+        }
+    }
+
+    index::delete_note(&mut connection, path)?;
+    index::prune_usage(&mut connection)?;
+
+    let attachments_directory = config.attachments_directory_path();
+    for attachment_path in &attachment_paths {
+        match fs::remove_file(attachment_path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                warning!(
+                    "Could not delete attachment {}: {err}",
+                    attachment_path.display()
+                );
+            }
+        }
+
+        // Tidy up the note's attachment directory, once it has nothing left in it
+        for directory in attachment_path
+            .ancestors()
+            .skip(1)
+            .take_while(|directory| directory.starts_with(&attachments_directory))
+        {
+            if fs::remove_dir(directory).is_err() {
+                break;
+            }
+        }
+    }
+
+    index::delete_attachments(&mut connection, path)?;
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+enum DeleteNoteAndAttachmentsError {
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not look up attachments: {0}")]
+    LookupError(IndexLookupError),
+
+    #[error("could not delete note at {path}: {err}")]
+    RemoveNoteError {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("could not remove note from the index: {0}")]
+    IndexDeleteError(#[from] index::DeleteError),
+}
+
+/// Append a line of text to the contents of a note, separated from what is already there by a
+/// blank line.
+fn appended_contents(contents: &str, text: &str) -> String {
+    let separator = if contents.ends_with("\n\n") {
+        ""
+    } else if contents.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+
+    format!("{contents}{separator}{text}\n")
+}
+
+/// Make a file in the drafts directory for a new note to be written to, so that it can be picked
+/// up again if quicknotes exits before the note is stored.
+fn make_draft_tempfile(config: &NoteConfig) -> Result<TempPath, io::Error> {
+    let drafts_directory = config.drafts_directory_path();
+    fs::create_dir_all(&drafts_directory)?;
+
+    TempFileBuilder::new()
+        .prefix(DRAFT_PREFIX)
+        .suffix(&format!(".{}", config.file_extension))
+        .tempfile_in(drafts_directory)
+        .map(NamedTempFile::into_temp_path)
+}
+
+fn make_tempfile(config: &NoteConfig) -> Result<TempPath, io::Error> {
+    let mut builder = TempFileBuilder::new();
+    builder.prefix(TEMPFILE_PREFIX);
+    let file_extension_suffix = format!(".{}", config.file_extension);
+    let builder = builder.suffix(&file_extension_suffix);
+
+    if let Some(temp_dir) = config.temp_root_override.as_ref() {
+        builder
+            .tempfile_in(temp_dir)
+            .map(NamedTempFile::into_temp_path)
+    } else {
+        builder.tempfile().map(NamedTempFile::into_temp_path)
+    }
+}
+
+fn write_initial_contents(
+    preamble: &Preamble,
+    body: &str,
+    path: &Path,
+) -> Result<String, WriteInitialContentsError> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(false)
+        .open(path)
+        .map_err(WriteInitialContentsError::OpenError)?;
+
+    let serialized_preamble = preamble.serialize()?;
+    let to_write = format!("{serialized_preamble}\n\n{body}");
+    file.write_all(to_write.as_bytes())
+        .map_err(WriteInitialContentsError::WriteError)?;
+
+    Ok(to_write)
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+enum WriteInitialContentsError {
+    OpenError(io::Error),
+    EncodeError(#[from] SerializeError),
+    WriteError(io::Error),
+}
+
+fn open_existing_note<E: Editor>(
+    config: &NoteConfig,
+    editor: E,
+    kind: NoteKind,
+    path: &Path,
+) -> Result<(), OpenExistingNoteError> {
+    ensure_note_exists(path).map_err(|error| OpenExistingNoteError::LookupError {
+        path: path.to_owned(),
+        error,
+    })?;
+    open_existing_note_in_editor(
+        config,
+        editor,
+        kind,
+        path,
+        CursorPlacement::Start,
+        Local::now().fixed_offset(),
+    )?;
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+enum OpenExistingNoteError {
+    #[error("could not open note at {path}: {error}")]
+    LookupError {
+        path: PathBuf,
+        #[source]
+        error: io::Error,
+    },
+
+    #[error(transparent)]
+    OpenNoteInEditorError(#[from] OpenExistingNoteInEditorError),
+}
+
+fn read_note_contents(
+    backend: &dyn Backend,
+    path: &Path,
+) -> Result<NoteContents, ReadNoteContentsError> {
+    let contents = backend
+        .read(path)
+        .map_err(|error| ReadNoteContentsError::OpenError {
+            path: path.to_owned(),
+            error,
+        })?;
+
+    let (preamble, body) =
+        note::extract_preamble_and_body(contents.as_bytes()).map_err(|error| {
+            ReadNoteContentsError::PreambleError {
+                path: path.to_owned(),
+                error,
+            }
+        })?;
+
+    Ok(NoteContents { preamble, body })
+}
+
+#[derive(Error, Debug)]
+enum ReadNoteContentsError {
+    #[error("could not open note at {path}: {error}")]
+    OpenError {
+        path: PathBuf,
+        #[source]
+        error: io::Error,
+    },
+
+    #[error("could not read preamble from note at {path}: {error}")]
+    PreambleError {
+        path: PathBuf,
+        #[source]
+        error: note::InvalidPreambleError,
+    },
+}
+
+fn ensure_note_exists(path: &Path) -> Result<(), io::Error> {
+    fs::metadata(path).and_then(|metadata| {
+        if metadata.is_dir() {
+            Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                "file is a directory",
+            ))
+        } else {
+            Ok(())
+        }
+    })
+}
+
+fn open_existing_note_in_editor<E: Editor>(
+    config: &NoteConfig,
+    editor: E,
+    kind: NoteKind,
+    path: &Path,
+    placement: CursorPlacement,
+    edited_at: DateTime<FixedOffset>,
+) -> Result<(), OpenExistingNoteInEditorError> {
+    let original_contents =
+        fs::read(path).map_err(|err| OpenExistingNoteInEditorError::ReadError {
+            path: path.to_owned(),
+            err,
+        })?;
+
+    // Editing the ciphertext directly could only corrupt the note
+    if is_encrypted_note(&String::from_utf8_lossy(&original_contents)) {
+        return Err(OpenExistingNoteInEditorError::EncryptedError {
+            path: path.to_owned(),
+        });
+    }
+
+    open_in_editor(editor, path, placement)?;
+    stamp_if_changed(config, kind, path, &original_contents, edited_at);
+
+    let mut index_connection = open_index_database(config)?;
+    record_note_open(&mut index_connection, path);
+
+    index_note(&mut index_connection, kind, path)
+        .or_else(|err| {
+            let IndexNoteError::PreambleError(err) = err else {
+                return Err(err)
+            };
+
+            match index::delete_note(&mut index_connection, path) {
+                Ok(()) => {
+                    warning!("After editing, the note could not be reindexed. It has been removed from the index. Original error: {err}");
+                    Ok(())
+                }
+
+                Err(delete_err) => {
+                    warning!("After editing, the note could not be reindexed. There was a subsequent failure that prevented it from being removed from the index, so there is now a stale entry. You can fix this by running `quicknotes index`. Original error: {err}; Delete error: {delete_err}");
+                    Ok(())
+                }
+            }
+        })?;
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+enum OpenExistingNoteInEditorError {
+    #[error("could not read note at {path}: {err}")]
+    ReadError {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("note at {path} is encrypted, and can only be opened with its passphrase")]
+    EncryptedError { path: PathBuf },
+
+    #[error(transparent)]
+    EditorSpawnError(#[from] OpenInEditorError),
+
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error(transparent)]
+    IndexNoteError(#[from] IndexNoteError),
+}
+
+fn open_encrypted_note_in_editor<E: Editor>(
+    config: &NoteConfig,
+    editor: E,
+    kind: NoteKind,
+    path: &Path,
+    passphrase: &str,
+    edited_at: DateTime<FixedOffset>,
+) -> Result<(), OpenEncryptedNoteInEditorError> {
+    let contents =
+        fs::read_to_string(path).map_err(|err| OpenEncryptedNoteInEditorError::ReadError {
+            path: path.to_owned(),
+            err,
+        })?;
+
+    let (plain_contents, was_encrypted) = match decrypted_contents(&contents, passphrase)? {
+        Some(plain_contents) => (plain_contents, true),
+        None => (contents, false),
+    };
+
+    let tempfile = make_tempfile(config).map_err(OpenEncryptedNoteInEditorError::TempfileError)?;
+    fs::write(&tempfile, &plain_contents).map_err(OpenEncryptedNoteInEditorError::TempfileError)?;
+    open_in_editor(editor, &tempfile, CursorPlacement::Start)?;
+
+    let edited_contents =
+        fs::read_to_string(&tempfile).map_err(OpenEncryptedNoteInEditorError::TempfileError)?;
+    let unchanged = edited_contents == plain_contents;
+    if !unchanged || !was_encrypted {
+        let edited_contents = if unchanged {
+            edited_contents
+        } else {
+            note::stamp_updated_at(&edited_contents, edited_at).unwrap_or(edited_contents)
+        };
+
+        if let Err(err) = write_encrypted_note(config, kind, path, &edited_contents, passphrase) {
+            let kept_path = tempfile.to_path_buf();
+            tempfile.keep().map_err(|keep_err| {
+                OpenEncryptedNoteInEditorError::KeepTempfileError {
+                    path: path.to_owned(),
+                    err: keep_err.error,
+                }
+            })?;
+
+            return Err(OpenEncryptedNoteInEditorError::WriteError {
+                path: path.to_owned(),
+                kept_path,
+                err: Box::new(err),
+            });
+        }
+    }
+
+    let mut index_connection = open_index_database(config)?;
+    record_note_open(&mut index_connection, path);
+    index_note(&mut index_connection, kind, path)?;
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+enum OpenEncryptedNoteInEditorError {
+    #[error("could not read note at {path}: {err}")]
+    ReadError {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error(transparent)]
+    DecryptError(#[from] DecryptNoteError),
+
+    #[error("could not write decrypted note to a temporary file: {0}")]
+    TempfileError(io::Error),
+
+    #[error(transparent)]
+    EditorSpawnError(#[from] OpenInEditorError),
+
+    #[error("could not write note back to {path}; the edited note is at {kept_path}: {err}")]
+    WriteError {
+        path: PathBuf,
+        kept_path: PathBuf,
+        #[source]
+        err: Box<EncryptNoteError>,
+    },
+
+    #[error("could not write note back to {path}, nor keep the edited note: {err}")]
+    KeepTempfileError {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
 
     #[error(transparent)]
-    EditorSpawnError(#[from] OpenInEditorError),
+    IndexOpenError(#[from] IndexOpenError),
 
     #[error(transparent)]
     IndexNoteError(#[from] IndexNoteError),
+}
+
+fn read_decrypted_note_contents(
+    path: &Path,
+    passphrase: &str,
+) -> Result<NoteContents, ReadDecryptedNoteContentsError> {
+    let mut contents = read_note_contents(&FilesystemBackend::default(), path)?;
+    if crypt::is_encrypted(&contents.body) {
+        contents.body = crypt::decrypt(&contents.body, passphrase).map_err(|err| {
+            ReadDecryptedNoteContentsError::DecryptError {
+                path: path.to_owned(),
+                err,
+            }
+        })?;
+    }
 
+    Ok(contents)
+}
+
+#[derive(Error, Debug)]
+enum ReadDecryptedNoteContentsError {
     #[error(transparent)]
-    IndexOpenError(#[from] IndexOpenError),
+    ReadError(#[from] ReadNoteContentsError),
+
+    #[error("could not decrypt note at {path}: {err}")]
+    DecryptError {
+        path: PathBuf,
+        #[source]
+        err: crypt::DecryptError,
+    },
 }
 
-fn make_tempfile(config: &NoteConfig) -> Result<TempPath, io::Error> {
-    let mut builder = TempFileBuilder::new();
-    let file_extension_suffix = format!(".{}", config.file_extension);
-    let builder = builder.suffix(&file_extension_suffix);
+/// Check if the note with the given contents has an encrypted body.
+fn is_encrypted_note(contents: &str) -> bool {
+    note::extract_preamble_and_body(contents.as_bytes())
+        .is_ok_and(|(_preamble, body)| crypt::is_encrypted(&body))
+}
 
-    if let Some(temp_dir) = config.temp_root_override.as_ref() {
-        builder
-            .tempfile_in(temp_dir)
-            .map(NamedTempFile::into_temp_path)
-    } else {
-        builder.tempfile().map(NamedTempFile::into_temp_path)
+/// Get the given note contents with the body decrypted, or None if the body is not encrypted.
+fn decrypted_contents(
+    contents: &str,
+    passphrase: &str,
+) -> Result<Option<String>, DecryptNoteError> {
+    let (_preamble, body) = note::extract_preamble_and_body(contents.as_bytes())?;
+    if !crypt::is_encrypted(&body) {
+        return Ok(None);
     }
+
+    let preamble_text = &contents[..contents.len() - body.len()];
+    let plain_body = crypt::decrypt(&body, passphrase)?;
+
+    Ok(Some(format!("{preamble_text}{plain_body}")))
 }
 
-fn write_preamble(preamble: &Preamble, path: &Path) -> Result<String, WritePreambleError> {
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(false)
-        .open(path)
-        .map_err(WritePreambleError::OpenError)?;
+#[derive(Error, Debug)]
+enum DecryptNoteError {
+    #[error("could not read preamble: {0}")]
+    PreambleError(#[from] note::InvalidPreambleError),
 
-    let serialized_preamble = preamble.serialize()?;
-    let to_write = format!("{serialized_preamble}\n\n");
-    file.write_all(to_write.as_bytes())
-        .map_err(WritePreambleError::WriteError)?;
+    #[error(transparent)]
+    DecryptError(#[from] crypt::DecryptError),
+}
 
-    Ok(to_write)
+/// Get the given note contents with the body encrypted, or None if the preamble does not mark it as
+/// encrypted or it already is. Unlike elsewhere, an invalid preamble is an error, so that a note
+/// which was meant to be encrypted is never written out in plain text by mistake.
+fn encrypted_contents(
+    contents: &str,
+    passphrase: &str,
+) -> Result<Option<String>, EncryptNoteError> {
+    let (preamble, body) = note::extract_preamble_and_body(contents.as_bytes())?;
+    if !preamble.encrypted || crypt::is_encrypted(&body) {
+        return Ok(None);
+    }
+
+    let preamble_text = &contents[..contents.len() - body.len()];
+    let encrypted_body = crypt::encrypt(&body, passphrase)?;
+
+    Ok(Some(format!("{preamble_text}\n{encrypted_body}")))
 }
 
-#[derive(Error, Debug)]
-#[error(transparent)]
-enum WritePreambleError {
-    OpenError(io::Error),
-    EncodeError(#[from] SerializeError),
-    WriteError(io::Error),
+/// Encrypt the body of the new note at `path` in place, if it has been edited from
+/// `original_contents`. This is only for the temporary file a new note is written in, which is
+/// stored somewhere safe afterwards.
+fn encrypt_edited_note(
+    path: &Path,
+    original_contents: &str,
+    passphrase: &str,
+) -> Result<(), EncryptNoteError> {
+    let contents = fs::read_to_string(path).map_err(EncryptNoteError::ReadError)?;
+    if contents == original_contents {
+        return Ok(());
+    }
+
+    if let Some(encrypted_contents) = encrypted_contents(&contents, passphrase)? {
+        fs::write(path, encrypted_contents).map_err(EncryptNoteError::WriteError)?;
+    }
+
+    Ok(())
 }
 
-fn open_existing_note<E: Editor>(
+/// Replace the note at `path` with the given contents, encrypting the body if the preamble marks
+/// it as encrypted.
+fn write_encrypted_note(
     config: &NoteConfig,
-    editor: E,
     kind: NoteKind,
     path: &Path,
-) -> Result<(), OpenExistingNoteError> {
-    ensure_note_exists(path).map_err(|error| OpenExistingNoteError::LookupError {
-        path: path.to_owned(),
-        error,
-    })?;
-    open_existing_note_in_editor(config, editor, kind, path)?;
+    contents: &str,
+    passphrase: &str,
+) -> Result<(), EncryptNoteError> {
+    let contents = encrypted_contents(contents, passphrase)?.unwrap_or_else(|| contents.to_owned());
+    let store = StoreNoteOver {
+        destination: path.to_owned(),
+    };
+    write_contents_with_store(store, config, kind, &contents)?;
 
     Ok(())
 }
 
 #[derive(Error, Debug)]
-#[error(transparent)]
-enum OpenExistingNoteError {
-    #[error("could not open note at {path}: {error}")]
-    LookupError {
-        path: PathBuf,
-        #[source]
-        error: io::Error,
-    },
+enum EncryptNoteError {
+    #[error("could not read note: {0}")]
+    ReadError(io::Error),
+
+    #[error("could not read preamble: {0}")]
+    PreambleError(#[from] note::InvalidPreambleError),
 
     #[error(transparent)]
-    OpenNoteInEditorError(#[from] OpenExistingNoteInEditorError),
-}
+    EncryptError(#[from] crypt::EncryptError),
 
-fn ensure_note_exists(path: &Path) -> Result<(), io::Error> {
-    fs::metadata(path).and_then(|metadata| {
-        if metadata.is_dir() {
-            Err(io::Error::new(
-                io::ErrorKind::IsADirectory,
-                "file is a directory",
-            ))
-        } else {
-            Ok(())
-        }
-    })
+    #[error("could not write note: {0}")]
+    WriteError(io::Error),
+
+    #[error("could not store note: {0}")]
+    StoreError(#[from] WriteNoteError),
 }
 
-fn open_existing_note_in_editor<E: Editor>(
+/// Set `updated_at` in the note's preamble if its contents differ from `original_contents`.
+///
+/// Failing to do so is not fatal, as the edit itself has already been saved. If the preamble
+/// was made invalid by the edit, it is left as is; the note will fail to be indexed, which is
+/// reported separately.
+fn stamp_if_changed(
     config: &NoteConfig,
-    editor: E,
     kind: NoteKind,
     path: &Path,
-) -> Result<(), OpenExistingNoteInEditorError> {
-    open_in_editor(editor, path)?;
-
-    let mut index_connection = open_index_database(config)?;
-
-    index_note(&mut index_connection, kind, path)
-        .or_else(|err| {
-            let IndexNoteError::PreambleError(err) = err else {
-                return Err(err)
-            };
+    original_contents: &[u8],
+    edited_at: DateTime<FixedOffset>,
+) {
+    let edited_contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warning!(
+                "Could not read {} after editing to update its modification time: {err}",
+                path.display()
+            );
+            return;
+        }
+    };
 
-            match index::delete_note(&mut index_connection, path) {
-                Ok(()) => {
-                    warning!("After editing, the note could not be reindexed. It has been removed from the index. Original error: {err}");
-                    Ok(())
-                }
+    if edited_contents.as_bytes() == original_contents {
+        return;
+    }
 
-                Err(delete_err) => {
-                    warning!("After editing, the note could not be reindexed. There was a subsequent failure that prevented it from being removed from the index, so there is now a stale entry. You can fix this by running `quicknotes index`. Original error: {err}; Delete error: {delete_err}");
-                    Ok(())
-                }
-            }
-        })?;
+    let Ok(stamped_contents) = note::stamp_updated_at(&edited_contents, edited_at) else {
+        return;
+    };
 
-    Ok(())
+    let store = StoreNoteOver {
+        destination: path.to_owned(),
+    };
+    if let Err(err) = write_contents_with_store(store, config, kind, &stamped_contents) {
+        warning!(
+            "Could not update the modification time of {}: {err}",
+            path.display()
+        );
+    }
 }
 
-#[derive(Error, Debug)]
-#[allow(clippy::enum_variant_names)]
-enum OpenExistingNoteInEditorError {
-    #[error(transparent)]
-    EditorSpawnError(#[from] OpenInEditorError),
+/// Where the editor's cursor should be placed when a note is opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CursorPlacement {
+    /// Leave it up to the editor (which is generally the start of the file).
+    Start,
+    /// Place the cursor after the last character in the note.
+    End,
+}
 
-    #[error(transparent)]
-    IndexOpenError(#[from] IndexOpenError),
+fn open_in_editor<E: Editor>(
+    editor: E,
+    path: &Path,
+    placement: CursorPlacement,
+) -> Result<(), OpenInEditorError> {
+    let position = match placement {
+        CursorPlacement::Start => None,
+        // The position is only a convenience; if we can't read the note here, the editor
+        // will almost certainly fail to as well, and it can report that to the user.
+        CursorPlacement::End => fs::read_to_string(path)
+            .ok()
+            .map(|contents| Position::end_of(&contents)),
+    };
 
-    #[error(transparent)]
-    IndexNoteError(#[from] IndexNoteError),
-}
+    let edit_result = match position {
+        Some(position) => editor.edit_at(path, position),
+        None => editor.edit(path),
+    };
 
-fn open_in_editor<E: Editor>(editor: E, path: &Path) -> Result<(), OpenInEditorError> {
-    editor.edit(path).map_err(|err| OpenInEditorError {
+    let outcome = edit_result.map_err(|err| OpenInEditorError::SpawnError {
         editor: editor.name().to_owned(),
         err,
-    })
+    })?;
+
+    match outcome {
+        EditOutcome::Completed => Ok(()),
+        EditOutcome::Aborted => Err(OpenInEditorError::Aborted {
+            editor: editor.name().to_owned(),
+        }),
+    }
 }
 
 #[derive(Error, Debug)]
-#[error("could not spawn editor '{editor}': {err}")]
-struct OpenInEditorError {
-    editor: String,
-    #[source]
-    err: io::Error,
+enum OpenInEditorError {
+    #[error("could not spawn editor '{editor}': {err}")]
+    SpawnError {
+        editor: String,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("editor '{editor}' exited unsuccessfully; aborting")]
+    Aborted { editor: String },
 }
 
-fn index_all_notes(config: &NoteConfig) -> Result<(), IndexAllNotesError> {
+fn index_all_notes(backend: &dyn Backend, config: &NoteConfig) -> Result<(), IndexAllNotesError> {
+    let mut connection = open_index_database(config)?;
     // This is a bit of a hack, but is easier than trying to prune stale entries from
     // the index
-    reset_index_database(config)?;
-    let mut connection = open_index_database(config)?;
+    index::reset(&mut connection)?;
 
-    for (kind, path) in note_file_paths(config) {
-        if let Err(err) = index_note(&mut connection, kind, &path) {
+    for (kind, path) in note_file_paths(backend, config) {
+        if let Err(err) = index_note_in(&mut connection, backend, kind, &path) {
             warning!("could not index note at {}: {}", path.display(), err);
         }
     }
 
+    index::prune_usage(&mut connection).map_err(IndexAllNotesError::PruneUsageError)?;
+
     Ok(())
 }
 
@@ -500,6 +4046,9 @@ enum IndexAllNotesError {
 
     #[error(transparent)]
     IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not remove usage of deleted notes: {0}")]
+    PruneUsageError(index::DeleteError),
 }
 
 fn all_indexed_notes(
@@ -520,6 +4069,144 @@ enum AllIndexedNotesError {
     QueryError(#[from] IndexLookupError),
 }
 
+fn indexed_note_usage(
+    config: &NoteConfig,
+) -> Result<HashMap<PathBuf, NoteUsage>, IndexedNoteUsageError> {
+    let mut connection = open_index_database(config)?;
+    let usage = index::note_usage(&mut connection)?;
+
+    Ok(usage)
+}
+
+#[derive(Error, Debug)]
+enum IndexedNoteUsageError {
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not query index database: {0}")]
+    QueryError(#[from] IndexLookupError),
+}
+
+fn all_indexed_tasks(config: &NoteConfig) -> Result<Vec<IndexedTask>, AllIndexedTasksError> {
+    let mut connection = open_index_database(config)?;
+    let tasks = index::all_tasks(&mut connection)?;
+
+    Ok(tasks)
+}
+
+#[derive(Error, Debug)]
+enum AllIndexedTasksError {
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not query index database: {0}")]
+    QueryError(#[from] IndexLookupError),
+}
+
+fn toggle_task_in_note(
+    config: &NoteConfig,
+    path: &Path,
+    line_number: usize,
+    edited_at: DateTime<FixedOffset>,
+) -> Result<Task, ToggleTaskInNoteError> {
+    let mut index_connection = open_index_database(config)?;
+    let kind = index::note_kind(&mut index_connection, path)?.ok_or_else(|| {
+        ToggleTaskInNoteError::NotIndexedError {
+            path: path.to_owned(),
+        }
+    })?;
+
+    let contents = fs::read_to_string(path).map_err(|err| ToggleTaskInNoteError::ReadError {
+        path: path.to_owned(),
+        err,
+    })?;
+
+    let (toggled_contents, task) = task::toggle_task(&contents, line_number).ok_or_else(|| {
+        ToggleTaskInNoteError::NoTaskError {
+            path: path.to_owned(),
+            line_number,
+        }
+    })?;
+
+    let stamped_contents =
+        note::stamp_updated_at(&toggled_contents, edited_at).unwrap_or(toggled_contents.clone());
+
+    // Stamping may have added a line to the preamble, which moves the task down.
+    let task = Task {
+        line_number: task.line_number + stamped_contents.lines().count()
+            - toggled_contents.lines().count(),
+        ..task
+    };
+
+    let store = StoreNoteOver {
+        destination: path.to_owned(),
+    };
+    write_contents_with_store(store, config, kind, &stamped_contents)?;
+
+    index_note(&mut index_connection, kind, path)?;
+
+    Ok(task)
+}
+
+#[derive(Error, Debug)]
+enum ToggleTaskInNoteError {
+    #[error("could not read note at {path}: {err}")]
+    ReadError {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("there is no task on line {line_number} of {path}")]
+    NoTaskError { path: PathBuf, line_number: usize },
+
+    #[error("could not write note: {0}")]
+    WriteError(#[from] WriteNoteError),
+
+    #[error("{path} is not in the index, so its tasks cannot be toggled. You can fix this by running `quicknotes index`")]
+    NotIndexedError { path: PathBuf },
+
+    #[error(transparent)]
+    IndexOpenError(#[from] IndexOpenError),
+
+    #[error("could not query index database: {0}")]
+    QueryError(#[from] IndexLookupError),
+
+    #[error(transparent)]
+    IndexNoteError(#[from] IndexNoteError),
+}
+
+/// Record that the note was opened. Failing to do so only affects the ordering of notes, so
+/// this is not treated as an error.
+fn record_note_open(index_connection: &mut Connection, path: &Path) {
+    if let Err(err) = index::record_open(index_connection, path, Utc::now()) {
+        warning!("could not record that the note was opened: {err}");
+    }
+}
+
+/// Get the daily notes in the index, keyed by the date they are for. The date is taken from the
+/// note's preamble, or its filename for notes made before dates were stored there. Notes with
+/// neither are skipped.
+fn indexed_dailies_by_date(
+    config: &NoteConfig,
+) -> Result<BTreeMap<NaiveDate, PathBuf>, KindedIndexedNotesError> {
+    let dailies = kinded_indexed_notes(config, NoteKind::Daily)?;
+    let dailies_by_date = dailies
+        .into_iter()
+        .filter_map(|(path, note)| Some((daily_note_date(&path, &note)?, path)))
+        .collect();
+
+    Ok(dailies_by_date)
+}
+
+/// Get the day a daily note is for, from its preamble if it is recorded there, or from its filename
+/// otherwise.
+fn daily_note_date(path: &Path, note: &IndexedNote) -> Option<NaiveDate> {
+    note.preamble
+        .date
+        .or_else(|| note::date_for_filename_stem(path.file_stem()?.to_str()?))
+}
+
 fn kinded_indexed_notes(
     config: &NoteConfig,
     kind: NoteKind,
@@ -539,55 +4226,59 @@ enum KindedIndexedNotesError {
     QueryError(#[from] IndexLookupError),
 }
 
-fn reset_index_database(config: &NoteConfig) -> Result<(), index::ResetError> {
-    index::reset(&config.index_db_path())
-}
-
 fn open_index_database(config: &NoteConfig) -> Result<Connection, IndexOpenError> {
     index::open(&config.index_db_path())
 }
 
+/// Check if a note may be stored at `destination`, i.e. it is in one of the note directories or is
+/// the inbox. Destinations read from files in the notes root are checked with this, so that
+/// editing those files can't make quicknotes write anywhere else.
+fn is_note_destination(config: &NoteConfig, destination: &Path) -> bool {
+    if destination
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return false;
+    }
+
+    *destination == config.inbox_path()
+        || note_directory_paths(config)
+            .iter()
+            .any(|directory| destination.starts_with(directory) && destination != directory)
+}
+
+/// Get every directory that notes are stored in.
+fn note_directory_paths(config: &NoteConfig) -> [PathBuf; 4] {
+    [
+        config.notes_directory_path(),
+        config.periodic_directory_path(Period::Daily),
+        config.periodic_directory_path(Period::Weekly),
+        config.periodic_directory_path(Period::Monthly),
+    ]
+}
+
 /// Get all note file paths in a best-effort fashion. If there is an error where some
 /// notes cannot be read, warnings will be logged.
-fn note_file_paths(config: &NoteConfig) -> impl Iterator<Item = (NoteKind, PathBuf)> {
-    WalkDir::new(config.notes_directory_path())
+fn note_file_paths<'a>(
+    backend: &'a dyn Backend,
+    config: &NoteConfig,
+) -> impl Iterator<Item = (NoteKind, PathBuf)> + 'a {
+    let periodic_directories = [Period::Daily, Period::Weekly, Period::Monthly]
+        .map(|period| (period.note_kind(), config.periodic_directory_path(period)));
+
+    [(NoteKind::Note, config.notes_directory_path())]
         .into_iter()
-        .map(|entry| (NoteKind::Note, entry))
-        .chain(
-            WalkDir::new(config.daily_directory_path())
-                .into_iter()
-                .map(|entry| (NoteKind::Daily, entry)),
-        )
-        .filter_map(|(note_kind, entry_res)| {
-            // skip entires we can't read, so we can get the rest
-            unpack_walkdir_entry_result(entry_res)
-                .ok()
-                .and_then(|entry| {
-                    let isnt_dir = !entry.file_type().is_dir();
-                    isnt_dir.then_some((note_kind, entry.into_path()))
-                })
-        })
-}
+        .chain(periodic_directories)
+        .flat_map(move |(note_kind, directory)| {
+            // entries we can't read are skipped by the backend, so we can get the rest
+            let paths = backend.list(&directory).unwrap_or_else(|err| {
+                warning!("Cannot traverse {}: {err}", directory.display().to_string());
 
-fn unpack_walkdir_entry_result(
-    entry_res: Result<DirEntry, walkdir::Error>,
-) -> Result<DirEntry, ()> {
-    match entry_res {
-        Ok(entry) => Ok(entry),
-        Err(err) => {
-            if let Some(path) = err.path() {
-                warning!(
-                    "Cannot traverse {}: {}",
-                    path.display().to_string(),
-                    io::Error::from(err)
-                );
-            } else {
-                warning!("Cannot traverse notes: {}", io::Error::from(err));
-            }
+                Vec::new()
+            });
 
-            Err(())
-        }
-    }
+            paths.into_iter().map(move |path| (note_kind, path))
+        })
 }
 
 fn index_note(
@@ -595,10 +4286,35 @@ fn index_note(
     kind: NoteKind,
     path: &Path,
 ) -> Result<(), IndexNoteError> {
-    let mut file = File::open(path).map_err(IndexNoteError::OpenError)?;
-    let preamble = note::extract_preamble(&mut file).map_err(IndexNoteError::PreambleError)?;
+    index_note_in(index_connection, &FilesystemBackend::default(), kind, path)
+}
+
+fn index_note_in(
+    index_connection: &mut Connection,
+    backend: &dyn Backend,
+    kind: NoteKind,
+    path: &Path,
+) -> Result<(), IndexNoteError> {
+    let contents = backend.read(path).map_err(IndexNoteError::OpenError)?;
+    let (preamble, body) = note::extract_preamble_and_body(contents.as_bytes())
+        .map_err(IndexNoteError::PreambleError)?;
+
+    // Nothing from the body of an encrypted note may end up in the index
+    let tasks = if preamble.encrypted {
+        if !crypt::is_encrypted(&body) {
+            warning!(
+                "{} is marked as encrypted, but its body is not. Open it with `quicknotes open` to encrypt it.",
+                path.display()
+            );
+        }
+
+        Vec::new()
+    } else {
+        task::tasks_in_note(&contents)
+    };
 
-    index::add_note(index_connection, &preamble, kind, path).map_err(IndexNoteError::IndexError)
+    index::add_note(index_connection, &preamble, kind, path).map_err(IndexNoteError::IndexError)?;
+    index::replace_tasks(index_connection, path, &tasks).map_err(IndexNoteError::IndexError)
 }
 
 #[derive(Error, Debug)]
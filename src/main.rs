@@ -1,57 +1,250 @@
 #![warn(clippy::all, clippy::pedantic)]
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt::Display;
 use std::fs::{self, File};
-use std::io::{self, Read, Write};
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
-use std::{env, process};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::{env, process, thread};
 
 use anyhow::anyhow;
-use chrono::{DateTime, FixedOffset, Local, NaiveDate, Timelike};
-use chrono_english::Dialect;
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Local, Months, NaiveDate, NaiveTime, Offset, Utc,
+};
+use chrono_tz::Tz;
 use clap::builder::PossibleValuesParser;
-use clap::{Arg, Command as ClapCommand};
+use clap::{value_parser, Arg, ArgAction, Command as ClapCommand};
 use colored::Colorize;
+use crossterm::event::{Event as CrosstermEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue, terminal};
 use directories::{ProjectDirs, UserDirs};
 use itertools::Itertools;
 use nucleo_picker::error::PickError;
+use nucleo_picker::event::{keybind_default, Event};
 use nucleo_picker::nucleo::pattern::CaseMatching;
+use nucleo_picker::render::DisplayRenderer;
 use nucleo_picker::{Picker, PickerOptions, Render};
-use quicknotes::{open_note, CommandEditor, IndexedNote, NoteConfig};
+use quicknotes::{
+    open_note, CommandEditor, DailyConfig, DateDialect, Direction, Draft, FuzzyDateError,
+    HealthIssue, ImportSource, InboxConfig, InboxEntry, IndexedNote, IndexedTask, NoteConfig,
+    NoteContents, NoteKind, NoteUsage, Period, DEFAULT_DAILY_FORMAT, DEFAULT_INBOX_FILENAME,
+};
 use serde::{de, Deserialize, Deserializer};
 use serde_derive::{Deserialize, Serialize};
 
+/// The number of lines of a note's body to show in the picker's preview
+const PREVIEW_LINES: usize = 5;
+
+/// The environment variable that the passphrase for encrypted notes is read from, if set, instead
+/// of prompting for it.
+const PASSPHRASE_VARIABLE: &str = "QUICKNOTES_PASSPHRASE";
+
+const CALENDAR_HELP: &str = "arrows/hjkl: move, pgup/pgdn: month, enter: open, q: quit";
+
 trait UnwrapOrExit<T> {
     fn unwrap_or_exit(self, msg: &str) -> T;
 }
 
+/// A problem found by `quicknotes doctor`, as printed with `--json`.
+#[derive(Serialize)]
+struct DoctorReportEntry<'a> {
+    check: &'static str,
+    paths: Vec<&'a Path>,
+    fixable: bool,
+    fixed: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title_date: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filename_date: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    renamed_path: Option<&'a Path>,
+}
+
 #[derive(Clone, Debug)]
 struct IndexEntry {
     path: PathBuf,
     note: IndexedNote,
     rendered_title_override: Option<String>,
+    /// The preview shown beneath the title, when previews are toggled on in the picker.
+    preview: Option<String>,
 }
 
 struct IndexedNoteRenderer;
 
+/// A timezone given with `--tz`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Timezone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+/// Something that can be done with an entry while processing the inbox.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum InboxAction {
+    NewNote,
+    Daily,
+    Delete,
+    Skip,
+}
+
+/// Something that can be done with a draft that was left behind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DraftAction {
+    Resume,
+    Store,
+    Discard,
+    Skip,
+}
+
+/// A [`DraftAction`] as shown in the picker, alongside the draft it applies to.
+struct DraftChoice {
+    action: DraftAction,
+    summary: String,
+}
+
+/// An [`InboxAction`] as shown in the picker, alongside the entry it applies to.
+struct InboxChoice {
+    action: InboxAction,
+    summary: String,
+}
+
+impl<'a> DoctorReportEntry<'a> {
+    fn new(issue: &'a HealthIssue, fixed: bool) -> Self {
+        let mut entry = Self {
+            check: issue.name(),
+            paths: Vec::new(),
+            fixable: issue.is_fixable(),
+            fixed,
+            title: None,
+            title_date: None,
+            filename_date: None,
+            renamed_path: None,
+        };
+
+        match issue {
+            HealthIssue::StaleIndexEntry { path }
+            | HealthIssue::PreservedTempfile { path }
+            | HealthIssue::RecoverableNote { path } => {
+                entry.paths.push(path);
+            }
+            HealthIssue::DuplicateTitle { title, paths } => {
+                entry.title = Some(title);
+                entry.paths.extend(paths.iter().map(PathBuf::as_path));
+            }
+            HealthIssue::Orphaned(orphan) => entry.paths.push(&orphan.path),
+            HealthIssue::MalformedPreamble { path, .. } => entry.paths.push(path),
+            HealthIssue::DailyDateMismatch {
+                path,
+                title_date,
+                filename_date,
+            } => {
+                entry.paths.push(path);
+                entry.title_date = Some(title_date.to_string());
+                entry.filename_date = Some(filename_date.to_string());
+            }
+            HealthIssue::WrongExtension {
+                path, renamed_path, ..
+            } => {
+                entry.paths.push(path);
+                entry.renamed_path = renamed_path.as_deref();
+            }
+        }
+
+        entry
+    }
+}
+
+impl Display for InboxChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self.action {
+            InboxAction::NewNote => "move to a new note",
+            InboxAction::Daily => "move to today's daily note",
+            InboxAction::Delete => "delete",
+            InboxAction::Skip => "skip",
+        };
+
+        write!(f, "{label}: {}", self.summary)
+    }
+}
+
+impl Display for DraftChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self.action {
+            DraftAction::Resume => "resume editing",
+            DraftAction::Store => "save as is",
+            DraftAction::Discard => "discard",
+            DraftAction::Skip => "decide later",
+        };
+
+        write!(f, "{label}: {}", self.summary)
+    }
+}
+
 impl IndexEntry {
     fn new(path: PathBuf, note: IndexedNote) -> Self {
         Self {
             path,
             note,
             rendered_title_override: None,
+            preview: None,
+        }
+    }
+
+    /// Get a copy of the entry that is rendered with its preview beneath the title.
+    fn with_preview(&self) -> Self {
+        Self {
+            preview: Some(self.load_preview()),
+            ..self.clone()
+        }
+    }
+
+    fn load_preview(&self) -> String {
+        match quicknotes::read_note(&self.path) {
+            Ok(contents) if contents.preamble.encrypted => {
+                render_preview(&self.note, "(encrypted)")
+            }
+            Ok(contents) => render_preview(&self.note, &contents.body),
+            Err(err) => format!("  (could not read note: {err})"),
         }
     }
 }
 
 impl Render<IndexEntry> for IndexedNoteRenderer {
-    type Str<'a> = &'a str;
+    type Str<'a> = Cow<'a, str>;
 
     fn render<'a>(&self, entry: &'a IndexEntry) -> Self::Str<'a> {
-        match &entry.rendered_title_override {
+        let title = match &entry.rendered_title_override {
             Some(title_override) => title_override,
             None => &entry.note.preamble.title,
+        };
+
+        // Tags are rendered so that they can be searched for. The preview can only be rendered
+        // as part of the entry, so it is searched too while it is shown.
+        let tags = &entry.note.preamble.tags;
+        let heading = if tags.is_empty() {
+            Cow::Borrowed(title.as_str())
+        } else {
+            Cow::Owned(format!(
+                "{title} {}",
+                tags.iter().map(|tag| format!("#{tag}")).join(" ")
+            ))
+        };
+
+        match &entry.preview {
+            Some(preview) => Cow::Owned(format!("{heading}\n{preview}")),
+            None => heading,
         }
     }
 }
@@ -66,6 +259,69 @@ struct OnDiskConfig {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub editor_command: Option<String>,
+
+    #[serde(default)]
+    pub date_dialect: OnDiskDateDialect,
+
+    #[serde(default)]
+    pub daily: OnDiskDailyConfig,
+
+    #[serde(default)]
+    pub inbox: OnDiskInboxConfig,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum OnDiskDateDialect {
+    #[default]
+    Us,
+    Uk,
+}
+
+impl From<OnDiskDateDialect> for DateDialect {
+    fn from(dialect: OnDiskDateDialect) -> Self {
+        match dialect {
+            OnDiskDateDialect::Us => DateDialect::Us,
+            OnDiskDateDialect::Uk => DateDialect::Uk,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct OnDiskDailyConfig {
+    #[serde(default)]
+    pub carry_over_tasks: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename_format: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_format: Option<String>,
+
+    #[serde(
+        default,
+        skip_serializing,
+        deserialize_with = "OnDiskDailyConfig::deserialize_day_starts_at"
+    )]
+    pub day_starts_at: Option<NaiveTime>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct OnDiskInboxConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+}
+
+impl OnDiskDailyConfig {
+    fn deserialize_day_starts_at<'a, D: Deserializer<'a>>(
+        deserializer: D,
+    ) -> Result<Option<NaiveTime>, D::Error> {
+        let day_starts_at: String = Deserialize::deserialize(deserializer)?;
+
+        NaiveTime::parse_from_str(&day_starts_at, "%H:%M")
+            .map(Some)
+            .map_err(|_err| de::Error::custom("must be a time of the form HH:MM"))
+    }
 }
 
 impl OnDiskConfig {
@@ -79,6 +335,25 @@ impl OnDiskConfig {
             root_dir: self.notes_root,
             file_extension: self.note_file_extension,
             temp_root_override: None,
+            date_dialect: self.date_dialect.into(),
+            daily: DailyConfig {
+                carry_over_tasks: self.daily.carry_over_tasks,
+                filename_format: self
+                    .daily
+                    .filename_format
+                    .unwrap_or_else(|| DEFAULT_DAILY_FORMAT.to_string()),
+                title_format: self
+                    .daily
+                    .title_format
+                    .unwrap_or_else(|| DEFAULT_DAILY_FORMAT.to_string()),
+                day_starts_at: self.daily.day_starts_at.unwrap_or(NaiveTime::MIN),
+            },
+            inbox: InboxConfig {
+                filename: self
+                    .inbox
+                    .filename
+                    .unwrap_or_else(|| DEFAULT_INBOX_FILENAME.to_string()),
+            },
         };
 
         (note_config, editor)
@@ -120,23 +395,68 @@ fn main() {
     let (note_config, editor) = load_config()
         .unwrap_or_exit("could not load configuration file")
         .unpack(&fallback_editor());
+    let matches = command.get_matches();
+
+    let makes_notes = matches!(matches.subcommand_name(), Some("new" | "daily" | "open"));
+    if makes_notes && io::stdin().is_terminal() && io::stdout().is_terminal() {
+        offer_abandoned_drafts(&note_config, &editor);
+    }
 
-    match command.get_matches().subcommand() {
+    match matches.subcommand() {
         Some(("new", submatches)) => run_new(&note_config, &editor, submatches),
         Some(("daily", submatches)) => run_daily(&note_config, &editor, submatches),
+        Some(("weekly", submatches)) => {
+            run_periodic(&note_config, &editor, Period::Weekly, submatches);
+        }
+        Some(("monthly", submatches)) => {
+            run_periodic(&note_config, &editor, Period::Monthly, submatches);
+        }
         Some(("index", _submatches)) => run_index(&note_config),
         Some(("open", submatches)) => run_open(&note_config, &editor, submatches),
+        Some(("show", submatches)) => run_show(&note_config, submatches),
+        Some(("list", submatches)) => run_list(&note_config, submatches),
+        Some(("tasks", submatches)) => run_tasks(&note_config, submatches),
+        Some(("calendar", submatches)) => run_calendar(&note_config, &editor, submatches),
+        Some(("capture", submatches)) => run_capture(&note_config, submatches),
+        Some(("inbox", submatches)) => run_inbox(&note_config, submatches),
+        Some(("export", submatches)) => run_export(&note_config, submatches),
+        Some(("import", submatches)) => run_import(&note_config, submatches),
+        Some(("doctor", submatches)) => run_doctor(&note_config, submatches),
+        Some(("recover", submatches)) => run_recover(&note_config, submatches),
+        Some(("attach", submatches)) => run_attach(&note_config, submatches),
+        Some(("delete", submatches)) => run_delete(&note_config, submatches),
         _ => unreachable!(),
     }
 }
 
+// This is one long builder expression; splitting it up would not make it any clearer.
+#[allow(clippy::too_many_lines)]
 fn cli_command() -> ClapCommand {
     ClapCommand::new("qn")
         .subcommand_required(true)
         .arg_required_else_help(true)
+        .arg(
+            Arg::new("tz")
+                .long("tz")
+                .global(true)
+                .value_parser(parse_timezone)
+                .help(concat!(
+                    "The timezone to use for the current date and time, as UTC, an offset such ",
+                    "as +09:00, or a name such as America/New_York"
+                ))
+        )
         .subcommand(
             ClapCommand::new("new")
                 .arg(Arg::new("title").num_args(1..).required(true))
+                .arg(
+                    Arg::new("encrypt")
+                        .long("encrypt")
+                        .action(ArgAction::SetTrue)
+                        .help(concat!(
+                            "Encrypt the body of the note with a passphrase, which is read from ",
+                            "QUICKNOTES_PASSPHRASE or prompted for"
+                        ))
+                )
                 .about("Create a new note")
                 .long_about(
                     concat!(
@@ -148,12 +468,55 @@ fn cli_command() -> ClapCommand {
         .subcommand(
             ClapCommand::new("daily")
                 .arg(Arg::new("offset").num_args(1..).required(false))
+                .arg(
+                    Arg::new("prev")
+                        .long("prev")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("next")
+                        .help("Open the closest existing daily note before today (or the given date)")
+                )
+                .arg(
+                    Arg::new("next")
+                        .long("next")
+                        .action(ArgAction::SetTrue)
+                        .help("Open the closest existing daily note after today (or the given date)")
+                )
                 .about("Open or create a daily note")
                 .long_about(
                     concat!(
                         "Open a daily note, or create one one does not already exist.",
                         " Optionally, an offset can be supplied, which is a fuzzy date relative to today.",
-                        " Acceptable formats include, but are not limited to,  \"2015-10-21\", \"yesterday\" \"3 days ago\""
+                        " Acceptable formats include, but are not limited to,  \"2015-10-21\", \"yesterday\" \"3 days ago\",",
+                        " \"-3\", \"last monday\", \"2 weeks ago friday\", \"start of week\" and \"2015-W43-3\".",
+                        " With --prev or --next, the nearest daily note that already exists before or after that date is opened instead,",
+                        " which is useful for reviewing past notes without creating notes for skipped days."
+                    )
+                )
+        )
+        .subcommand(
+            ClapCommand::new("weekly")
+                .arg(Arg::new("offset").num_args(1..).required(false))
+                .about("Open or create a weekly note")
+                .long_about(
+                    concat!(
+                        "Open this week's note, or create one one does not already exist.",
+                        " Weeks are ISO weeks, which start on Monday.",
+                        " Optionally, an offset can be supplied, which is a fuzzy date relative to today;",
+                        " the note for the week containing that date is opened.",
+                        " Acceptable formats include, but are not limited to,  \"2015-10-21\", \"1 week ago\" \"3 weeks ago\""
+                    )
+                )
+        )
+        .subcommand(
+            ClapCommand::new("monthly")
+                .arg(Arg::new("offset").num_args(1..).required(false))
+                .about("Open or create a monthly note")
+                .long_about(
+                    concat!(
+                        "Open this month's note, or create one one does not already exist.",
+                        " Optionally, an offset can be supplied, which is a fuzzy date relative to today;",
+                        " the note for the month containing that date is opened.",
+                        " Acceptable formats include, but are not limited to,  \"2015-10-21\", \"1 month ago\" \"3 months ago\""
                     )
                 )
         )
@@ -172,15 +535,327 @@ fn cli_command() -> ClapCommand {
             ClapCommand::new("open")
             .arg(
                 Arg::new("kind")
-                    .value_parser(PossibleValuesParser::new(vec!["note", "daily", "all"]))
+                    .value_parser(PossibleValuesParser::new(vec!["note", "daily", "weekly", "monthly", "all"]))
                     .default_value("note")
             )
+            .arg(
+                Arg::new("recent")
+                    .long("recent")
+                    .value_name("COUNT")
+                    .num_args(0..=1)
+                    .default_missing_value("10")
+                    .value_parser(value_parser!(usize))
+                    .help("Only show the most recently opened notes (10, unless specified)")
+            )
             .about("Open an existing note")
             .long_about(
                 concat!(
                     "Open an existing note.",
                     " Optionally, the type of note can be specified. Defaults to 'note'",
                     " (i.e. those created with quicknotes new).",
+                    " Notes are ordered by how frequently and recently they have been opened.",
+                    " In the picker, press tab to show or hide a preview of each note.",
+                )
+            )
+        )
+        .subcommand(
+            ClapCommand::new("show")
+            .arg(Arg::new("path").value_parser(value_parser!(PathBuf)))
+            .arg(
+                Arg::new("kind")
+                    .long("kind")
+                    .value_parser(PossibleValuesParser::new(vec!["note", "daily", "weekly", "monthly", "all"]))
+                    .default_value("note")
+                    .conflicts_with("path")
+            )
+            .arg(
+                Arg::new("raw")
+                    .long("raw")
+                    .action(ArgAction::SetTrue)
+                    .help("Print the note file exactly as it is stored")
+            )
+            .arg(
+                Arg::new("markdown")
+                    .long("markdown")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("raw")
+                    .help("Render the note's markdown for the terminal")
+            )
+            .about("Display a note without editing it")
+            .long_about(
+                concat!(
+                    "Display a note without editing it, using $PAGER.",
+                    " If no path is given, the note can be selected from the index,",
+                    " optionally filtered by --kind (defaults to 'note').",
+                )
+            )
+        )
+        .subcommand(
+            ClapCommand::new("list")
+            .arg(
+                Arg::new("kind")
+                    .long("kind")
+                    .value_parser(PossibleValuesParser::new(vec!["note", "daily", "weekly", "monthly", "all"]))
+                    .default_value("note")
+            )
+            .arg(
+                Arg::new("sort")
+                    .long("sort")
+                    .value_parser(PossibleValuesParser::new(vec!["created", "updated", "title"]))
+                    .default_value("created")
+                    .help("How to order the listed notes")
+            )
+            .about("List indexed notes")
+            .long_about(
+                concat!(
+                    "List the notes in the index, optionally filtered by --kind (defaults to 'note').",
+                    " Notes can be sorted by when they were created (the default), when they were last updated, or by title.",
+                    " Dates are listed newest first.",
+                )
+            )
+        )
+        .subcommand(
+            ClapCommand::new("tasks")
+            .args_conflicts_with_subcommands(true)
+            .arg(
+                Arg::new("open")
+                    .long("open")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("done")
+                    .help("Only list tasks that have not been checked off")
+            )
+            .arg(
+                Arg::new("done")
+                    .long("done")
+                    .action(ArgAction::SetTrue)
+                    .help("Only list tasks that have been checked off")
+            )
+            .arg(
+                Arg::new("due-before")
+                    .long("due-before")
+                    .value_name("DATE")
+                    .help("Only list tasks due before the given date, which may be fuzzy (e.g. \"tomorrow\")")
+            )
+            .subcommand(
+                ClapCommand::new("toggle")
+                .arg(
+                    Arg::new("location")
+                        .value_name("PATH:LINE")
+                        .required(true)
+                        .help("The note and line number of the task, as shown by quicknotes tasks")
+                )
+                .about("Check or uncheck a task")
+            )
+            .about("List tasks from all notes")
+            .long_about(
+                concat!(
+                    "List the tasks (e.g. \"- [ ] buy milk\") from all indexed notes.",
+                    " Tasks can have a due date, written as due:YYYY-MM-DD, and tags, written as @tag.",
+                    " Tasks with a due date are listed first, soonest first.",
+                )
+            )
+        )
+        .subcommand(
+            ClapCommand::new("calendar")
+            .arg(Arg::new("month").num_args(1..).required(false))
+            .arg(
+                Arg::new("interactive")
+                    .long("interactive")
+                    .short('i')
+                    .action(ArgAction::SetTrue)
+                    .help("Pick a day with the arrow keys, and open its daily note with enter")
+            )
+            .about("Show which days have a daily note")
+            .long_about(
+                concat!(
+                    "Show a calendar of the current month, with the days that have a daily note highlighted.",
+                    " Optionally, a month can be supplied, either as YYYY-MM or as a fuzzy date (e.g. \"1 month ago\").",
+                    " With --interactive, the arrow keys (or hjkl) move between days, page up and page down move between months,",
+                    " and enter opens the selected day's daily note.",
+                )
+            )
+        )
+        .subcommand(
+            ClapCommand::new("capture")
+            .arg(Arg::new("text").num_args(1..).required(false))
+            .about("Add an entry to the inbox")
+            .long_about(
+                concat!(
+                    "Add a timestamped entry to the inbox, without opening the editor.",
+                    " The text can be entered into the shell directly, including spaces, or piped in through stdin.",
+                )
+            )
+        )
+        .subcommand(
+            ClapCommand::new("inbox")
+            .subcommand(
+                ClapCommand::new("process")
+                .about("Move each inbox entry into a note, or delete it")
+                .long_about(
+                    concat!(
+                        "Go through the inbox entries, oldest first, and pick whether to move each one into a new note,",
+                        " move it into today's daily note, delete it, or leave it in the inbox.",
+                        " Quitting the picker stops processing, leaving the remaining entries in the inbox.",
+                    )
+                )
+            )
+            .about("List the entries in the inbox")
+        )
+        .subcommand(
+            ClapCommand::new("export")
+            .args_conflicts_with_subcommands(true)
+            .arg_required_else_help(true)
+            .arg(
+                Arg::new("bundle")
+                    .long("bundle")
+                    .value_name("PATH")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("Package every indexed note into a single .tar.gz bundle, which can be loaded with quicknotes import")
+            )
+            .subcommand(
+                ClapCommand::new("html")
+                .arg(
+                    Arg::new("out-dir")
+                        .value_parser(value_parser!(PathBuf))
+                        .required(true)
+                        .help("The directory to write the site to")
+                )
+                .arg(
+                    Arg::new("kind")
+                        .long("kind")
+                        .value_parser(PossibleValuesParser::new(vec!["note", "daily", "weekly", "monthly", "all"]))
+                        .default_value("all")
+                )
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .help("Only export notes with the given tag")
+                )
+                .about("Export notes to a static HTML site")
+                .long_about(
+                    concat!(
+                        "Export the notes in the index to a static HTML site, optionally filtered by --kind (defaults to 'all') and --tag.",
+                        " Each note gets its own page, and [[links]] to the title of another exported note link to its page.",
+                        " The site has an index page listing every note, newest first, and an archive of daily notes by month.",
+                    )
+                )
+            )
+            .about("Export notes")
+        )
+        .subcommand(
+            ClapCommand::new("import")
+            .arg(
+                Arg::new("path")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(true)
+                    .help("A bundle created by quicknotes export --bundle, or a directory of notes if --from is given")
+            )
+            .arg(
+                Arg::new("from")
+                    .long("from")
+                    .value_parser(PossibleValuesParser::new(vec!["obsidian", "joplin-md", "plain"]))
+                    .help("Import a directory of markdown notes from another tool")
+            )
+            .about("Import notes from a bundle or another tool")
+            .long_about(
+                concat!(
+                    "Import the notes from a bundle created by quicknotes export --bundle, and add them to the index.",
+                    " With --from, import a directory of markdown notes instead: an Obsidian vault, a Joplin",
+                    " \"Markdown + Front Matter\" export, or plain markdown files. Titles, creation times and tags are taken",
+                    " from front matter where possible, and notes named after a date (e.g. 2015-10-21.md) become daily notes.",
+                    " Existing notes are never overwritten; if a different note already has the same name,",
+                    " the imported note is given a new name and the conflict is reported.",
+                )
+            )
+        )
+        .subcommand(
+            ClapCommand::new("doctor")
+            .arg(
+                Arg::new("fix")
+                    .long("fix")
+                    .action(ArgAction::SetTrue)
+                    .help("Fix the problems that can be fixed without losing or overwriting anything")
+            )
+            .arg(
+                Arg::new("adopt")
+                    .long("adopt")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("json")
+                    .help("Offer to add a preamble to files that are missing one, so that they are indexed")
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Print each problem as a line of JSON")
+            )
+            .about("Find problems with your notes")
+            .long_about(
+                concat!(
+                    "Find problems with your notes: index entries for notes that no longer exist, notes that share a title,",
+                    " files that are not indexed because they have no preamble (e.g. because they were created by hand),",
+                    " daily notes whose title is for a different day than their filename, files with the wrong extension,",
+                    " notes left in the temporary directory after they could not be saved, and notes waiting to be recovered",
+                    " with quicknotes recover.",
+                    " With --fix, stale index entries are removed, and files with the wrong extension are renamed if they have a preamble",
+                    " and nothing is in the way. The other problems must be fixed by hand.",
+                    " With --adopt, after confirming, files without a preamble are given one, with a title based on their filename",
+                    " and a creation time based on when they were last modified, and are indexed.",
+                    " With --json, each problem is printed as a JSON object on its own line, with \"check\", \"paths\", \"fixable\"",
+                    " and \"fixed\" fields, along with any details specific to the check.",
+                )
+            )
+        )
+        .subcommand(
+            ClapCommand::new("recover")
+            .arg(
+                Arg::new("name")
+                    .help("The filename of the note to recover, as listed by quicknotes recover")
+            )
+            .about("Recover notes that could not be saved")
+            .long_about(
+                concat!(
+                    "If a note cannot be saved, it is moved to the recovery directory in your notes root, along with a record",
+                    " of where it was meant to go. With no arguments, list the notes waiting to be recovered.",
+                    " Given the name of one, store it where it was meant to go and add it to the index.",
+                    " If a different note has since been stored there, the recovered note is given a new name instead.",
+                )
+            )
+        )
+        .subcommand(
+            ClapCommand::new("attach")
+            .arg(
+                Arg::new("file")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(true)
+            )
+            .arg(
+                Arg::new("kind")
+                    .long("kind")
+                    .value_parser(PossibleValuesParser::new(vec!["note", "daily", "weekly", "monthly", "all"]))
+                    .default_value("note")
+            )
+            .about("Attach a file to a note")
+            .long_about(
+                concat!(
+                    "Attach a file to a note, selected from the index and optionally filtered by --kind (defaults to 'note').",
+                    " The file is copied into attachments/<note name> in your notes root, and a link to it is added to the end of the note.",
+                )
+            )
+        )
+        .subcommand(
+            ClapCommand::new("delete")
+            .arg(
+                Arg::new("kind")
+                    .long("kind")
+                    .value_parser(PossibleValuesParser::new(vec!["note", "daily", "weekly", "monthly", "all"]))
+                    .default_value("note")
+            )
+            .about("Delete a note and its attachments")
+            .long_about(
+                concat!(
+                    "Delete a note, selected from the index and optionally filtered by --kind (defaults to 'note'),",
+                    " along with any files attached to it with quicknotes attach.",
                 )
             )
         )
@@ -194,8 +869,14 @@ fn run_new(config: &NoteConfig, editor: &CommandEditor, args: &clap::ArgMatches)
         .unwrap_or_default()
         .join(" ");
 
-    let path = quicknotes::make_note(config, editor, title, &Local::now())
-        .unwrap_or_exit("could not create note");
+    let path = if args.get_flag("encrypt") {
+        let passphrase = read_passphrase(true).unwrap_or_exit("could not read passphrase");
+
+        quicknotes::make_encrypted_note(config, editor, &passphrase, title, &current_time(args))
+    } else {
+        quicknotes::make_note(config, editor, title, &current_time(args))
+    }
+    .unwrap_or_exit("could not create note");
 
     if path.is_none() {
         eprintln!("nothing was written in the note; note discarded");
@@ -203,20 +884,80 @@ fn run_new(config: &NoteConfig, editor: &CommandEditor, args: &clap::ArgMatches)
 }
 
 fn run_daily(config: &NoteConfig, editor: &CommandEditor, args: &clap::ArgMatches) {
-    ensure_daily_dir_exists(config).unwrap_or_exit("could not create dailies directory");
-    let now = Local::now();
-    let note_date = args.get_many::<String>("offset").map_or_else(
-        || now.date_naive(),
-        |offset_args| {
+    let direction = if args.get_flag("prev") {
+        Direction::Previous
+    } else if args.get_flag("next") {
+        Direction::Next
+    } else {
+        run_periodic(config, editor, Period::Daily, args);
+        return;
+    };
+
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    let from_date = periodic_note_date(config, Period::Daily, args);
+    let adjacent = quicknotes::adjacent_daily(config, from_date, direction)
+        .unwrap_or_exit("couldn't load daily notes");
+
+    // The daily filename format may have changed since the note was made, so it must be opened
+    // where it is, rather than where a new one would go.
+    if let Some((_date, path)) = adjacent {
+        open_existing_daily(config, editor, &path);
+    } else {
+        let relation = match direction {
+            Direction::Previous => "before",
+            Direction::Next => "after",
+        };
+
+        eprintln!("there is no daily note {relation} {from_date}");
+    }
+}
+
+fn run_periodic(
+    config: &NoteConfig,
+    editor: &CommandEditor,
+    period: Period,
+    args: &clap::ArgMatches,
+) {
+    let note_date = periodic_note_date(config, period, args);
+
+    open_periodic_note(config, editor, period, note_date, &current_time(args));
+}
+
+/// Get the date given by the offset argument of a periodic note command, or today if there is none.
+fn periodic_note_date(config: &NoteConfig, period: Period, args: &clap::ArgMatches) -> NaiveDate {
+    let today = current_date(config, args);
+
+    args.get_many::<String>("offset")
+        .map_or(today, |offset_args| {
             let offset = offset_args.into_iter().join(" ");
 
-            fuzzy_offset_from_date(now.date_naive(), &offset)
-                .unwrap_or_exit("could not parse daily note offset")
-        },
-    );
+            quicknotes::parse_fuzzy_date(&offset, today, config.date_dialect)
+                .unwrap_or_exit(&format!("could not parse {period} note offset"))
+        })
+}
+
+fn open_existing_daily(config: &NoteConfig, editor: &CommandEditor, path: &Path) {
+    let encrypted = quicknotes::read_note(path)
+        .unwrap_or_exit("could not read daily note")
+        .preamble
+        .encrypted;
+
+    open_existing_note(config, editor, NoteKind::Daily, path, encrypted);
+}
+
+fn open_periodic_note(
+    config: &NoteConfig,
+    editor: &CommandEditor,
+    period: Period,
+    note_date: NaiveDate,
+    creation_time: &DateTime<FixedOffset>,
+) {
+    ensure_periodic_dir_exists(config, period)
+        .unwrap_or_exit(&format!("could not create {period} notes directory"));
 
-    let path = quicknotes::make_or_open_daily(config, editor, note_date, &now)
-        .unwrap_or_exit("could not create daily note");
+    let path = quicknotes::make_or_open_periodic(config, editor, period, note_date, creation_time)
+        .unwrap_or_exit(&format!("could not create {period} note"));
 
     if path.is_none() {
         eprintln!("nothing was written in the note; note discarded");
@@ -236,33 +977,945 @@ fn run_open(config: &NoteConfig, editor: &CommandEditor, args: &clap::ArgMatches
         .get_one::<String>("kind")
         .expect("kind has a default value");
 
-    let indexed_notes = match kind.as_str() {
-        "all" => quicknotes::indexed_notes(config).unwrap_or_exit("couldn't load notes"),
+    let recent = args.get_one::<usize>("recent").copied();
 
-        "note" => quicknotes::indexed_notes_with_kind(config, quicknotes::NoteKind::Note)
-            .unwrap_or_exit("couldn't load notes"),
+    if let Some(selected_note) = pick_indexed_note(config, kind, recent) {
+        open_existing_note(
+            config,
+            editor,
+            selected_note.note.kind,
+            &selected_note.path,
+            selected_note.note.preamble.encrypted,
+        );
+    }
+}
+
+fn open_existing_note(
+    config: &NoteConfig,
+    editor: &CommandEditor,
+    kind: NoteKind,
+    path: &Path,
+    encrypted: bool,
+) {
+    if encrypted {
+        let passphrase = read_passphrase(false).unwrap_or_exit("could not read passphrase");
+
+        quicknotes::open_encrypted_note(config, editor, kind, path, &passphrase)
+            .unwrap_or_exit("could not open selected file");
+    } else {
+        open_note(config, editor, kind, path).unwrap_or_exit("could not open selected file");
+    }
+}
+
+fn run_show(config: &NoteConfig, args: &clap::ArgMatches) {
+    let path = if let Some(path) = args.get_one::<PathBuf>("path") {
+        path.clone()
+    } else {
+        ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+        let kind = args
+            .get_one::<String>("kind")
+            .expect("kind has a default value");
+
+        match pick_indexed_note(config, kind, None) {
+            Some(selected_note) => selected_note.path,
+            None => return,
+        }
+    };
+
+    let text = if args.get_flag("raw") {
+        fs::read_to_string(&path).unwrap_or_exit("could not read note")
+    } else {
+        let mut contents = quicknotes::read_note(&path).unwrap_or_exit("could not read note");
+        if contents.preamble.encrypted {
+            let passphrase = read_passphrase(false).unwrap_or_exit("could not read passphrase");
+            contents = quicknotes::read_encrypted_note(&path, &passphrase)
+                .unwrap_or_exit("could not read note");
+        }
+
+        render_note(&contents, args.get_flag("markdown"))
+    };
+
+    page(&text).unwrap_or_exit("could not display note");
+}
+
+fn run_list(config: &NoteConfig, args: &clap::ArgMatches) {
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    let kind = args
+        .get_one::<String>("kind")
+        .expect("kind has a default value");
+
+    let sort = args
+        .get_one::<String>("sort")
+        .expect("sort has a default value");
+
+    let listing = sort_for_listing(load_indexed_notes(config, kind), sort)
+        .into_iter()
+        .map(|(path, note)| {
+            let listed_at = if sort == "updated" {
+                last_updated_at(&note.preamble)
+            } else {
+                note.preamble.created_at
+            };
+
+            format!(
+                "{}  {}  {}",
+                listed_at.format("%Y-%m-%d %H:%M").to_string().bright_blue(),
+                note.preamble.title,
+                path.display().to_string().dimmed()
+            )
+        })
+        .join("\n");
+
+    if !listing.is_empty() {
+        write_to_stdout(&format!("{listing}\n")).unwrap_or_exit("could not list notes");
+    }
+}
+
+fn run_tasks(config: &NoteConfig, args: &clap::ArgMatches) {
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    if let Some(("toggle", submatches)) = args.subcommand() {
+        run_toggle_task(config, submatches);
+        return;
+    }
+
+    let status = if args.get_flag("open") {
+        Some(false)
+    } else if args.get_flag("done") {
+        Some(true)
+    } else {
+        None
+    };
+
+    let due_before = args.get_one::<String>("due-before").map(|due_before| {
+        quicknotes::parse_fuzzy_date(due_before, current_date(config, args), config.date_dialect)
+            .unwrap_or_exit("could not parse due date")
+    });
+
+    let tasks = quicknotes::indexed_tasks(config).unwrap_or_exit("couldn't load tasks");
+    let listing = filter_tasks(tasks, status, due_before)
+        .into_iter()
+        .map(|indexed_task| {
+            let checkbox = if indexed_task.task.done { "[x]" } else { "[ ]" };
+
+            format!(
+                "{} {}  {}  {}",
+                checkbox.yellow(),
+                indexed_task.task.text,
+                indexed_task.note_title.bright_blue(),
+                format!(
+                    "{}:{}",
+                    indexed_task.path.display(),
+                    indexed_task.task.line_number
+                )
+                .dimmed()
+            )
+        })
+        .join("\n");
+
+    if !listing.is_empty() {
+        write_to_stdout(&format!("{listing}\n")).unwrap_or_exit("could not list tasks");
+    }
+}
+
+fn run_toggle_task(config: &NoteConfig, args: &clap::ArgMatches) {
+    let location = args
+        .get_one::<String>("location")
+        .expect("location is required");
+
+    let (path, line_number) = parse_task_location(location).unwrap_or_exit("could not find task");
+
+    let task =
+        quicknotes::toggle_task(config, &path, line_number).unwrap_or_exit("could not toggle task");
+
+    let checkbox = if task.done { "[x]" } else { "[ ]" };
+    println!("{} {}", checkbox.yellow(), task.text);
+}
+
+fn run_calendar(config: &NoteConfig, editor: &CommandEditor, args: &clap::ArgMatches) {
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    let today = current_date(config, args);
+    let month = args
+        .get_many::<String>("month")
+        .map_or(today, |month_args| {
+            let month = month_args.into_iter().join(" ");
+
+            parse_month(today, &month, config.date_dialect).unwrap_or_exit("could not parse month")
+        });
+
+    let dailies = quicknotes::indexed_dailies(config).unwrap_or_exit("couldn't load daily notes");
+    let daily_dates = dailies.keys().copied().collect::<BTreeSet<_>>();
+
+    if !args.get_flag("interactive") {
+        let calendar = quicknotes::render_calendar(month, &daily_dates, today, None);
+        write_to_stdout(&format!("{calendar}\n")).unwrap_or_exit("could not show calendar");
+
+        return;
+    }
+
+    // Start on today if it's in the requested month, so that opening today's note is just "enter"
+    let initial_selection = if month.year() == today.year() && month.month() == today.month() {
+        today
+    } else {
+        month.with_day(1).expect("every month has a first day")
+    };
+
+    let selected_day = pick_calendar_day(&daily_dates, today, initial_selection)
+        .unwrap_or_exit("could not show calendar");
+
+    match selected_day.map(|day| (day, dailies.get(&day))) {
+        Some((_day, Some(path))) => open_existing_daily(config, editor, path),
+        Some((day, None)) => {
+            open_periodic_note(config, editor, Period::Daily, day, &current_time(args));
+        }
+        None => {}
+    }
+}
+
+/// Let the user pick a day from an interactive calendar. Returns None if they quit without
+/// picking one.
+fn pick_calendar_day(
+    daily_dates: &BTreeSet<NaiveDate>,
+    today: NaiveDate,
+    initial_selection: NaiveDate,
+) -> anyhow::Result<Option<NaiveDate>> {
+    let _screen = AlternateScreen::enter()?;
+    let mut stderr = io::stderr();
+    let mut selected = initial_selection;
+
+    loop {
+        let calendar = quicknotes::render_calendar(selected, daily_dates, today, Some(selected));
+
+        queue!(
+            stderr,
+            cursor::MoveTo(0, 0),
+            terminal::Clear(ClearType::All)
+        )?;
+        for line in calendar.lines().chain(["", CALENDAR_HELP]) {
+            queue!(stderr, Print(line), cursor::MoveToNextLine(1))?;
+        }
+        stderr.flush()?;
+
+        let CrosstermEvent::Key(key_event) = crossterm::event::read()? else {
+            continue;
+        };
+
+        match calendar_action(selected, key_event) {
+            CalendarAction::Select(day) => selected = day,
+            CalendarAction::Open => return Ok(Some(selected)),
+            CalendarAction::Quit => return Ok(None),
+            CalendarAction::Ignore => {}
+        }
+    }
+}
+
+/// Decide what a key press does in the interactive calendar, given the selected day.
+fn calendar_action(selected: NaiveDate, key_event: KeyEvent) -> CalendarAction {
+    if key_event.kind != KeyEventKind::Press {
+        return CalendarAction::Ignore;
+    }
+
+    let new_selection = match key_event.code {
+        KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            return CalendarAction::Quit;
+        }
+        KeyCode::Esc | KeyCode::Char('q') => return CalendarAction::Quit,
+        KeyCode::Enter => return CalendarAction::Open,
+
+        KeyCode::Left | KeyCode::Char('h') => selected.checked_sub_days(Days::new(1)),
+        KeyCode::Right | KeyCode::Char('l') => selected.checked_add_days(Days::new(1)),
+        KeyCode::Up | KeyCode::Char('k') => selected.checked_sub_days(Days::new(7)),
+        KeyCode::Down | KeyCode::Char('j') => selected.checked_add_days(Days::new(7)),
+        KeyCode::PageUp => selected.checked_sub_months(Months::new(1)),
+        KeyCode::PageDown => selected.checked_add_months(Months::new(1)),
+
+        _ => None,
+    };
+
+    new_selection.map_or(CalendarAction::Ignore, CalendarAction::Select)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CalendarAction {
+    Select(NaiveDate),
+    Open,
+    Quit,
+    Ignore,
+}
+
+/// Switches the terminal to an alternate screen in raw mode, restoring it when dropped.
+struct AlternateScreen;
+
+impl AlternateScreen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        // Construct the guard first, so that raw mode is disabled if this fails
+        let screen = Self;
+        execute!(io::stderr(), EnterAlternateScreen, cursor::Hide)?;
+
+        Ok(screen)
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        // There's nothing more we can do if restoring the terminal fails
+        let _ = execute!(io::stderr(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Parse a month given on the command line, which may either be of the form YYYY-MM, or a fuzzy
+/// date relative to today. The returned date is some day within the month.
+fn parse_month(
+    today: NaiveDate,
+    month: &str,
+    dialect: DateDialect,
+) -> Result<NaiveDate, FuzzyDateError> {
+    if let Ok(first_day) = NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d") {
+        return Ok(first_day);
+    }
+
+    quicknotes::parse_fuzzy_date(month, today, dialect)
+}
+
+fn load_indexed_notes(config: &NoteConfig, kind: &str) -> HashMap<PathBuf, IndexedNote> {
+    match kind {
+        "all" => quicknotes::indexed_notes(config).unwrap_or_exit("couldn't load notes"),
+
+        "note" => quicknotes::indexed_notes_with_kind(config, quicknotes::NoteKind::Note)
+            .unwrap_or_exit("couldn't load notes"),
+
+        "daily" => quicknotes::indexed_notes_with_kind(config, quicknotes::NoteKind::Daily)
+            .unwrap_or_exit("couldn't load notes"),
+
+        "weekly" => quicknotes::indexed_notes_with_kind(config, quicknotes::NoteKind::Weekly)
+            .unwrap_or_exit("couldn't load notes"),
+
+        "monthly" => quicknotes::indexed_notes_with_kind(config, quicknotes::NoteKind::Monthly)
+            .unwrap_or_exit("couldn't load notes"),
+
+        _ => unreachable!("invalid argument, should be caught by clap"),
+    }
+}
+
+fn run_capture(config: &NoteConfig, args: &clap::ArgMatches) {
+    let text_args = args.get_many::<String>("text");
+    // Reading from a terminal would wait silently for input the user doesn't know to give
+    if text_args.is_none() && io::stdin().is_terminal() {
+        let mut command = cli_command();
+        command.build();
+        let usage = command
+            .find_subcommand_mut("capture")
+            .expect("capture is a subcommand")
+            .render_usage();
+
+        eprintln!(
+            "{}: no text to capture; pass it as arguments or pipe it in through stdin\n\n{usage}",
+            "error".red()
+        );
+        process::exit(2);
+    }
+
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    let text = if let Some(text_args) = text_args {
+        text_args.into_iter().join(" ")
+    } else {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .unwrap_or_exit("could not read stdin");
+
+        text
+    };
+
+    quicknotes::capture(config, &text, &current_time(args)).unwrap_or_exit("could not capture");
+}
+
+fn run_inbox(config: &NoteConfig, args: &clap::ArgMatches) {
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    if let Some(("process", submatches)) = args.subcommand() {
+        run_process_inbox(config, submatches);
+        return;
+    }
+
+    let entries = quicknotes::inbox_entries(config).unwrap_or_exit("couldn't load inbox");
+    let listing = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{}  {}",
+                entry
+                    .captured_at
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+                    .dimmed(),
+                entry.text.replace('\n', "\n                  ")
+            )
+        })
+        .join("\n");
+
+    if !listing.is_empty() {
+        write_to_stdout(&format!("{listing}\n")).unwrap_or_exit("could not list inbox");
+    }
+}
+
+fn run_process_inbox(config: &NoteConfig, args: &clap::ArgMatches) {
+    let entries = quicknotes::inbox_entries(config).unwrap_or_exit("couldn't load inbox");
+    if entries.is_empty() {
+        eprintln!("the inbox is empty");
+        return;
+    }
+
+    for entry in entries {
+        let Some(action) = pick_inbox_action(&entry) else {
+            return;
+        };
+
+        match action {
+            InboxAction::NewNote => {
+                ensure_notes_dir_exists(config).unwrap_or_exit("could not create notes directory");
+
+                let path =
+                    quicknotes::move_inbox_entry_to_note(config, &entry, &current_time(args))
+                        .unwrap_or_exit("could not move entry to a new note");
+
+                eprintln!("moved \"{}\" to {}", entry.summary(), path.display());
+            }
+
+            InboxAction::Daily => {
+                ensure_periodic_dir_exists(config, Period::Daily)
+                    .unwrap_or_exit("could not create daily notes directory");
+
+                let path = quicknotes::move_inbox_entry_to_daily(
+                    config,
+                    &entry,
+                    current_date(config, args),
+                    &current_time(args),
+                )
+                .unwrap_or_exit("could not move entry to the daily note");
+
+                eprintln!("moved \"{}\" to {}", entry.summary(), path.display());
+            }
+
+            InboxAction::Delete => {
+                quicknotes::delete_inbox_entry(config, &entry)
+                    .unwrap_or_exit("could not delete entry");
+
+                eprintln!("deleted \"{}\"", entry.summary());
+            }
+
+            InboxAction::Skip => {}
+        }
+    }
+}
+
+fn run_export(config: &NoteConfig, args: &clap::ArgMatches) {
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    let Some(("html", submatches)) = args.subcommand() else {
+        let bundle_path = args
+            .get_one::<PathBuf>("bundle")
+            .expect("export requires --bundle or a subcommand, which should be enforced by clap");
+        let bundled = quicknotes::export_bundle(config, bundle_path)
+            .unwrap_or_exit("could not export bundle");
+
+        eprintln!("bundled {bundled} notes into {}", bundle_path.display());
+        return;
+    };
+
+    let out_dir = submatches
+        .get_one::<PathBuf>("out-dir")
+        .expect("out-dir is required");
+    let kind = submatches
+        .get_one::<String>("kind")
+        .expect("kind has a default value");
+    let tag = submatches.get_one::<String>("tag");
+
+    let mut notes = load_indexed_notes(config, kind);
+    if let Some(tag) = tag {
+        notes.retain(|_path, note| note.preamble.tags.contains(tag));
+    }
+
+    let exported =
+        quicknotes::export_html(config, notes, out_dir).unwrap_or_exit("could not export notes");
+
+    eprintln!("exported {exported} notes to {}", out_dir.display());
+}
+
+fn run_import(config: &NoteConfig, args: &clap::ArgMatches) {
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    let path = args.get_one::<PathBuf>("path").expect("path is required");
+    let source = args
+        .get_one::<String>("from")
+        .map(|source| match source.as_str() {
+            "obsidian" => ImportSource::Obsidian,
+            "joplin-md" => ImportSource::JoplinMarkdown,
+            "plain" => ImportSource::Plain,
+            _ => unreachable!("invalid argument, should be caught by clap"),
+        });
+
+    let report = match source {
+        Some(source) => quicknotes::import_directory(config, source, path)
+            .unwrap_or_exit("could not import notes"),
+        None => quicknotes::import_bundle(config, path).unwrap_or_exit("could not import bundle"),
+    };
+
+    for conflict in &report.conflicts {
+        eprintln!(
+            "{}: {} already exists, so the imported note was stored at {}",
+            "conflict".yellow(),
+            conflict.existing_path.display(),
+            conflict.imported_path.display()
+        );
+    }
+
+    eprintln!(
+        "imported {} notes ({} already present, {} conflicts)",
+        report.imported.len() + report.conflicts.len(),
+        report.unchanged.len(),
+        report.conflicts.len()
+    );
+}
+
+fn run_doctor(config: &NoteConfig, args: &clap::ArgMatches) {
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    let issues = quicknotes::check_health(config).unwrap_or_exit("could not check notes");
+    let fixed = issues
+        .iter()
+        .map(|issue| {
+            if !args.get_flag("fix") || !issue.is_fixable() {
+                return false;
+            }
+
+            match quicknotes::fix_health_issue(config, issue) {
+                Ok(()) => true,
+                Err(err) => {
+                    eprintln!("{}: could not fix {}: {err}", "error".red(), issue.name());
+                    false
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if args.get_flag("json") {
+        for (issue, &fixed) in issues.iter().zip(&fixed) {
+            let entry = DoctorReportEntry::new(issue, fixed);
+            println!(
+                "{}",
+                serde_json::to_string(&entry).unwrap_or_exit("could not serialize problem")
+            );
+        }
+
+        return;
+    }
+
+    if issues.is_empty() {
+        eprintln!("no problems found");
+        return;
+    }
+
+    for (issue, &fixed) in issues.iter().zip(&fixed) {
+        let label = if fixed {
+            "fixed".green()
+        } else {
+            issue.name().yellow()
+        };
+
+        println!("{label}: {}", describe_health_issue(issue, fixed));
+    }
+
+    let unfixed_fixable = issues
+        .iter()
+        .zip(&fixed)
+        .filter(|(issue, &fixed)| issue.is_fixable() && !fixed)
+        .count();
+    if unfixed_fixable > 0 && !args.get_flag("fix") {
+        eprintln!("run quicknotes doctor --fix to fix {unfixed_fixable} of these");
+    }
+
+    let orphans = issues
+        .iter()
+        .filter_map(|issue| match issue {
+            HealthIssue::Orphaned(orphan) => Some(orphan),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if orphans.is_empty() {
+        return;
+    }
+
+    if !args.get_flag("adopt") {
+        eprintln!("run quicknotes doctor --adopt to give files without a preamble one");
+        return;
+    }
+
+    if !confirm(&format!("Add a preamble to {} files?", orphans.len())) {
+        return;
+    }
+
+    for orphan in orphans {
+        let preamble = quicknotes::adopt_note(config, orphan)
+            .unwrap_or_exit(&format!("could not adopt {}", orphan.path.display()));
+
+        eprintln!(
+            "adopted {} as \"{}\"",
+            orphan.path.display(),
+            preamble.title
+        );
+    }
+}
+
+fn describe_health_issue(issue: &HealthIssue, fixed: bool) -> String {
+    match issue {
+        HealthIssue::StaleIndexEntry { path } if fixed => {
+            format!("removed {} from the index", path.display())
+        }
+        HealthIssue::StaleIndexEntry { path } => {
+            format!("{} is indexed, but does not exist", path.display())
+        }
+        HealthIssue::DuplicateTitle { title, paths } => format!(
+            "\"{title}\" is the title of {}",
+            paths.iter().map(|path| path.display()).join(", ")
+        ),
+        HealthIssue::Orphaned(orphan) => format!(
+            "{} has no preamble, so is not indexed",
+            orphan.path.display()
+        ),
+        HealthIssue::MalformedPreamble { path, error } => format!(
+            "{} has a preamble that cannot be read ({error}), so is not indexed",
+            path.display()
+        ),
+        HealthIssue::DailyDateMismatch {
+            path,
+            title_date,
+            filename_date,
+        } => format!(
+            "{} is titled for {title_date}, but named for {filename_date}",
+            path.display()
+        ),
+        HealthIssue::WrongExtension {
+            path,
+            renamed_path: Some(renamed_path),
+            ..
+        } if fixed => format!("renamed {} to {}", path.display(), renamed_path.display()),
+        HealthIssue::WrongExtension {
+            path,
+            renamed_path: Some(renamed_path),
+            ..
+        } => format!(
+            "{} has the wrong extension, and can be renamed to {}",
+            path.display(),
+            renamed_path.display()
+        ),
+        HealthIssue::WrongExtension {
+            path,
+            renamed_path: None,
+            ..
+        } => format!("{} has the wrong extension", path.display()),
+        HealthIssue::PreservedTempfile { path } => {
+            format!("{} is a note that may not have been saved", path.display())
+        }
+        HealthIssue::RecoverableNote { path } => format!(
+            "{} could not be saved; run quicknotes recover to save it",
+            path.display()
+        ),
+    }
+}
+
+fn run_recover(config: &NoteConfig, args: &clap::ArgMatches) {
+    let notes =
+        quicknotes::recoverable_notes(config).unwrap_or_exit("could not list recoverable notes");
+
+    let Some(name) = args.get_one::<String>("name") else {
+        if notes.is_empty() {
+            eprintln!("no notes to recover");
+            return;
+        }
+
+        for note in &notes {
+            println!(
+                "{}: meant for {} ({}), preserved {}",
+                note.path
+                    .file_name()
+                    .unwrap_or(note.path.as_os_str())
+                    .to_string_lossy(),
+                note.destination.display(),
+                kind_name(note.kind),
+                note.preserved_at.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+
+        eprintln!("run quicknotes recover <name> to save one");
+        return;
+    };
+
+    let note = notes
+        .iter()
+        .find(|note| {
+            note.path
+                .file_name()
+                .is_some_and(|file_name| file_name == name.as_str())
+                || note.path == Path::new(name)
+        })
+        .ok_or_else(|| anyhow!("no note named {name} is waiting to be recovered"))
+        .unwrap_or_exit("could not recover note");
+
+    let path = quicknotes::recover_note(config, note).unwrap_or_exit("could not recover note");
+
+    eprintln!("recovered {name} to {}", path.display());
+}
+
+fn run_attach(config: &NoteConfig, args: &clap::ArgMatches) {
+    let file = args.get_one::<PathBuf>("file").expect("file is required");
+    if !file.is_file() {
+        eprintln!("{}: {} is not a file", "error".red(), file.display());
+        process::exit(1);
+    }
+
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    let kind = args
+        .get_one::<String>("kind")
+        .expect("kind has a default value");
+
+    let Some(selected_note) = pick_indexed_note(config, kind, None) else {
+        return;
+    };
+
+    let attachment_path = quicknotes::attach_file(
+        config,
+        &selected_note.path,
+        selected_note.note.kind,
+        file,
+        &current_time(args),
+    )
+    .unwrap_or_exit("could not attach file");
+
+    eprintln!(
+        "attached {} to \"{}\" as {}",
+        file.display(),
+        selected_note.note.preamble.title,
+        attachment_path.display()
+    );
+}
+
+fn run_delete(config: &NoteConfig, args: &clap::ArgMatches) {
+    ensure_root_dir_exists(config).unwrap_or_exit("could not create root quicknotes directory");
+
+    let kind = args
+        .get_one::<String>("kind")
+        .expect("kind has a default value");
+
+    let Some(selected_note) = pick_indexed_note(config, kind, None) else {
+        return;
+    };
+
+    let attachment_count = quicknotes::note_attachments(config, &selected_note.path)
+        .unwrap_or_exit("could not look up attachments")
+        .len();
+    let prompt = match attachment_count {
+        0 => format!("Delete \"{}\"?", selected_note.note.preamble.title),
+        1 => format!(
+            "Delete \"{}\" and its attachment?",
+            selected_note.note.preamble.title
+        ),
+        _ => format!(
+            "Delete \"{}\" and its {attachment_count} attachments?",
+            selected_note.note.preamble.title
+        ),
+    };
+    if !confirm(&prompt) {
+        return;
+    }
+
+    quicknotes::delete_note(config, &selected_note.path).unwrap_or_exit("could not delete note");
+
+    eprintln!("deleted {}", selected_note.path.display());
+}
+
+/// Ask the user a yes or no question on stderr, defaulting to no.
+fn confirm(prompt: &str) -> bool {
+    eprint!("{prompt} [y/N] ");
+
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .unwrap_or_exit("could not read answer");
+
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Offer to resume, save or discard each draft of a new note that was left behind when quicknotes
+/// last exited while the editor was open.
+fn offer_abandoned_drafts(config: &NoteConfig, editor: &CommandEditor) {
+    let drafts = match quicknotes::abandoned_drafts(config) {
+        Ok(drafts) => drafts,
+        Err(err) => {
+            eprintln!(
+                "{}: could not look for unsaved drafts: {err}",
+                "warning".yellow()
+            );
+            return;
+        }
+    };
+
+    for draft in &drafts {
+        eprintln!(
+            "{}: found an unsaved draft of {}, started {}",
+            "warning".yellow(),
+            draft.destination.display(),
+            draft.started_at.format("%Y-%m-%d %H:%M:%S")
+        );
+
+        let Some(action) = pick_draft_action(draft) else {
+            return;
+        };
+
+        // Drafts are kept in plain text, so those marked as encrypted need a passphrase to store
+        let passphrase = (matches!(action, DraftAction::Resume | DraftAction::Store)
+            && draft.is_encrypted())
+        .then(|| read_passphrase(true).unwrap_or_exit("could not read passphrase"));
+
+        match action {
+            DraftAction::Resume => {
+                match quicknotes::resume_draft(config, editor, draft, passphrase.as_deref())
+                    .unwrap_or_exit("could not resume draft")
+                {
+                    Some(path) => eprintln!("saved draft to {}", path.display()),
+                    None => eprintln!("draft was empty, and has been discarded"),
+                }
+            }
+
+            DraftAction::Store => {
+                match quicknotes::store_draft(config, draft, passphrase.as_deref())
+                    .unwrap_or_exit("could not save draft")
+                {
+                    Some(path) => eprintln!("saved draft to {}", path.display()),
+                    None => eprintln!("draft was empty, and has been discarded"),
+                }
+            }
+
+            DraftAction::Discard => {
+                quicknotes::discard_draft(draft).unwrap_or_exit("could not discard draft");
+            }
+
+            DraftAction::Skip => {}
+        }
+    }
+}
+
+/// Pick what to do with an abandoned draft. Returns None if the user quit the picker.
+fn pick_draft_action(draft: &Draft) -> Option<DraftAction> {
+    let summary = draft
+        .destination
+        .file_name()
+        .unwrap_or(draft.destination.as_os_str())
+        .to_string_lossy()
+        .into_owned();
+    let mut picker = PickerOptions::new()
+        .case_matching(CaseMatching::Smart)
+        .picker(DisplayRenderer);
+
+    picker.extend(
+        [
+            DraftAction::Resume,
+            DraftAction::Store,
+            DraftAction::Discard,
+            DraftAction::Skip,
+        ]
+        .into_iter()
+        .map(|action| DraftChoice {
+            action,
+            summary: summary.clone(),
+        }),
+    );
+
+    pick(&mut picker, keybind_default)
+        .unwrap_or_exit("could not launch picker")
+        .map(|choice| choice.action)
+}
+
+/// Pick what to do with an inbox entry. Returns None if the user quit the picker.
+fn pick_inbox_action(entry: &InboxEntry) -> Option<InboxAction> {
+    let mut picker = PickerOptions::new()
+        .case_matching(CaseMatching::Smart)
+        .picker(DisplayRenderer);
+
+    picker.extend(
+        [
+            InboxAction::NewNote,
+            InboxAction::Daily,
+            InboxAction::Delete,
+            InboxAction::Skip,
+        ]
+        .into_iter()
+        .map(|action| InboxChoice {
+            action,
+            summary: entry.summary().to_string(),
+        }),
+    );
 
-        "daily" => quicknotes::indexed_notes_with_kind(config, quicknotes::NoteKind::Daily)
-            .unwrap_or_exit("couldn't load notes"),
+    pick(&mut picker, keybind_default)
+        .unwrap_or_exit("could not launch picker")
+        .map(|choice| choice.action)
+}
 
-        _ => unreachable!("invalid argument, should be caught by clap"),
-    };
+/// Pick a note from the index with the given kind. Before anything is typed, notes are ordered by
+/// how frequently and recently they've been opened. If `recent` is given, only that many of the
+/// most recently opened notes are shown.
+fn pick_indexed_note(config: &NoteConfig, kind: &str, recent: Option<usize>) -> Option<IndexEntry> {
+    let indexed_notes = load_indexed_notes(config, kind);
 
     let mut picker = PickerOptions::new()
         .highlight(true)
         .case_matching(CaseMatching::Smart)
         .picker(IndexedNoteRenderer);
 
-    let picker_injector = picker.injector();
+    let usage = quicknotes::note_usage(config).unwrap_or_exit("couldn't load note usage");
+    let entries = build_index_entires(indexed_notes);
+    let entries = match recent {
+        Some(count) => most_recently_opened(entries, &usage, count),
+        None => order_by_frecency(entries, &usage, Utc::now()),
+    };
+    picker.injector().extend(entries.iter().cloned());
+
+    // Pressing tab restarts the picker with the same query, which is seen by the thread below,
+    // so that it can put the entries back with their previews shown or hidden.
+    let observer = picker.injector_observer(false);
+    thread::spawn(move || {
+        let mut show_previews = false;
+        while let Ok(mut injector) = observer.recv() {
+            show_previews = !show_previews;
+            if show_previews {
+                injector.extend(entries.iter().map(IndexEntry::with_preview));
+            } else {
+                injector.extend(entries.iter().cloned());
+            }
+        }
+    });
+
+    let keybind = |key_event| match key_event {
+        KeyEvent {
+            kind: KeyEventKind::Press,
+            modifiers: KeyModifiers::NONE,
+            code: KeyCode::Tab,
+            ..
+        } => Some(Event::Restart),
 
-    for entry in build_index_entires(indexed_notes) {
-        picker_injector.push(entry);
-    }
+        key_event => keybind_default(key_event),
+    };
 
-    if let Some(selected_note) = pick(&mut picker).unwrap_or_exit("could not launch picker") {
-        open_note(config, editor, selected_note.note.kind, &selected_note.path)
-            .unwrap_or_exit("could not open selected file");
-    }
+    pick(&mut picker, keybind)
+        .unwrap_or_exit("could not launch picker")
+        .cloned()
 }
 
 fn load_config() -> anyhow::Result<OnDiskConfig> {
@@ -313,6 +1966,9 @@ fn default_config() -> anyhow::Result<OnDiskConfig> {
         notes_root,
         note_file_extension: ".md".to_string(),
         editor_command: None,
+        date_dialect: OnDiskDateDialect::default(),
+        daily: OnDiskDailyConfig::default(),
+        inbox: OnDiskInboxConfig::default(),
     })
 }
 
@@ -320,6 +1976,25 @@ fn fallback_editor() -> String {
     env::var("EDITOR").unwrap_or_else(|_err| "nano".to_string())
 }
 
+/// Get the passphrase for encrypted notes. If `confirm` is set, the passphrase is about to be used
+/// to encrypt a new note, so it must be entered twice to guard against typos.
+fn read_passphrase(confirm: bool) -> anyhow::Result<String> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_VARIABLE) {
+        return Ok(passphrase);
+    }
+
+    let passphrase = rpassword::prompt_password("Passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(anyhow!("the passphrase must not be empty"));
+    }
+
+    if confirm && rpassword::prompt_password("Confirm passphrase: ")? != passphrase {
+        return Err(anyhow!("the passphrases did not match"));
+    }
+
+    Ok(passphrase)
+}
+
 fn ensure_config_directory_exists() -> anyhow::Result<()> {
     let config_directory = config_directory_path()?;
     ensure_directory_exists(&config_directory)
@@ -329,8 +2004,8 @@ fn ensure_notes_dir_exists(config: &NoteConfig) -> anyhow::Result<()> {
     ensure_directory_exists(&config.notes_directory_path())
 }
 
-fn ensure_daily_dir_exists(config: &NoteConfig) -> anyhow::Result<()> {
-    ensure_directory_exists(&config.daily_directory_path())
+fn ensure_periodic_dir_exists(config: &NoteConfig, period: Period) -> anyhow::Result<()> {
+    ensure_directory_exists(&config.periodic_directory_path(period))
 }
 
 fn ensure_root_dir_exists(config: &NoteConfig) -> anyhow::Result<()> {
@@ -407,6 +2082,132 @@ fn build_index_entires(entries: HashMap<PathBuf, IndexedNote>) -> Vec<IndexEntry
         .collect::<Vec<_>>()
 }
 
+fn order_by_frecency(
+    entries: Vec<IndexEntry>,
+    usage: &HashMap<PathBuf, NoteUsage>,
+    now: DateTime<Utc>,
+) -> Vec<IndexEntry> {
+    let frecency = |entry: &IndexEntry| {
+        usage
+            .get(&entry.path)
+            .map_or(0.0, |note_usage| note_usage.frecency(now))
+    };
+
+    entries
+        .into_iter()
+        .sorted_by(|entry_a, entry_b| {
+            frecency(entry_b)
+                .total_cmp(&frecency(entry_a))
+                .then_with(|| {
+                    entry_b
+                        .note
+                        .preamble
+                        .created_at
+                        .cmp(&entry_a.note.preamble.created_at)
+                })
+        })
+        .collect()
+}
+
+fn most_recently_opened(
+    entries: Vec<IndexEntry>,
+    usage: &HashMap<PathBuf, NoteUsage>,
+    count: usize,
+) -> Vec<IndexEntry> {
+    entries
+        .into_iter()
+        .filter_map(|entry| {
+            let last_opened_at = usage.get(&entry.path)?.last_opened_at;
+
+            Some((last_opened_at, entry))
+        })
+        .sorted_by(|(opened_a, _entry_a), (opened_b, _entry_b)| opened_b.cmp(opened_a))
+        .take(count)
+        .map(|(_opened_at, entry)| entry)
+        .collect()
+}
+
+/// Sort notes for `quicknotes list`. Dates are sorted newest first, with ties broken by title.
+fn sort_for_listing(
+    notes: HashMap<PathBuf, IndexedNote>,
+    sort: &str,
+) -> Vec<(PathBuf, IndexedNote)> {
+    let by_title = |(path_a, note_a): &(PathBuf, IndexedNote),
+                    (path_b, note_b): &(PathBuf, IndexedNote)| {
+        note_a
+            .preamble
+            .title
+            .cmp(&note_b.preamble.title)
+            .then_with(|| path_a.cmp(path_b))
+    };
+
+    notes
+        .into_iter()
+        .sorted_by(|entry_a, entry_b| {
+            let (_path_a, note_a) = entry_a;
+            let (_path_b, note_b) = entry_b;
+
+            match sort {
+                "created" => note_b
+                    .preamble
+                    .created_at
+                    .cmp(&note_a.preamble.created_at)
+                    .then_with(|| by_title(entry_a, entry_b)),
+
+                "updated" => last_updated_at(&note_b.preamble)
+                    .cmp(&last_updated_at(&note_a.preamble))
+                    .then_with(|| by_title(entry_a, entry_b)),
+
+                "title" => by_title(entry_a, entry_b),
+
+                _ => unreachable!("invalid argument, should be caught by clap"),
+            }
+        })
+        .collect()
+}
+
+/// The last time a note was changed. Notes that have never been edited since they were
+/// created have no `updated_at`, so their creation time is used instead.
+fn last_updated_at(preamble: &quicknotes::NotePreamble) -> DateTime<FixedOffset> {
+    preamble.updated_at.unwrap_or(preamble.created_at)
+}
+
+/// Filter tasks for `quicknotes tasks`, and order them so that tasks with the soonest due dates
+/// come first. Tasks without a due date are kept in the order they appear in their notes.
+fn filter_tasks(
+    tasks: Vec<IndexedTask>,
+    done: Option<bool>,
+    due_before: Option<NaiveDate>,
+) -> Vec<IndexedTask> {
+    tasks
+        .into_iter()
+        .filter(|indexed_task| done.is_none_or(|done| indexed_task.task.done == done))
+        .filter(|indexed_task| {
+            due_before
+                .is_none_or(|due_before| indexed_task.task.due.is_some_and(|due| due < due_before))
+        })
+        .sorted_by(|task_a, task_b| match (task_a.task.due, task_b.task.due) {
+            (Some(due_a), Some(due_b)) => due_a.cmp(&due_b),
+            (Some(_due_a), None) => cmp::Ordering::Less,
+            (None, Some(_due_b)) => cmp::Ordering::Greater,
+            (None, None) => cmp::Ordering::Equal,
+        })
+        .collect()
+}
+
+/// Parse a task location of the form `PATH:LINE`.
+fn parse_task_location(location: &str) -> anyhow::Result<(PathBuf, usize)> {
+    let (path, line_number) = location
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("'{location}' must be of the form PATH:LINE"))?;
+
+    let line_number = line_number
+        .parse()
+        .map_err(|err| anyhow!("invalid line number '{line_number}': {err}"))?;
+
+    Ok((PathBuf::from(path), line_number))
+}
+
 fn override_title_with_date(title: &str, created_at: DateTime<FixedOffset>) -> String {
     let formatted_date = created_at
         .format("(%Y-%m-%d %H:%M:%S)")
@@ -416,10 +2217,11 @@ fn override_title_with_date(title: &str, created_at: DateTime<FixedOffset>) -> S
     format!("{title} {formatted_date}")
 }
 
-fn pick<T: Send + Sync + 'static, R: Render<T>>(
+fn pick<T: Send + Sync + 'static, R: Render<T>, F: Fn(KeyEvent) -> Option<Event>>(
     picker: &mut Picker<T, R>,
+    keybind: F,
 ) -> anyhow::Result<Option<&T>> {
-    picker.pick().or_else(|err| {
+    picker.pick_with_keybind(keybind).or_else(|err| {
         if let PickError::UserInterrupted = err {
             // A user hitting ctrl-c is no different than esc for this purpose
             Ok(None)
@@ -429,15 +2231,154 @@ fn pick<T: Send + Sync + 'static, R: Render<T>>(
     })
 }
 
-fn fuzzy_offset_from_date(date: NaiveDate, offset: &str) -> Result<NaiveDate, anyhow::Error> {
-    // this will always be valid because 00:00:00 is a valid time
-    let marker = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
-    let changed = chrono_english::parse_date_string(offset, marker, Dialect::Us)?;
-    if changed.num_seconds_from_midnight() > 0 {
-        return Err(anyhow!("invalid offset"));
+fn render_preview(note: &IndexedNote, body: &str) -> String {
+    let tags = note
+        .preamble
+        .tags
+        .iter()
+        .map(|tag| format!("#{tag}"))
+        .join(" ");
+
+    let metadata = format!(
+        "  {} · {}{}",
+        note.preamble.created_at.format("%Y-%m-%d %H:%M"),
+        kind_name(note.kind),
+        if tags.is_empty() {
+            String::new()
+        } else {
+            format!(" · {tags}")
+        }
+    );
+
+    let body_lines = body
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(PREVIEW_LINES)
+        // The picker can't render control characters, so we must strip them out
+        .map(|line| {
+            let cleaned = line
+                .replace('\t', "    ")
+                .chars()
+                .filter(|c| !c.is_control())
+                .collect::<String>();
+
+            format!("  │ {cleaned}")
+        });
+
+    std::iter::once(metadata).chain(body_lines).join("\n")
+}
+
+fn kind_name(kind: NoteKind) -> &'static str {
+    match kind {
+        NoteKind::Note => "note",
+        NoteKind::Daily => "daily",
+        NoteKind::Weekly => "weekly",
+        NoteKind::Monthly => "monthly",
+    }
+}
+
+fn render_note(contents: &NoteContents, render_markdown: bool) -> String {
+    let title = &contents.preamble.title;
+    let created_at = contents
+        .preamble
+        .created_at
+        .format("created %Y-%m-%d %H:%M:%S")
+        .to_string();
+
+    let rule = "─".repeat(title.chars().count().max(created_at.len()));
+    let body = contents.body.trim_start_matches('\n');
+    let rendered_body = if render_markdown {
+        quicknotes::render_markdown(body)
+    } else {
+        body.to_string()
+    };
+
+    format!(
+        "{}\n{}\n{}\n\n{rendered_body}\n",
+        title.bold(),
+        created_at.dimmed(),
+        rule.dimmed()
+    )
+}
+
+/// Display the given text with the user's `$PAGER` (or `less`, if unset). If stdout is not a
+/// terminal, or no pager is available, the text is written to stdout directly.
+fn page(text: &str) -> anyhow::Result<()> {
+    if !io::stdout().is_terminal() {
+        return write_to_stdout(text);
+    }
+
+    let pager = env::var("PAGER").unwrap_or_else(|_err| "less".to_string());
+    let mut pager_parts = pager.split_whitespace();
+    let Some(pager_program) = pager_parts.next() else {
+        return write_to_stdout(text);
+    };
+
+    let mut command = Command::new(pager_program);
+    command.args(pager_parts).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        // Allow our colors through, and don't page if the text fits on one screen
+        command.env("LESS", "FRX");
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return write_to_stdout(text),
+        Err(err) => return Err(anyhow!("could not spawn pager '{pager_program}': {err}")),
+    };
+
+    let mut stdin = child.stdin.take().expect("stdin was piped");
+    match stdin.write_all(text.as_bytes()) {
+        // The user quit the pager before reading everything; that's fine
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+        res => res?,
+    }
+
+    drop(stdin);
+    child.wait()?;
+
+    Ok(())
+}
+
+fn write_to_stdout(text: &str) -> anyhow::Result<()> {
+    let mut stdout = io::stdout().lock();
+    match stdout.write_all(text.as_bytes()) {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        res => Ok(res?),
+    }
+}
+
+/// Get the current time, in the timezone given by `--tz` if there is one.
+fn current_time(args: &clap::ArgMatches) -> DateTime<FixedOffset> {
+    let now = Local::now();
+
+    match args.get_one::<Timezone>("tz") {
+        None => now.fixed_offset(),
+        Some(Timezone::Fixed(offset)) => now.with_timezone(offset),
+        // The offset is taken at the current instant, so that daylight saving time is respected
+        Some(Timezone::Named(timezone)) => now.with_timezone(timezone).fixed_offset(),
+    }
+}
+
+/// Get today's date for the purposes of daily notes, which may still be yesterday if the
+/// configured `day_starts_at` has not yet passed.
+fn current_date(config: &NoteConfig, args: &clap::ArgMatches) -> NaiveDate {
+    config.daily.date_at(&current_time(args))
+}
+
+/// Parse a timezone, given as `UTC`, a fixed offset from UTC such as `+09:00`, or an IANA name
+/// such as `America/New_York`.
+fn parse_timezone(timezone: &str) -> anyhow::Result<Timezone> {
+    if timezone.eq_ignore_ascii_case("utc") || timezone.eq_ignore_ascii_case("z") {
+        return Ok(Timezone::Fixed(Utc.fix()));
     }
 
-    Ok(changed.date_naive())
+    FixedOffset::from_str(timezone)
+        .map(Timezone::Fixed)
+        .or_else(|_err| Tz::from_str(timezone).map(Timezone::Named))
+        .map_err(|_err| {
+            anyhow!("must be UTC, an offset from UTC such as +09:00, or a name such as America/New_York")
+        })
 }
 
 #[cfg(test)]
@@ -446,6 +2387,7 @@ mod tests {
     use quicknotes::{Editor, NotePreamble};
     use serde::de::value::StrDeserializer;
     use serde::de::IntoDeserializer;
+    use test_case::test_case;
 
     use super::*;
 
@@ -455,6 +2397,9 @@ mod tests {
             notes_root: Path::new("/home/me/notes").to_owned(),
             note_file_extension: ".txt".to_string(),
             editor_command: Some("vim".to_string()),
+            date_dialect: OnDiskDateDialect::default(),
+            daily: OnDiskDailyConfig::default(),
+            inbox: OnDiskInboxConfig::default(),
         };
 
         let (_note_config, editor) = disk_config.unpack("emacs");
@@ -468,6 +2413,9 @@ mod tests {
             notes_root: Path::new("/home/me/notes").to_owned(),
             note_file_extension: ".txt".to_string(),
             editor_command: None,
+            date_dialect: OnDiskDateDialect::default(),
+            daily: OnDiskDailyConfig::default(),
+            inbox: OnDiskInboxConfig::default(),
         };
 
         let (_note_config, editor) = disk_config.unpack("vim");
@@ -481,6 +2429,9 @@ mod tests {
             notes_root: Path::new("/home/me/notes").to_owned(),
             note_file_extension: ".md".to_string(),
             editor_command: None,
+            date_dialect: OnDiskDateDialect::default(),
+            daily: OnDiskDailyConfig::default(),
+            inbox: OnDiskInboxConfig::default(),
         };
 
         let (note_config, _editor) = disk_config.unpack("vim");
@@ -488,6 +2439,61 @@ mod tests {
         assert_eq!(note_config.file_extension, ".md");
     }
 
+    #[test_case("2015-10-21T01:30:00-07:00", "2015-10-20"; "before the day starts")]
+    #[test_case("2015-10-21T04:00:00-07:00", "2015-10-21"; "when the day starts")]
+    #[test_case("2015-10-21T23:59:00-07:00", "2015-10-21"; "late in the day")]
+    fn day_starts_at_is_read_from_the_config_file(time: &str, expected_date: &str) {
+        let disk_config: OnDiskConfig = toml::from_str(
+            "notes_root = \"/home/me/notes\"\nnote_file_extension = \".md\"\n\n[daily]\nday_starts_at = \"04:00\"\n",
+        )
+        .expect("could not parse config");
+
+        let (note_config, _editor) = disk_config.unpack("vim");
+        let time = DateTime::parse_from_rfc3339(time).unwrap();
+
+        assert_eq!(
+            note_config.daily.date_at(&time),
+            NaiveDate::parse_from_str(expected_date, "%Y-%m-%d").unwrap()
+        );
+    }
+
+    #[test]
+    fn day_starts_at_must_be_a_time() {
+        let result = toml::from_str::<OnDiskConfig>(
+            "notes_root = \"/home/me/notes\"\nnote_file_extension = \".md\"\n\n[daily]\nday_starts_at = \"4am\"\n",
+        );
+
+        assert!(
+            result.is_err(),
+            "expected error, got {:?}",
+            result.ok().map(|_| ())
+        );
+    }
+
+    #[test_case("UTC", 0; "utc")]
+    #[test_case("+09:00", 9 * 60 * 60; "positive offset")]
+    #[test_case("-0530", -(5 * 60 + 30) * 60; "negative offset without colon")]
+    fn parses_timezones(timezone: &str, expected_offset_seconds: i32) {
+        let parsed = parse_timezone(timezone).expect("could not parse timezone");
+
+        assert_eq!(
+            parsed,
+            Timezone::Fixed(FixedOffset::east_opt(expected_offset_seconds).unwrap())
+        );
+    }
+
+    #[test]
+    fn parses_named_timezones() {
+        let parsed = parse_timezone("America/New_York").expect("could not parse timezone");
+
+        assert_eq!(parsed, Timezone::Named(Tz::America__New_York));
+    }
+
+    #[test]
+    fn unknown_timezones_are_rejected() {
+        assert!(parse_timezone("America/Springfield").is_err());
+    }
+
     #[test]
     fn deserialize_extension_removes_dot_to_file_extension() {
         let deserializer: StrDeserializer<'static, serde::de::value::Error> =
@@ -530,28 +2536,6 @@ mod tests {
         assert!(OnDiskConfig::deserialize_notes_root(deserializer).is_err());
     }
 
-    #[test]
-    fn fuzzy_offset_from_date_allows_date_based_offsets() {
-        let date =
-            fuzzy_offset_from_date(NaiveDate::from_ymd_opt(2015, 10, 21).unwrap(), "2 days ago")
-                .expect("could not convert from offset");
-
-        assert_eq!(date, NaiveDate::from_ymd_opt(2015, 10, 19).unwrap());
-    }
-
-    #[test]
-    fn fuzzy_offset_from_date_does_not_allow_time_based_offset() {
-        let res = fuzzy_offset_from_date(
-            NaiveDate::from_ymd_opt(2015, 10, 21).unwrap(),
-            "3 hours ago",
-        );
-
-        assert!(
-            res.is_err(),
-            "should not have been able to perform this conversion"
-        );
-    }
-
     #[test]
     fn build_index_entries_does_not_override_titles_for_unique_notes() {
         let make_created_at = |day_offset: u32| {
@@ -569,6 +2553,10 @@ mod tests {
                     preamble: NotePreamble {
                         created_at: make_created_at(0),
                         title: "abc".to_string(),
+                        tags: vec![],
+                        updated_at: None,
+                        date: None,
+                        encrypted: false,
                     },
                     kind: quicknotes::NoteKind::Note,
                 },
@@ -579,6 +2567,10 @@ mod tests {
                     preamble: NotePreamble {
                         created_at: make_created_at(1),
                         title: "def".to_string(),
+                        tags: vec![],
+                        updated_at: None,
+                        date: None,
+                        encrypted: false,
                     },
                     kind: quicknotes::NoteKind::Note,
                 },
@@ -589,6 +2581,10 @@ mod tests {
                     preamble: NotePreamble {
                         created_at: make_created_at(2),
                         title: "xyz".to_string(),
+                        tags: vec![],
+                        updated_at: None,
+                        date: None,
+                        encrypted: false,
                     },
                     kind: quicknotes::NoteKind::Note,
                 },
@@ -620,6 +2616,10 @@ mod tests {
                     preamble: NotePreamble {
                         created_at: make_created_at(0),
                         title: "abc".to_string(),
+                        tags: vec![],
+                        updated_at: None,
+                        date: None,
+                        encrypted: false,
                     },
                     kind: quicknotes::NoteKind::Note,
                 },
@@ -630,6 +2630,10 @@ mod tests {
                     preamble: NotePreamble {
                         created_at: make_created_at(1),
                         title: "def".to_string(),
+                        tags: vec![],
+                        updated_at: None,
+                        date: None,
+                        encrypted: false,
                     },
                     kind: quicknotes::NoteKind::Note,
                 },
@@ -640,6 +2644,10 @@ mod tests {
                     preamble: NotePreamble {
                         created_at: make_created_at(2),
                         title: "abc".to_string(),
+                        tags: vec![],
+                        updated_at: None,
+                        date: None,
+                        encrypted: false,
                     },
                     kind: quicknotes::NoteKind::Note,
                 },
@@ -669,6 +2677,364 @@ mod tests {
         assert_eq!(overrides, expected);
     }
 
+    #[test]
+    fn rendered_note_has_header_before_body() {
+        colored::control::set_override(false);
+
+        let contents = NoteContents {
+            preamble: NotePreamble {
+                title: "abc".to_string(),
+                created_at: FixedOffset::east_opt(-7 * 60 * 60)
+                    .unwrap()
+                    .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                    .single()
+                    .unwrap(),
+                tags: vec![],
+                updated_at: None,
+                date: None,
+                encrypted: false,
+            },
+            body: "\n# hello\n".to_string(),
+        };
+
+        let rendered = render_note(&contents, false);
+
+        assert_eq!(
+            rendered,
+            "abc\ncreated 2015-10-21 07:28:00\n───────────────────────────\n\n# hello\n\n"
+        );
+    }
+
+    #[test]
+    fn preview_shows_metadata_and_first_lines_of_body() {
+        let note = IndexedNote {
+            preamble: NotePreamble {
+                title: "abc".to_string(),
+                created_at: FixedOffset::east_opt(-7 * 60 * 60)
+                    .unwrap()
+                    .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                    .single()
+                    .unwrap(),
+                tags: vec!["work".to_string()],
+                updated_at: None,
+                date: None,
+                encrypted: false,
+            },
+            kind: quicknotes::NoteKind::Note,
+        };
+
+        let body = "\nfirst\n\n\tsecond\nthird\nfourth\nfifth\nsixth\n";
+
+        assert_eq!(
+            render_preview(&note, body),
+            [
+                "  2015-10-21 07:28 · note · #work",
+                "  │ first",
+                "  │     second",
+                "  │ third",
+                "  │ fourth",
+                "  │ fifth",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn previews_are_rendered_beneath_the_title() {
+        let note = IndexedNote {
+            preamble: NotePreamble {
+                title: "abc".to_string(),
+                created_at: FixedOffset::east_opt(-7 * 60 * 60)
+                    .unwrap()
+                    .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                    .single()
+                    .unwrap(),
+                tags: vec!["work".to_string()],
+                updated_at: None,
+                date: None,
+                encrypted: false,
+            },
+            kind: quicknotes::NoteKind::Note,
+        };
+        let entry = IndexEntry::new(PathBuf::from("/notes/abc.md"), note);
+        let previewed_entry = IndexEntry {
+            preview: Some("  │ first".to_string()),
+            ..entry.clone()
+        };
+
+        assert_eq!(IndexedNoteRenderer.render(&entry), "abc #work");
+        assert_eq!(
+            IndexedNoteRenderer.render(&previewed_entry),
+            "abc #work\n  │ first"
+        );
+    }
+
+    #[test]
+    fn entries_are_ordered_by_frecency_then_creation_time() {
+        let make_entry = |name: &str, day: u32| {
+            IndexEntry::new(
+                PathBuf::from(format!(
+                    "/home/ferris/Documents/quicknotes/notes/{name}.txt"
+                )),
+                IndexedNote {
+                    preamble: NotePreamble {
+                        title: name.to_string(),
+                        created_at: FixedOffset::east_opt(-7 * 60 * 60)
+                            .unwrap()
+                            .with_ymd_and_hms(2015, 10, day, 7, 28, 0)
+                            .single()
+                            .unwrap(),
+                        tags: vec![],
+                        updated_at: None,
+                        date: None,
+                        encrypted: false,
+                    },
+                    kind: quicknotes::NoteKind::Note,
+                },
+            )
+        };
+
+        let now = Utc.with_ymd_and_hms(2015, 10, 25, 7, 28, 0).unwrap();
+        let entries = vec![
+            make_entry("never-opened-old", 1),
+            make_entry("opened-once", 2),
+            make_entry("never-opened-new", 3),
+            make_entry("opened-often", 4),
+        ];
+
+        let usage = HashMap::from([
+            (
+                PathBuf::from("/home/ferris/Documents/quicknotes/notes/opened-once.txt"),
+                NoteUsage {
+                    last_opened_at: now,
+                    open_count: 1,
+                },
+            ),
+            (
+                PathBuf::from("/home/ferris/Documents/quicknotes/notes/opened-often.txt"),
+                NoteUsage {
+                    last_opened_at: now,
+                    open_count: 5,
+                },
+            ),
+        ]);
+
+        let titles = order_by_frecency(entries, &usage, now)
+            .into_iter()
+            .map(|entry| entry.note.preamble.title)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            titles,
+            vec![
+                "opened-often",
+                "opened-once",
+                "never-opened-new",
+                "never-opened-old"
+            ]
+        );
+    }
+
+    #[test]
+    fn most_recently_opened_only_includes_opened_notes() {
+        let make_entry = |name: &str| {
+            IndexEntry::new(
+                PathBuf::from(format!(
+                    "/home/ferris/Documents/quicknotes/notes/{name}.txt"
+                )),
+                IndexedNote {
+                    preamble: NotePreamble {
+                        title: name.to_string(),
+                        created_at: FixedOffset::east_opt(-7 * 60 * 60)
+                            .unwrap()
+                            .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                            .single()
+                            .unwrap(),
+                        tags: vec![],
+                        updated_at: None,
+                        date: None,
+                        encrypted: false,
+                    },
+                    kind: quicknotes::NoteKind::Note,
+                },
+            )
+        };
+
+        let now = Utc.with_ymd_and_hms(2015, 10, 25, 7, 28, 0).unwrap();
+        let entries = vec![
+            make_entry("never-opened"),
+            make_entry("opened-yesterday"),
+            make_entry("opened-today"),
+            make_entry("opened-last-week"),
+        ];
+
+        let usage = HashMap::from([
+            (
+                PathBuf::from("/home/ferris/Documents/quicknotes/notes/opened-yesterday.txt"),
+                NoteUsage {
+                    last_opened_at: now - chrono::TimeDelta::days(1),
+                    open_count: 1,
+                },
+            ),
+            (
+                PathBuf::from("/home/ferris/Documents/quicknotes/notes/opened-today.txt"),
+                NoteUsage {
+                    last_opened_at: now,
+                    open_count: 1,
+                },
+            ),
+            (
+                PathBuf::from("/home/ferris/Documents/quicknotes/notes/opened-last-week.txt"),
+                NoteUsage {
+                    last_opened_at: now - chrono::TimeDelta::weeks(1),
+                    open_count: 30,
+                },
+            ),
+        ]);
+
+        let titles = most_recently_opened(entries, &usage, 2)
+            .into_iter()
+            .map(|entry| entry.note.preamble.title)
+            .collect::<Vec<_>>();
+
+        assert_eq!(titles, vec!["opened-today", "opened-yesterday"]);
+    }
+
+    #[test_case("created", &["updated-late", "never-updated", "updated-early"]; "by creation time")]
+    #[test_case("updated", &["updated-early", "updated-late", "never-updated"]; "by update time")]
+    #[test_case("title", &["never-updated", "updated-early", "updated-late"]; "by title")]
+    fn listed_notes_are_sorted(sort: &str, expected_titles: &[&str]) {
+        let offset = FixedOffset::east_opt(-7 * 60 * 60).unwrap();
+        let make_note = |name: &str, created_day: u32, updated_day: Option<u32>| {
+            (
+                PathBuf::from(format!(
+                    "/home/ferris/Documents/quicknotes/notes/{name}.txt"
+                )),
+                IndexedNote {
+                    preamble: NotePreamble {
+                        title: name.to_string(),
+                        created_at: offset
+                            .with_ymd_and_hms(2015, 10, created_day, 7, 28, 0)
+                            .single()
+                            .unwrap(),
+                        tags: vec![],
+                        updated_at: updated_day.map(|day| {
+                            offset
+                                .with_ymd_and_hms(2015, 10, day, 7, 28, 0)
+                                .single()
+                                .unwrap()
+                        }),
+                        date: None,
+                        encrypted: false,
+                    },
+                    kind: quicknotes::NoteKind::Note,
+                },
+            )
+        };
+
+        let notes = HashMap::from([
+            make_note("never-updated", 21, None),
+            make_note("updated-early", 20, Some(25)),
+            make_note("updated-late", 22, Some(23)),
+        ]);
+
+        let titles = sort_for_listing(notes, sort)
+            .into_iter()
+            .map(|(_path, note)| note.preamble.title)
+            .collect::<Vec<_>>();
+
+        assert_eq!(titles, expected_titles);
+    }
+
+    #[test_case(None, None, &["due soon", "due later", "not due", "done"]; "all tasks")]
+    #[test_case(Some(false), None, &["due soon", "due later", "not due"]; "open tasks")]
+    #[test_case(Some(true), None, &["done"]; "done tasks")]
+    #[test_case(None, NaiveDate::from_ymd_opt(2015, 10, 23), &["due soon"]; "due before")]
+    fn tasks_are_filtered_and_ordered_by_due_date(
+        done: Option<bool>,
+        due_before: Option<NaiveDate>,
+        expected_texts: &[&str],
+    ) {
+        let make_task =
+            |line_number: usize, text: &str, done: bool, due_day: Option<u32>| IndexedTask {
+                path: PathBuf::from("/home/ferris/Documents/quicknotes/notes/todo.txt"),
+                note_title: "todo".to_string(),
+                task: quicknotes::Task {
+                    line_number,
+                    text: text.to_string(),
+                    done,
+                    due: due_day.and_then(|day| NaiveDate::from_ymd_opt(2015, 10, day)),
+                    tags: vec![],
+                },
+            };
+
+        let tasks = vec![
+            make_task(5, "not due", false, None),
+            make_task(6, "due later", false, Some(25)),
+            make_task(7, "done", true, None),
+            make_task(8, "due soon", false, Some(22)),
+        ];
+
+        let texts = filter_tasks(tasks, done, due_before)
+            .into_iter()
+            .map(|indexed_task| indexed_task.task.text)
+            .collect::<Vec<_>>();
+
+        assert_eq!(texts, expected_texts);
+    }
+
+    #[test]
+    fn task_location_is_split_at_the_last_colon() {
+        let (path, line_number) = parse_task_location("C:/notes/todo.txt:12").unwrap();
+
+        assert_eq!(path, PathBuf::from("C:/notes/todo.txt"));
+        assert_eq!(line_number, 12);
+    }
+
+    #[test_case("todo.txt"; "no line number")]
+    #[test_case("todo.txt:twelve"; "invalid line number")]
+    fn task_location_must_have_a_line_number(location: &str) {
+        assert!(parse_task_location(location).is_err());
+    }
+
+    #[test_case(KeyCode::Left, NaiveDate::from_ymd_opt(2015, 10, 20); "left goes back a day")]
+    #[test_case(KeyCode::Char('l'), NaiveDate::from_ymd_opt(2015, 10, 22); "l goes forward a day")]
+    #[test_case(KeyCode::Up, NaiveDate::from_ymd_opt(2015, 10, 14); "up goes back a week")]
+    #[test_case(KeyCode::Down, NaiveDate::from_ymd_opt(2015, 10, 28); "down goes forward a week")]
+    #[test_case(KeyCode::PageDown, NaiveDate::from_ymd_opt(2015, 11, 21); "page down goes forward a month")]
+    fn calendar_keys_move_the_selection(code: KeyCode, expected: Option<NaiveDate>) {
+        let action = calendar_action(
+            NaiveDate::from_ymd_opt(2015, 10, 21).unwrap(),
+            KeyEvent::new(code, KeyModifiers::NONE),
+        );
+
+        assert_eq!(action, CalendarAction::Select(expected.unwrap()));
+    }
+
+    #[test_case(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), CalendarAction::Open; "enter opens")]
+    #[test_case(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE), CalendarAction::Quit; "escape quits")]
+    #[test_case(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL), CalendarAction::Quit; "ctrl c quits")]
+    #[test_case(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE), CalendarAction::Ignore; "other keys are ignored")]
+    fn calendar_keys_can_open_or_quit(key_event: KeyEvent, expected: CalendarAction) {
+        let action = calendar_action(NaiveDate::from_ymd_opt(2015, 10, 21).unwrap(), key_event);
+
+        assert_eq!(action, expected);
+    }
+
+    #[test_case("2015-10", NaiveDate::from_ymd_opt(2015, 10, 1); "year and month")]
+    #[test_case("1 month ago", NaiveDate::from_ymd_opt(2015, 9, 21); "fuzzy date")]
+    #[test_case("last month", NaiveDate::from_ymd_opt(2015, 9, 21); "last month")]
+    fn months_can_be_given_exactly_or_fuzzily(month: &str, expected: Option<NaiveDate>) {
+        let parsed = parse_month(
+            NaiveDate::from_ymd_opt(2015, 10, 21).unwrap(),
+            month,
+            DateDialect::Us,
+        )
+        .expect("could not parse month");
+
+        assert_eq!(parsed, expected.unwrap());
+    }
+
     #[test]
     fn title_override_starts_with_title() {
         let created_at = FixedOffset::east_opt(-7 * 60 * 60)
@@ -1,3 +1,4 @@
+use std::fmt::Write;
 use std::io::{self, BufRead, BufReader, Read};
 
 use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Offset, TimeZone, Timelike};
@@ -17,6 +18,27 @@ pub struct Preamble {
         deserialize_with = "deserialize_datetime"
     )]
     pub created_at: DateTime<FixedOffset>,
+    /// The day that a daily note is for. Dailies can have any title, so this is used to find
+    /// them by date.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_date",
+        deserialize_with = "deserialize_optional_date"
+    )]
+    pub date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Whether the body of the note is stored encrypted with a passphrase.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypted: bool,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_datetime",
+        deserialize_with = "deserialize_optional_datetime"
+    )]
+    pub updated_at: Option<DateTime<FixedOffset>>,
 }
 
 impl Preamble {
@@ -47,7 +69,14 @@ pub struct SerializeError(toml::ser::Error);
 impl Preamble {
     #[must_use]
     pub fn new(title: String, created_at: DateTime<FixedOffset>) -> Self {
-        Self { title, created_at }
+        Self {
+            title,
+            created_at,
+            date: None,
+            tags: Vec::new(),
+            encrypted: false,
+            updated_at: None,
+        }
     }
 }
 
@@ -61,16 +90,139 @@ pub fn filename_stem_for_title(title: &str) -> String {
     base_name
 }
 
+/// A best-effort inverse of [`filename_stem_for_title`]. The original capitalization and any
+/// special characters can't be recovered, so `my-cool-note` becomes `my cool note`.
+pub fn title_for_filename_stem(stem: &str) -> String {
+    stem.split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .join(" ")
+}
+
 pub fn filename_stem_for_date(date: NaiveDate) -> String {
     date.format("%Y-%m-%d").to_string()
 }
 
+/// The inverse of [`filename_stem_for_date`]. Returns None if the stem is not a date.
+pub fn date_for_filename_stem(stem: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok()
+}
+
+/// Format the date with the given strftime format. Returns None if the format is invalid, or
+/// refers to fields a date does not have (e.g. the hour).
+pub fn format_date(date: NaiveDate, format: &str) -> Option<String> {
+    let mut formatted = String::new();
+    write!(formatted, "{}", date.format(format)).ok()?;
+
+    Some(formatted)
+}
+
+/// Parse a date written with the given `strftime` format. Returns None if the text does not match
+/// the format, or the format does not describe a single day.
+pub fn parse_date(text: &str, format: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text, format).ok()
+}
+
+pub fn filename_stem_for_week(date: NaiveDate) -> String {
+    date.format("%G-W%V").to_string()
+}
+
+pub fn filename_stem_for_month(date: NaiveDate) -> String {
+    date.format("%Y-%m").to_string()
+}
+
 pub fn extract_preamble<R: Read>(reader: R) -> Result<Preamble, InvalidPreambleError> {
+    parse_preamble(BufReader::new(reader))
+}
+
+/// Extract both the preamble and everything that follows it. The body is returned
+/// exactly as it is stored, including any leading newlines.
+pub fn extract_preamble_and_body<R: Read>(
+    reader: R,
+) -> Result<(Preamble, String), InvalidPreambleError> {
     let mut buffered_reader = BufReader::new(reader);
-    ensure_preamble_fence(&mut buffered_reader)?;
-    let toml = read_until_closing_fence(&mut buffered_reader)?;
+    let preamble = parse_preamble(&mut buffered_reader)?;
 
-    toml::from_str(&toml).map_err(InvalidPreambleError::DeserializeError)
+    let mut body = String::new();
+    buffered_reader
+        .read_to_string(&mut body)
+        .map_err(InvalidPreambleError::IOError)?;
+
+    Ok((preamble, body))
+}
+
+/// Set the `updated_at` field of the preamble in the given note contents, leaving every other
+/// line of the note untouched. If the preamble has no `updated_at` field, one is added at the end
+/// of the preamble.
+pub fn stamp_updated_at(
+    contents: &str,
+    updated_at: DateTime<FixedOffset>,
+) -> Result<String, InvalidPreambleError> {
+    #[derive(Serialize)]
+    struct UpdatedAt {
+        #[serde(serialize_with = "serialize_datetime")]
+        updated_at: DateTime<FixedOffset>,
+    }
+
+    let stamp = toml::to_string(&UpdatedAt { updated_at })
+        .expect("a single timestamp must be serializable as TOML");
+
+    let mut lines = contents.split_inclusive('\n');
+    let opening_fence = lines.next().unwrap_or_default();
+    if opening_fence != "---\n" {
+        return Err(InvalidPreambleError::MalformedFence(
+            opening_fence.to_string(),
+        ));
+    }
+
+    let mut stamped = opening_fence.to_string();
+    let mut wrote_stamp = false;
+    // Once a table has started, every key belongs to it, so the stamp must go before the first one
+    let mut in_table = false;
+    loop {
+        let line = lines
+            .next()
+            .ok_or(InvalidPreambleError::UnterminatedFence())?;
+
+        if line == "---" || line == "---\n" {
+            if !wrote_stamp {
+                stamped += &stamp;
+            }
+            stamped += line;
+            break;
+        }
+
+        if !in_table && is_table_header_line(line) {
+            in_table = true;
+            if !wrote_stamp {
+                stamped += &stamp;
+                wrote_stamp = true;
+            }
+        }
+
+        if !in_table && is_updated_at_line(line) {
+            stamped += &stamp;
+            wrote_stamp = true;
+        } else {
+            stamped += line;
+        }
+    }
+
+    stamped.extend(lines);
+
+    Ok(stamped)
+}
+
+fn is_table_header_line(line: &str) -> bool {
+    // Lines inside a multi-line array may also start with `[`, but are followed by a comma
+    let line = line.split('#').next().unwrap_or_default().trim();
+
+    line.starts_with('[') && line.ends_with(']')
+}
+
+fn is_updated_at_line(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix("updated_at")
+        .is_some_and(|rest| rest.trim_start().starts_with('='))
 }
 
 #[derive(Error, Debug)]
@@ -88,6 +240,24 @@ pub enum InvalidPreambleError {
     IOError(io::Error),
 }
 
+impl InvalidPreambleError {
+    /// Check if the note has no preamble at all, rather than one that is malformed. A note whose
+    /// first line is a fence (perhaps with trailing whitespace) is considered to have a preamble.
+    pub fn is_missing_preamble(&self) -> bool {
+        match self {
+            Self::MalformedFence(line) => line.trim_end() != "---",
+            Self::UnterminatedFence() | Self::DeserializeError(_) | Self::IOError(_) => false,
+        }
+    }
+}
+
+fn parse_preamble<R: BufRead>(mut reader: R) -> Result<Preamble, InvalidPreambleError> {
+    ensure_preamble_fence(&mut reader)?;
+    let toml = read_until_closing_fence(&mut reader)?;
+
+    toml::from_str(&toml).map_err(InvalidPreambleError::DeserializeError)
+}
+
 fn ensure_preamble_fence<R: BufRead>(mut reader: R) -> Result<(), InvalidPreambleError> {
     let mut text = String::new();
     reader
@@ -131,7 +301,7 @@ fn remove_specials(s: &str) -> String {
         .join("")
 }
 
-fn serialize_datetime<S: Serializer, T: TimeZone>(
+pub(crate) fn serialize_datetime<S: Serializer, T: TimeZone>(
     dt: &DateTime<T>,
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
@@ -185,7 +355,25 @@ fn toml_datetime<Tz: TimeZone, S: Serializer>(dt: &DateTime<Tz>) -> Result<TomlD
     Ok(converted)
 }
 
-fn deserialize_datetime<'a, D: Deserializer<'a>>(
+// serde passes `serialize_with` functions a reference to the field, so this must take `&Option`
+#[allow(clippy::ref_option, clippy::trivially_copy_pass_by_ref)]
+fn serialize_optional_datetime<S: Serializer, T: TimeZone>(
+    dt: &Option<DateTime<T>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match dt {
+        Some(dt) => serialize_datetime(dt, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_optional_datetime<'a, D: Deserializer<'a>>(
+    deserializer: D,
+) -> Result<Option<chrono::DateTime<FixedOffset>>, D::Error> {
+    deserialize_datetime(deserializer).map(Some)
+}
+
+pub(crate) fn deserialize_datetime<'a, D: Deserializer<'a>>(
     deserializer: D,
 ) -> Result<chrono::DateTime<FixedOffset>, D::Error> {
     let dt: TomlDateTime = Deserialize::deserialize(deserializer)?;
@@ -217,6 +405,53 @@ fn deserialize_datetime<'a, D: Deserializer<'a>>(
         .ok_or_else(|| de::Error::custom("timestamp {dt} is unresolvable"))
 }
 
+// serde passes `serialize_with` functions a reference to the field, so this must take `&Option`
+#[allow(clippy::ref_option, clippy::trivially_copy_pass_by_ref)]
+fn serialize_optional_date<S: Serializer>(
+    date: &Option<NaiveDate>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let Some(date) = date else {
+        return serializer.serialize_none();
+    };
+
+    let toml_date = TomlDateTime {
+        date: Some(toml::value::Date {
+            year: date
+                .year()
+                .try_into()
+                .map_err(|_err| ser::Error::custom("year must fit into a u16"))?,
+            month: date
+                .month()
+                .try_into()
+                .map_err(|_err| ser::Error::custom("month must fit into a u8"))?,
+            day: date
+                .day()
+                .try_into()
+                .map_err(|_err| ser::Error::custom("day must fit into a u8"))?,
+        }),
+        time: None,
+        offset: None,
+    };
+
+    toml_date.serialize(serializer)
+}
+
+fn deserialize_optional_date<'a, D: Deserializer<'a>>(
+    deserializer: D,
+) -> Result<Option<NaiveDate>, D::Error> {
+    let dt: TomlDateTime = Deserialize::deserialize(deserializer)?;
+    if dt.time.is_some() || dt.offset.is_some() {
+        return Err(de::Error::custom("expected a date without a time"));
+    }
+
+    let date = dt.date.ok_or(de::Error::custom("missing date"))?;
+
+    NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into())
+        .map(Some)
+        .ok_or_else(|| de::Error::custom(format!("date {date} is invalid")))
+}
+
 fn utc_offset_seconds<Tz: TimeZone>(dt: &DateTime<Tz>) -> i32 {
     dt.offset().fix().local_minus_utc()
 }
@@ -238,6 +473,10 @@ mod tests {
                 .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
                 .single()
                 .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
         };
 
         assert_eq!(
@@ -259,11 +498,150 @@ mod tests {
                 .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
                 .single()
                 .unwrap(),
+            tags: vec![],
+            updated_at: None,
+            date: None,
+            encrypted: false,
         };
 
         assert_eq!(preamble, expected);
     }
 
+    #[test]
+    fn can_read_preamble_with_tags() {
+        let reader = StringReader::new(
+            "---\ntitle = \"Hello world\"\ncreated_at = 2015-10-21T07:28:00-07:00\ntags = [\"work\", \"ideas\"]\n---",
+        );
+
+        let preamble = extract_preamble(reader).expect("failed to parse preamble");
+
+        assert_eq!(preamble.tags, vec!["work".to_string(), "ideas".to_string()]);
+    }
+
+    #[test]
+    fn can_read_body_after_preamble() {
+        let reader = StringReader::new(
+            "---\ntitle = \"Hello world\"\ncreated_at = 2015-10-21T07:28:00-07:00\n---\n\nsick notes bro\n",
+        );
+
+        let (preamble, body) = extract_preamble_and_body(reader).expect("failed to parse preamble");
+
+        assert_eq!(preamble.title, "Hello world");
+        assert_eq!(body, "\nsick notes bro\n");
+    }
+
+    #[test]
+    fn preamble_date_is_stored_as_a_toml_date() {
+        let preamble = Preamble {
+            date: NaiveDate::from_ymd_opt(2015, 10, 21),
+            ..Preamble::new(
+                "Wednesday, Oct 21 2015".to_string(),
+                FixedOffset::east_opt(-7 * 60 * 60)
+                    .unwrap()
+                    .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+                    .single()
+                    .unwrap(),
+            )
+        };
+
+        let serialized = preamble.serialize().expect("failed to serialize preamble");
+        let deserialized =
+            extract_preamble(StringReader::new(&serialized)).expect("failed to parse preamble");
+
+        assert_eq!(
+            serialized,
+            "---\ntitle = \"Wednesday, Oct 21 2015\"\ncreated_at = 2015-10-21T07:28:00-07:00\ndate = 2015-10-21\n---"
+        );
+        assert_eq!(deserialized, preamble);
+    }
+
+    #[test_case("my-cool-note", "my cool note"; "dashes")]
+    #[test_case("my_cool--note", "my cool note"; "underscores and repeated separators")]
+    fn titles_are_guessed_from_filename_stems(stem: &str, expected: &str) {
+        assert_eq!(title_for_filename_stem(stem), expected);
+    }
+
+    #[test_case("%Y/%m/%d", Some("2015/10/21"); "with slashes")]
+    #[test_case("%A, %b %-d %Y", Some("Wednesday, Oct 21 2015"); "with names")]
+    #[test_case("%Y-%m-%d %H:%M", None; "with time")]
+    #[test_case("%Q", None; "with invalid specifier")]
+    fn formats_dates(format: &str, expected: Option<&str>) {
+        let date = NaiveDate::from_ymd_opt(2015, 10, 21).unwrap();
+
+        assert_eq!(format_date(date, format).as_deref(), expected);
+    }
+
+    #[test_case("2015/10/21", "%Y/%m/%d", Some(NaiveDate::from_ymd_opt(2015, 10, 21).unwrap()); "with slashes")]
+    #[test_case("Wednesday, Oct 21 2015", "%A, %b %-d %Y", Some(NaiveDate::from_ymd_opt(2015, 10, 21).unwrap()); "with names")]
+    #[test_case("Meeting notes", "%Y-%m-%d", None; "not a date")]
+    fn parses_dates(text: &str, format: &str, expected: Option<NaiveDate>) {
+        assert_eq!(parse_date(text, format), expected);
+    }
+
+    #[test]
+    fn can_read_preamble_with_updated_at() {
+        let reader = StringReader::new(
+            "---\ntitle = \"Hello world\"\ncreated_at = 2015-10-21T07:28:00-07:00\nupdated_at = 2015-10-22T08:00:00-07:00\n---",
+        );
+
+        let preamble = extract_preamble(reader).expect("failed to parse preamble");
+
+        assert_eq!(
+            preamble.updated_at,
+            Some(
+                FixedOffset::east_opt(-7 * 60 * 60)
+                    .unwrap()
+                    .with_ymd_and_hms(2015, 10, 22, 8, 0, 0)
+                    .single()
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test_case(
+        "---\ntitle = \"Hello world\"\n# keep me\ncreated_at = 2015-10-21T07:28:00-07:00\n---\nbody\n",
+        "---\ntitle = \"Hello world\"\n# keep me\ncreated_at = 2015-10-21T07:28:00-07:00\nupdated_at = 2015-10-22T08:00:00-07:00\n---\nbody\n";
+        "adds missing field"
+    )]
+    #[test_case(
+        "---\ntitle = \"Hello world\"\nupdated_at = 2015-10-21T09:00:00-07:00\ncreated_at = 2015-10-21T07:28:00-07:00\n---\nupdated_at = not me\n",
+        "---\ntitle = \"Hello world\"\nupdated_at = 2015-10-22T08:00:00-07:00\ncreated_at = 2015-10-21T07:28:00-07:00\n---\nupdated_at = not me\n";
+        "replaces existing field in place"
+    )]
+    #[test_case(
+        "---\ntitle = \"Hello world\"\ncreated_at = 2015-10-21T07:28:00-07:00\n[extra]\nupdated_at = \"not me\"\n---\nbody\n",
+        "---\ntitle = \"Hello world\"\ncreated_at = 2015-10-21T07:28:00-07:00\nupdated_at = 2015-10-22T08:00:00-07:00\n[extra]\nupdated_at = \"not me\"\n---\nbody\n";
+        "adds missing field before tables"
+    )]
+    fn stamping_updated_at_preserves_the_rest_of_the_note(contents: &str, expected: &str) {
+        let updated_at = FixedOffset::east_opt(-7 * 60 * 60)
+            .unwrap()
+            .with_ymd_and_hms(2015, 10, 22, 8, 0, 0)
+            .single()
+            .unwrap();
+
+        let stamped = stamp_updated_at(contents, updated_at).expect("failed to stamp note");
+
+        assert_eq!(stamped, expected);
+    }
+
+    #[test]
+    fn stamping_updated_at_requires_a_terminated_preamble() {
+        let result = stamp_updated_at(
+            "---\ntitle = \"Hello world\"\n",
+            FixedOffset::east_opt(0)
+                .unwrap()
+                .with_ymd_and_hms(2015, 10, 22, 8, 0, 0)
+                .single()
+                .unwrap(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(InvalidPreambleError::UnterminatedFence())
+        ));
+    }
+
     #[test]
     fn filename_for_title_converts_to_lowercase() {
         assert_eq!("note", filename_stem_for_title("Note"));
@@ -0,0 +1,71 @@
+use std::fmt::{self, Display, Formatter};
+
+use chrono::NaiveDate;
+
+use crate::index::NoteKind;
+use crate::note;
+
+/// The span of time covered by a periodic note, such as a daily note.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Period {
+    /// The kind of note that is used to store notes of this period.
+    #[must_use]
+    pub fn note_kind(self) -> NoteKind {
+        match self {
+            Self::Daily => NoteKind::Daily,
+            Self::Weekly => NoteKind::Weekly,
+            Self::Monthly => NoteKind::Monthly,
+        }
+    }
+
+    /// The name of the directory, relative to the notes root, that notes of this period are
+    /// stored in.
+    pub(crate) fn directory_name(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+        }
+    }
+
+    /// The filename stem for the note of this period containing the given date. This also
+    /// serves as the note's title.
+    pub(crate) fn filename_stem(self, date: NaiveDate) -> String {
+        match self {
+            Self::Daily => note::filename_stem_for_date(date),
+            Self::Weekly => note::filename_stem_for_week(date),
+            Self::Monthly => note::filename_stem_for_month(date),
+        }
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Daily => write!(f, "daily"),
+            Self::Weekly => write!(f, "weekly"),
+            Self::Monthly => write!(f, "monthly"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(Period::Daily, NaiveDate::from_ymd_opt(2025, 2, 5).unwrap(), "2025-02-05"; "daily")]
+    #[test_case(Period::Weekly, NaiveDate::from_ymd_opt(2025, 2, 5).unwrap(), "2025-W06"; "weekly")]
+    #[test_case(Period::Weekly, NaiveDate::from_ymd_opt(2024, 12, 30).unwrap(), "2025-W01"; "weekly uses the iso year")]
+    #[test_case(Period::Monthly, NaiveDate::from_ymd_opt(2025, 2, 5).unwrap(), "2025-02"; "monthly")]
+    fn filename_stem_covers_the_period(period: Period, date: NaiveDate, expected: &str) {
+        assert_eq!(period.filename_stem(date), expected);
+    }
+}
@@ -0,0 +1,150 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, FixedOffset};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::index::NoteKind;
+use crate::note;
+use crate::sidecar::{SidecarKind, WriteSidecarError};
+
+/// The sidecar beside a preserved note, recording where it was meant to be stored.
+const RECOVERY_SIDECAR: SidecarKind = SidecarKind {
+    suffix: ".recovery.toml",
+    description: "recovery information",
+};
+
+/// A note that could not be stored, and was moved to the recovery directory instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecoverableNote {
+    /// Where the note is in the recovery directory.
+    pub path: PathBuf,
+    /// Where the note was meant to be stored.
+    pub destination: PathBuf,
+    pub kind: NoteKind,
+    /// When the note was moved to the recovery directory.
+    pub preserved_at: DateTime<FixedOffset>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecoveryInfo {
+    destination: PathBuf,
+    kind: NoteKind,
+
+    #[serde(
+        serialize_with = "note::serialize_datetime",
+        deserialize_with = "note::deserialize_datetime"
+    )]
+    preserved_at: DateTime<FixedOffset>,
+}
+
+/// Write the sidecar for a note preserved at `path`, recording where it was meant to be stored.
+pub fn write_sidecar(
+    path: &Path,
+    destination: &Path,
+    kind: NoteKind,
+    preserved_at: DateTime<FixedOffset>,
+) -> Result<(), WriteSidecarError> {
+    RECOVERY_SIDECAR.write(
+        path,
+        &RecoveryInfo {
+            destination: destination.to_owned(),
+            kind,
+            preserved_at,
+        },
+    )
+}
+
+/// List the notes in the recovery directory, sorted by path. Notes whose sidecar is missing or
+/// cannot be read are skipped with a warning. A missing recovery directory has no notes.
+pub fn list(directory: &Path) -> Result<Vec<RecoverableNote>, io::Error> {
+    let notes = RECOVERY_SIDECAR
+        .list::<RecoveryInfo>(directory)?
+        .into_iter()
+        .map(|(path, info)| RecoverableNote {
+            path,
+            destination: info.destination,
+            kind: info.kind,
+            preserved_at: info.preserved_at,
+        })
+        .collect();
+
+    Ok(notes)
+}
+
+/// Remove a recovered note, and its sidecar, from the recovery directory.
+pub fn remove(note: &RecoverableNote) -> Result<(), io::Error> {
+    RECOVERY_SIDECAR.remove(&note.path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn notes_are_listed_with_their_sidecars() {
+        let directory = TempDir::new().expect("could not create directory");
+        let path = directory.path().join("my-note.md");
+        let preserved_at = FixedOffset::east_opt(-7 * 60 * 60)
+            .unwrap()
+            .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+            .single()
+            .unwrap();
+        fs::write(&path, "hello, world!\n").expect("could not write note");
+        write_sidecar(
+            &path,
+            Path::new("/notes/my-note.md"),
+            NoteKind::Note,
+            preserved_at,
+        )
+        .expect("could not write sidecar");
+
+        let notes = list(directory.path()).expect("could not list notes");
+
+        assert_eq!(
+            notes,
+            vec![RecoverableNote {
+                path: path.clone(),
+                destination: PathBuf::from("/notes/my-note.md"),
+                kind: NoteKind::Note,
+                preserved_at,
+            }]
+        );
+
+        remove(&notes[0]).expect("could not remove note");
+        assert!(list(directory.path())
+            .expect("could not list notes")
+            .is_empty());
+    }
+
+    #[test]
+    fn notes_stored_as_toml_are_not_mistaken_for_sidecars() {
+        let directory = TempDir::new().expect("could not create directory");
+        let preserved_at = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+            .single()
+            .unwrap();
+        let paths = [
+            directory.path().join("my-note.toml"),
+            directory.path().join("my-note.toml.toml"),
+        ];
+        for path in &paths {
+            fs::write(path, "hello, world!\n").expect("could not write note");
+            write_sidecar(path, path, NoteKind::Note, preserved_at)
+                .expect("could not write sidecar");
+        }
+
+        let notes = list(directory.path()).expect("could not list notes");
+
+        assert_eq!(
+            notes.into_iter().map(|note| note.path).collect::<Vec<_>>(),
+            paths
+        );
+    }
+}
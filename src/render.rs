@@ -0,0 +1,189 @@
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+use colored::{ColoredString, Colorize};
+use itertools::Itertools;
+
+const CODE_BLOCK_INDENT: &str = "    ";
+const CALENDAR_WEEKDAYS: &str = "Mo Tu We Th Fr Sa Su";
+
+/// Render markdown for display in a terminal.
+///
+/// This is not a complete markdown renderer; it only styles the block-level elements that are
+/// most common in notes (headings, lists, and fenced code blocks). Everything else is passed
+/// through untouched.
+#[must_use]
+pub fn render_markdown(text: &str) -> String {
+    let mut in_code_block = false;
+
+    text.lines()
+        .filter_map(|line| {
+            if is_code_fence(line) {
+                in_code_block = !in_code_block;
+                return None;
+            }
+
+            let rendered = if in_code_block {
+                format!("{CODE_BLOCK_INDENT}{}", line.green())
+            } else {
+                render_line(line)
+            };
+
+            Some(rendered)
+        })
+        .join("\n")
+}
+
+/// Render a month as a grid of days for display in a terminal, with weeks starting on Monday.
+/// Days in `highlighted` (e.g. those with a daily note) are shown in bold green, `today` is
+/// underlined, and `selected`, if given, is shown in reverse video.
+#[must_use]
+pub fn render_calendar(
+    month: NaiveDate,
+    highlighted: &BTreeSet<NaiveDate>,
+    today: NaiveDate,
+    selected: Option<NaiveDate>,
+) -> String {
+    let first_day = month - Days::new(u64::from(month.day0()));
+    let next_month = first_day + Months::new(1);
+    let header = format!(
+        "{:^width$}",
+        first_day.format("%B %Y").to_string(),
+        width = CALENDAR_WEEKDAYS.len()
+    );
+
+    let leading_blanks = (0..first_day.weekday().num_days_from_monday()).map(|_| "  ".to_string());
+    let days = first_day
+        .iter_days()
+        .take_while(|day| *day < next_month)
+        .map(|day| {
+            let mut rendered = format!("{:>2}", day.day()).normal();
+            if highlighted.contains(&day) {
+                rendered = rendered.bold().green();
+            }
+            if day == today {
+                rendered = rendered.underline();
+            }
+            if Some(day) == selected {
+                rendered = rendered.reversed();
+            }
+
+            rendered.to_string()
+        });
+
+    let weeks = leading_blanks
+        .chain(days)
+        .chunks(7)
+        .into_iter()
+        .map(|mut week| week.join(" "))
+        .join("\n");
+
+    format!("{}\n{CALENDAR_WEEKDAYS}\n{weeks}", header.bold())
+}
+
+fn render_line(line: &str) -> String {
+    if let Some(heading) = render_heading(line) {
+        return heading.to_string();
+    }
+
+    if let Some(list_item) = render_list_item(line) {
+        return list_item;
+    }
+
+    line.to_string()
+}
+
+fn is_code_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+fn render_heading(line: &str) -> Option<ColoredString> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let text = line[level..].strip_prefix(' ')?.trim();
+    let rendered = if level == 1 {
+        text.bold().underline().magenta()
+    } else {
+        text.bold().magenta()
+    };
+
+    Some(rendered)
+}
+
+fn render_list_item(line: &str) -> Option<String> {
+    let content = line.trim_start();
+    let indent = &line[..line.len() - content.len()];
+
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| content.strip_prefix(bullet))
+    {
+        return Some(format!("{indent}{} {item}", "•".yellow()));
+    }
+
+    let (number, item) = content.split_once(". ")?;
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(format!("{indent}{} {item}", format!("{number}.").yellow()))
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("# My Note", "My Note"; "top level heading")]
+    #[test_case("### Details", "Details"; "nested heading")]
+    #[test_case("#hashtag", "#hashtag"; "hashtag is not a heading")]
+    #[test_case("- an item", "• an item"; "dash bullet")]
+    #[test_case("  * nested item", "  • nested item"; "nested star bullet")]
+    #[test_case("12. numbered item", "12. numbered item"; "numbered item")]
+    #[test_case("1.5 is a number", "1.5 is a number"; "number is not a list")]
+    #[test_case("just some text", "just some text"; "plain text")]
+    fn renders_markdown_lines(line: &str, expected: &str) {
+        colored::control::set_override(false);
+
+        assert_eq!(render_markdown(line), expected);
+    }
+
+    #[test]
+    fn calendar_starts_weeks_on_monday() {
+        colored::control::set_override(false);
+
+        let rendered = render_calendar(
+            NaiveDate::from_ymd_opt(2015, 10, 21).unwrap(),
+            &BTreeSet::new(),
+            NaiveDate::from_ymd_opt(2015, 10, 21).unwrap(),
+            None,
+        );
+
+        assert_eq!(
+            rendered,
+            [
+                "    October 2015    ",
+                "Mo Tu We Th Fr Sa Su",
+                "          1  2  3  4",
+                " 5  6  7  8  9 10 11",
+                "12 13 14 15 16 17 18",
+                "19 20 21 22 23 24 25",
+                "26 27 28 29 30 31",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn code_blocks_are_indented_without_fences() {
+        colored::control::set_override(false);
+
+        let rendered = render_markdown("before\n```rust\n# not a heading\n```\nafter");
+
+        assert_eq!(rendered, "before\n    # not a heading\nafter");
+    }
+}
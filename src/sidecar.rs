@@ -0,0 +1,131 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::warning;
+
+/// A kind of TOML file kept beside another file to describe it, such as the recovery information
+/// kept beside a preserved note. The sidecar's path is the file's path with `suffix` added.
+pub struct SidecarKind {
+    /// The suffix added to a file's name to get the path of its sidecar. It must be distinct from
+    /// any note extension, so that notes stored as `.toml` are not mistaken for sidecars.
+    pub suffix: &'static str,
+    /// What the sidecar holds, for error messages.
+    pub description: &'static str,
+}
+
+impl SidecarKind {
+    /// Get the path of the sidecar for the file at `path`, e.g. `my-note.md.recovery.toml`, so
+    /// that files of any extension can have one.
+    pub fn path(&self, path: &Path) -> PathBuf {
+        let mut sidecar_path = OsString::from(path);
+        sidecar_path.push(self.suffix);
+
+        PathBuf::from(sidecar_path)
+    }
+
+    /// Write the sidecar for the file at `path`.
+    pub fn write<T: Serialize>(&self, path: &Path, sidecar: &T) -> Result<(), WriteSidecarError> {
+        let serialized =
+            toml::to_string(sidecar).map_err(|err| WriteSidecarError::SerializeError {
+                description: self.description,
+                err,
+            })?;
+
+        fs::write(self.path(path), serialized).map_err(|err| WriteSidecarError::IOError {
+            description: self.description,
+            err,
+        })
+    }
+
+    /// Read the sidecar for the file at `path`.
+    fn read<T: DeserializeOwned>(&self, path: &Path) -> Result<T, ReadSidecarError> {
+        let contents = fs::read_to_string(self.path(path))?;
+        let sidecar = toml::from_str(&contents)?;
+
+        Ok(sidecar)
+    }
+
+    /// List the files in `directory` along with their sidecars, sorted by path. Files whose sidecar
+    /// is missing or cannot be read are skipped with a warning. A missing directory has no files.
+    pub fn list<T: DeserializeOwned>(
+        &self,
+        directory: &Path,
+    ) -> Result<Vec<(PathBuf, T)>, io::Error> {
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let files = entries
+            .map_ok(|entry| entry.path())
+            .filter_ok(|path| {
+                path.is_file()
+                    && path
+                        .file_name()
+                        .is_none_or(|name| !name.to_string_lossy().ends_with(self.suffix))
+            })
+            .filter_map_ok(|path| match self.read(&path) {
+                Ok(sidecar) => Some((path, sidecar)),
+
+                Err(err) => {
+                    warning!(
+                        "Could not read {} for {}: {}",
+                        self.description,
+                        path.display(),
+                        err
+                    );
+                    None
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .sorted_by(|(path_a, _), (path_b, _)| path_a.cmp(path_b))
+            .collect();
+
+        Ok(files)
+    }
+
+    /// Remove the file at `path`, and its sidecar.
+    pub fn remove(&self, path: &Path) -> Result<(), io::Error> {
+        fs::remove_file(path)?;
+
+        match fs::remove_file(self.path(path)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum WriteSidecarError {
+    #[error("could not serialize {description}: {err}")]
+    SerializeError {
+        description: &'static str,
+        #[source]
+        err: toml::ser::Error,
+    },
+
+    #[error("could not write {description}: {err}")]
+    IOError {
+        description: &'static str,
+        #[source]
+        err: io::Error,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum ReadSidecarError {
+    #[error(transparent)]
+    IOError(#[from] io::Error),
+
+    #[error(transparent)]
+    DeserializeError(#[from] toml::de::Error),
+}
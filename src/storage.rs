@@ -2,23 +2,37 @@ use std::fs::{self, File, OpenOptions};
 use std::io::{self, BufReader, Read, Seek};
 use std::path::{Path, PathBuf};
 
+use chrono::Local;
 use itertools::Itertools;
 use regex::Regex;
 use sha2::{Digest, Sha256};
 use tempfile::TempPath;
 use thiserror::Error;
 
-use crate::warning;
+use crate::backend::{self, Backend};
+use crate::index::NoteKind;
+use crate::{recovery, sidecar, warning};
 
 pub struct TempFileHandle {
     opened: BufReader<File>,
     path: TempPath,
+    recovery: Option<Recovery>,
+}
+
+/// Where a note is moved to if it cannot be stored, so that it can be recovered later.
+pub struct Recovery {
+    pub directory: PathBuf,
+    pub kind: NoteKind,
 }
 
 /// Stores the given tempfile into a storage medium. This trait can not be implemented
 /// by other modules, in order to avoid heap allocations for handling the error.
 pub trait StoreNote: sealed::StoreNote {
     fn store(self, tempfile: TempFileHandle) -> Result<PathBuf, StoreNoteError>;
+
+    /// The path the note is intended to be stored at. The note may end up elsewhere, e.g. to
+    /// avoid clobbering an existing file.
+    fn destination(&self) -> PathBuf;
 }
 
 #[derive(Error, Debug)]
@@ -32,6 +46,8 @@ pub struct StoreNoteError {
 enum InnerStoreNoteError {
     StoreNoteInError(#[from] StoreNoteInError),
     StoreNoteAtError(#[from] StoreNoteAtError),
+    StoreNoteOverError(#[from] StoreNoteOverError),
+    StoreNoteInBackendError(#[from] StoreNoteInBackendError),
 }
 
 mod sealed {
@@ -55,13 +71,37 @@ pub struct StoreNoteIn {
     pub file_extension: String,
 }
 
+/// A [`StoreNote`] strategy which replaces the note at the given destination. The note is written
+/// beside the destination and renamed over it, so the existing note is never left half-written.
+pub struct StoreNoteOver {
+    pub destination: PathBuf,
+}
+
+/// A [`StoreNote`] strategy which stores the note through a [`Backend`], at the given destination
+/// or beside it with a numbered suffix, so that no note is clobbered.
+pub struct StoreNoteInBackend<'a> {
+    pub backend: &'a dyn Backend,
+    pub destination: PathBuf,
+}
+
 impl TempFileHandle {
-    pub fn open(temppath: TempPath) -> Result<Self, io::Error> {
+    pub fn open(temppath: TempPath, recovery: Recovery) -> Result<Self, io::Error> {
+        Self::open_with(temppath, Some(recovery))
+    }
+
+    /// Open a tempfile that is kept where it is if it cannot be stored, as there is no recovery
+    /// directory to move it to.
+    pub fn open_without_recovery(temppath: TempPath) -> Result<Self, io::Error> {
+        Self::open_with(temppath, None)
+    }
+
+    fn open_with(temppath: TempPath, recovery: Option<Recovery>) -> Result<Self, io::Error> {
         let file = File::open(&temppath)?;
 
         Ok(Self {
             opened: BufReader::new(file),
             path: temppath,
+            recovery,
         })
     }
 }
@@ -71,6 +111,10 @@ impl StoreNote for StoreNoteAt {
         self.do_store(tempfile)
             .map_err(|err| StoreNoteError { inner: err.into() })
     }
+
+    fn destination(&self) -> PathBuf {
+        self.destination.clone()
+    }
 }
 
 impl StoreNoteAt {
@@ -79,13 +123,12 @@ impl StoreNoteAt {
             Ok(()) => Ok(self.destination),
 
             Err(err) => {
-                let tempfile_path = tempfile.path.display().to_string();
-                try_preserve_note(tempfile)?;
+                let preserved_path = try_preserve_note(tempfile, &self.destination)?;
 
                 Err(StoreNoteAtError::CopyError {
                     err: err.into(),
                     destination: self.destination.display().to_string(),
-                    src: tempfile_path,
+                    src: preserved_path.display().to_string(),
                 })
             }
         }
@@ -106,19 +149,118 @@ enum StoreNoteAtError {
     TryPreserveNoteError(#[from] TryPreserveNoteError),
 }
 
+impl StoreNote for StoreNoteOver {
+    fn store(self, tempfile: TempFileHandle) -> Result<PathBuf, StoreNoteError> {
+        self.do_store(tempfile)
+            .map_err(|err| StoreNoteError { inner: err.into() })
+    }
+
+    fn destination(&self) -> PathBuf {
+        self.destination.clone()
+    }
+}
+
+impl StoreNoteOver {
+    fn do_store(self, mut tempfile: TempFileHandle) -> Result<PathBuf, StoreNoteOverError> {
+        match replace_destination(&mut tempfile.opened, &self.destination) {
+            Ok(()) => Ok(self.destination),
+
+            Err(err) => {
+                let preserved_path = try_preserve_note(tempfile, &self.destination)?;
+
+                Err(StoreNoteOverError::ReplaceError {
+                    err,
+                    destination: self.destination.display().to_string(),
+                    src: preserved_path.display().to_string(),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+enum StoreNoteOverError {
+    #[error(
+        "could not replace note at {destination}. The new version still exists at {src:?}: {err}"
+    )]
+    ReplaceError {
+        src: String,
+        destination: String,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error(transparent)]
+    TryPreserveNoteError(#[from] TryPreserveNoteError),
+}
+
+impl StoreNote for StoreNoteInBackend<'_> {
+    fn store(self, tempfile: TempFileHandle) -> Result<PathBuf, StoreNoteError> {
+        self.do_store(tempfile)
+            .map_err(|err| StoreNoteError { inner: err.into() })
+    }
+
+    fn destination(&self) -> PathBuf {
+        self.destination.clone()
+    }
+}
+
+impl StoreNoteInBackend<'_> {
+    fn do_store(self, mut tempfile: TempFileHandle) -> Result<PathBuf, StoreNoteInBackendError> {
+        let mut contents = String::new();
+        let stored = tempfile
+            .opened
+            .read_to_string(&mut contents)
+            .and_then(|_size| {
+                backend::create_unclobbered(self.backend, &self.destination, &contents)
+            });
+
+        match stored {
+            Ok(path) => Ok(path),
+
+            Err(err) => {
+                let preserved_path = try_preserve_note(tempfile, &self.destination)?;
+
+                Err(StoreNoteInBackendError::CreateError {
+                    err,
+                    destination: self.destination.display().to_string(),
+                    src: preserved_path.display().to_string(),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+enum StoreNoteInBackendError {
+    #[error("could not store note at {destination}. It still exists at {src:?}: {err}")]
+    CreateError {
+        src: String,
+        destination: String,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error(transparent)]
+    TryPreserveNoteError(#[from] TryPreserveNoteError),
+}
+
 impl StoreNote for StoreNoteIn {
     fn store(self, tempfile: TempFileHandle) -> Result<PathBuf, StoreNoteError> {
         self.do_store(tempfile)
             .map_err(|err| StoreNoteError { inner: err.into() })
     }
+
+    fn destination(&self) -> PathBuf {
+        self.storage_directory
+            .join(&self.preferred_file_stem)
+            .with_extension(&self.file_extension)
+    }
 }
 
 impl StoreNoteIn {
     fn do_store(self, mut tempfile: TempFileHandle) -> Result<PathBuf, StoreNoteInError> {
-        let mut destination = self
-            .storage_directory
-            .join(self.preferred_file_stem)
-            .with_extension(&self.file_extension);
+        let mut destination = self.destination();
 
         // This is a loop to prevent the race where we generate a new filename and
         // something else inserts it quickly. It is technically possible this loops
@@ -142,26 +284,24 @@ impl StoreNoteIn {
                         }
 
                         Err(err) => {
-                            let tempfile_path = tempfile.path.display().to_string();
-                            try_preserve_note(tempfile)?;
+                            let preserved_path = try_preserve_note(tempfile, &destination)?;
 
                             return Err(StoreNoteInError::NoteClobberPreventionError {
                                 err,
                                 destination: destination.display().to_string(),
-                                src: tempfile_path,
+                                src: preserved_path.display().to_string(),
                             });
                         }
                     }
                 }
 
                 Err(err) => {
-                    let tempfile_path = tempfile.path.display().to_string();
-                    try_preserve_note(tempfile)?;
+                    let preserved_path = try_preserve_note(tempfile, &destination)?;
 
                     return Err(StoreNoteInError::CopyError {
                         err: err.into(),
                         destination: destination.display().to_string(),
-                        src: tempfile_path,
+                        src: preserved_path.display().to_string(),
                     });
                 }
             }
@@ -202,6 +342,32 @@ fn copy_to_destination<R: Read>(mut src: R, to: &Path) -> Result<(), CopyToDesti
     Ok(())
 }
 
+/// Replace the file at `to` with the contents of `src`, by writing a hidden file beside it and
+/// renaming it into place. The permissions of the existing file, if any, are kept.
+fn replace_destination<R: Read>(mut src: R, to: &Path) -> Result<(), io::Error> {
+    let directory = to
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut replacement = tempfile::Builder::new()
+        .prefix(".")
+        .tempfile_in(directory)?;
+
+    match fs::metadata(to) {
+        Ok(metadata) => replacement
+            .as_file()
+            .set_permissions(metadata.permissions())?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+
+    io::copy(&mut src, &mut replacement)?;
+    replacement.as_file().sync_all()?;
+    replacement.persist(to).map_err(|err| err.error)?;
+
+    Ok(())
+}
+
 #[derive(Error, Debug)]
 enum CopyToDestinationError {
     #[error(transparent)]
@@ -247,8 +413,8 @@ pub fn store_if_different<S: StoreNote>(
         }
 
         Err(err) => {
-            let path = tempfile.path.to_path_buf();
-            try_preserve_note(tempfile).map_err(|err| StoreIfDifferentError(err.into()))?;
+            let path = try_preserve_note(tempfile, &storage.destination())
+                .map_err(|err| StoreIfDifferentError(err.into()))?;
 
             Err(InnerStoreIfDifferentError::CheckFileError { path, err }.into())
         }
@@ -294,19 +460,37 @@ fn is_different(tempfile: &mut TempFileHandle, against: &str) -> Result<bool, io
     Ok(true)
 }
 
-fn try_preserve_note(tempfile: TempFileHandle) -> Result<(), TryPreserveNoteError> {
+/// Preserve a note that could not be stored at `destination`, returning where it was preserved.
+/// Notes are moved to the recovery directory if possible, and otherwise kept where they are.
+fn try_preserve_note(
+    mut tempfile: TempFileHandle,
+    destination: &Path,
+) -> Result<PathBuf, TryPreserveNoteError> {
+    if let Some(recovery) = tempfile.recovery.take() {
+        match preserve_in_recovery(&mut tempfile, &recovery, destination) {
+            // The tempfile is deleted when it is dropped, as it has been copied
+            Ok(preserved_path) => return Ok(preserved_path),
+
+            Err(err) => warning!(
+                "Your note could not be moved to {}: {}",
+                recovery.directory.display(),
+                err
+            ),
+        }
+    }
+
     // Store the path in case the keep operation fails somehow
     let tempfile_path = tempfile.path.to_path_buf();
 
     match tempfile.path.keep() {
-        Ok(_result) => Ok(()),
+        Ok(_result) => Ok(tempfile_path),
         Err(tempfile::PathPersistError {
             error: keep_error, ..
-        }) => match fs::read_to_string(tempfile_path) {
+        }) => match fs::read_to_string(&tempfile_path) {
             Ok(contents) => {
                 warning!("Your note could not be saved due to an error. Here are its contents");
                 eprintln!("{contents}");
-                Ok(())
+                Ok(tempfile_path)
             }
             Err(read_error) => Err(TryPreserveNoteError {
                 keep_error,
@@ -316,6 +500,67 @@ fn try_preserve_note(tempfile: TempFileHandle) -> Result<(), TryPreserveNoteErro
     }
 }
 
+/// Copy the note into the recovery directory, alongside a sidecar describing where it was meant
+/// to be stored.
+fn preserve_in_recovery(
+    tempfile: &mut TempFileHandle,
+    recovery: &Recovery,
+    destination: &Path,
+) -> Result<PathBuf, PreserveInRecoveryError> {
+    fs::create_dir_all(&recovery.directory)
+        .map_err(PreserveInRecoveryError::CreateDirectoryError)?;
+    tempfile
+        .opened
+        .rewind()
+        .map_err(PreserveInRecoveryError::ReadError)?;
+
+    let filename = destination
+        .file_name()
+        .ok_or_else(|| PreserveInRecoveryError::InvalidDestination(destination.to_owned()))?;
+    let mut preserved_path = recovery.directory.join(filename);
+    loop {
+        match copy_to_destination(&mut tempfile.opened, &preserved_path) {
+            Ok(()) => break,
+
+            Err(err) if err.is_destination_exists() => {
+                preserved_path = generate_unclobbered_destination(&preserved_path)?;
+            }
+
+            Err(err) => return Err(PreserveInRecoveryError::CopyError(err.into())),
+        }
+    }
+
+    recovery::write_sidecar(
+        &preserved_path,
+        destination,
+        recovery.kind,
+        Local::now().fixed_offset(),
+    )?;
+
+    Ok(preserved_path)
+}
+
+#[derive(Error, Debug)]
+enum PreserveInRecoveryError {
+    #[error("could not create recovery directory: {0}")]
+    CreateDirectoryError(io::Error),
+
+    #[error("could not read note: {0}")]
+    ReadError(io::Error),
+
+    #[error("{0:?} is not a valid destination for a note")]
+    InvalidDestination(PathBuf),
+
+    #[error(transparent)]
+    GenerateUnclobberedDestinationError(#[from] GenerateUnclobberedDestinationError),
+
+    #[error("could not copy note: {0}")]
+    CopyError(io::Error),
+
+    #[error(transparent)]
+    WriteSidecarError(#[from] sidecar::WriteSidecarError),
+}
+
 #[derive(Error, Debug)]
 #[error("note was unable to be preserved ({keep_error}), and then could not be read for you ({read_error}).")]
 struct TryPreserveNoteError {
@@ -326,6 +571,59 @@ struct TryPreserveNoteError {
     read_error: io::Error,
 }
 
+/// Copy the file at `source` into `directory`, keeping its filename unless a file by that name is
+/// already there, in which case a numbered suffix is added. Returns the path of the copy.
+pub fn copy_unclobbered(source: &Path, directory: &Path) -> Result<PathBuf, CopyUnclobberedError> {
+    let filename = source
+        .file_name()
+        .filter(|filename| filename.to_str().is_some())
+        .ok_or_else(|| InnerCopyUnclobberedError::InvalidSource(source.to_owned()))?;
+
+    let mut destination = directory.join(filename);
+    loop {
+        let source_file = File::open(source).map_err(InnerCopyUnclobberedError::OpenError)?;
+        match copy_to_destination(source_file, &destination) {
+            Ok(()) => return Ok(destination),
+
+            Err(err) if err.is_destination_exists() => {
+                destination = generate_unclobbered_destination(&destination)
+                    .map_err(InnerCopyUnclobberedError::from)?;
+            }
+
+            Err(err) => {
+                return Err(InnerCopyUnclobberedError::CopyError {
+                    destination,
+                    err: err.into(),
+                }
+                .into())
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct CopyUnclobberedError(#[from] InnerCopyUnclobberedError);
+
+#[derive(Error, Debug)]
+enum InnerCopyUnclobberedError {
+    #[error("{0:?} does not have a valid UTF-8 filename")]
+    InvalidSource(PathBuf),
+
+    #[error("could not open file: {0}")]
+    OpenError(io::Error),
+
+    #[error("could not copy file to {destination}: {err}")]
+    CopyError {
+        destination: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error(transparent)]
+    GenerateUnclobberedDestinationError(#[from] GenerateUnclobberedDestinationError),
+}
+
 fn generate_unclobbered_destination(
     path: &Path,
 ) -> Result<PathBuf, GenerateUnclobberedDestinationError> {
@@ -338,9 +636,7 @@ fn generate_unclobbered_destination(
 
     let extension = path
         .extension()
-        .expect("path is already a full filename")
-        .to_str()
-        .expect("file extension must be UTF-8");
+        .map(|extension| extension.to_str().expect("file extension must be UTF-8"));
 
     let dir = path.parent().expect("path is already a full path");
     let destination = find_next_destination_basename(dir, stem, extension)
@@ -356,20 +652,43 @@ struct GenerateUnclobberedDestinationError(#[from] FindNextDestinationBasenameEr
 fn find_next_destination_basename(
     dir: &Path,
     stem: &str,
-    extension: &str,
+    extension: Option<&str>,
 ) -> Result<String, FindNextDestinationBasenameError> {
+    let file_names = fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map_ok(|entry| entry.file_name())
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(FindNextDestinationBasenameError::ReadDirError)?;
+
+    Ok(next_destination_basename(
+        file_names.iter().filter_map(|file_name| file_name.to_str()),
+        stem,
+        extension,
+    ))
+}
+
+/// Get a filename for `stem` and `extension` that is not among `file_names`, by adding a number
+/// one higher than any already used (e.g. `my-note-2.txt` if `my-note-1.txt` exists).
+pub(crate) fn next_destination_basename<'a>(
+    file_names: impl IntoIterator<Item = &'a str>,
+    stem: &str,
+    extension: Option<&str>,
+) -> String {
+    let extension_suffix = extension
+        .map(|extension| format!(".{extension}"))
+        .unwrap_or_default();
     let pattern = Regex::new(&format!(
-        r"{}-(\d+).{}",
+        r"{}-(\d+){}",
         regex::escape(stem),
-        regex::escape(extension)
+        regex::escape(&extension_suffix)
     ))
     .unwrap();
 
-    let r = fs::read_dir(dir).map_err(FindNextDestinationBasenameError::ReadDirError)?;
-    let suffix_num = r
-        .filter_map_ok(|entry| {
-            let raw_file_name = entry.file_name();
-            let file_name = raw_file_name.to_str()?;
+    let suffix_num = file_names
+        .into_iter()
+        .filter_map(|file_name| {
             let captured_suffix = pattern.captures(file_name).and_then(|captures| {
                 captures
                     .iter()
@@ -384,11 +703,10 @@ fn find_next_destination_basename(
                     .expect("pattern must guarantee we have a number")
             })
         })
-        .try_fold(0, |acc, n_result| n_result.map(|n| acc.max(n)))
-        .map(|max| max + 1)
-        .map_err(FindNextDestinationBasenameError::ReadDirError)?;
+        .fold(0, u32::max)
+        + 1;
 
-    Ok(format!("{stem}-{suffix_num}.{extension}"))
+    format!("{stem}-{suffix_num}{extension_suffix}")
 }
 
 #[derive(Error, Debug)]
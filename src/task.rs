@@ -0,0 +1,189 @@
+use chrono::NaiveDate;
+
+const LIST_MARKERS: [&str; 2] = ["- ", "* "];
+const UNCHECKED_BOX: &str = "[ ]";
+const CHECKED_BOXES: [&str; 2] = ["[x]", "[X]"];
+const DUE_PREFIX: &str = "due:";
+const TAG_PREFIX: char = '@';
+
+/// A checklist item in a note, such as `- [ ] buy milk due:2015-10-21 @errands`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Task {
+    /// The line of the note file that the task is on, starting at 1.
+    pub line_number: usize,
+    /// The text after the checkbox, including any markers.
+    pub text: String,
+    pub done: bool,
+    /// The date given by a `due:YYYY-MM-DD` marker.
+    pub due: Option<NaiveDate>,
+    /// The tags given by `@tag` markers, without the `@`.
+    pub tags: Vec<String>,
+}
+
+/// Get every task in the given note file contents. The preamble is skipped, but line numbers are
+/// still relative to the start of the file.
+pub fn tasks_in_note(contents: &str) -> Vec<Task> {
+    let mut lines = contents.lines().enumerate();
+
+    // Skip past the preamble, which must start on the first line
+    if contents.starts_with("---\n") {
+        lines.next();
+        lines.by_ref().find(|(_index, line)| *line == "---");
+    }
+
+    lines
+        .filter_map(|(index, line)| parse_task(line, index + 1))
+        .collect()
+}
+
+/// Get every unchecked task (e.g. `- [ ] buy milk`) in the given note body, as it was written,
+/// including any indentation.
+pub fn unchecked_tasks(body: &str) -> impl Iterator<Item = &str> {
+    body.lines()
+        .filter(|line| parse_task(line, 0).is_some_and(|task| !task.done))
+        .map(str::trim_end)
+}
+
+/// Flip the checkbox of the task on the given line (starting at 1), leaving everything else
+/// in the note as it was. Returns None if there is no task on that line.
+pub fn toggle_task(contents: &str, line_number: usize) -> Option<(String, Task)> {
+    let index = line_number.checked_sub(1)?;
+    let line = contents.lines().nth(index)?;
+    let task = parse_task(line, line_number)?;
+
+    let checkbox_start = line.find('[').expect("tasks must have a checkbox");
+    let toggled_box = if task.done {
+        UNCHECKED_BOX
+    } else {
+        CHECKED_BOXES[0]
+    };
+    let toggled_line = format!(
+        "{}{toggled_box}{}",
+        &line[..checkbox_start],
+        &line[checkbox_start + UNCHECKED_BOX.len()..]
+    );
+
+    let toggled_contents = contents
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(i, original)| {
+            if i == index {
+                let line_ending = &original[line.len()..];
+
+                format!("{toggled_line}{line_ending}")
+            } else {
+                original.to_string()
+            }
+        })
+        .collect::<String>();
+
+    let toggled_task = Task {
+        done: !task.done,
+        ..task
+    };
+
+    Some((toggled_contents, toggled_task))
+}
+
+fn parse_task(line: &str, line_number: usize) -> Option<Task> {
+    let content = line.trim_start();
+    let item = LIST_MARKERS
+        .iter()
+        .find_map(|marker| content.strip_prefix(marker))?;
+
+    let (done, text) = if let Some(text) = item.strip_prefix(UNCHECKED_BOX) {
+        (false, text)
+    } else {
+        let text = CHECKED_BOXES
+            .iter()
+            .find_map(|checkbox| item.strip_prefix(checkbox))?;
+
+        (true, text)
+    };
+
+    let text = text.strip_prefix(' ')?.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    let due = text
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix(DUE_PREFIX))
+        .find_map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+
+    let tags = text
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix(TAG_PREFIX))
+        .map(|tag| tag.trim_end_matches(|c: char| c.is_ascii_punctuation()))
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    Some(Task {
+        line_number,
+        text: text.to_string(),
+        done,
+        due,
+        tags,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test]
+    fn finds_only_unchecked_tasks() {
+        let body = "\n# Today\n- [ ] buy milk\n- [x] walk the dog\n  * [ ] nested task  \n- [] not a task\n-[ ] also not a task\n";
+
+        assert_eq!(
+            unchecked_tasks(body).collect::<Vec<_>>(),
+            vec!["- [ ] buy milk", "  * [ ] nested task"]
+        );
+    }
+
+    #[test]
+    fn tasks_in_note_skips_the_preamble_and_counts_lines_from_the_start_of_the_file() {
+        let contents =
+            "---\ntitle = \"- [ ] not a task\"\n---\n\n- [ ] buy milk\n- [X] walk the dog\n";
+
+        let tasks = tasks_in_note(contents);
+
+        assert_eq!(
+            tasks
+                .iter()
+                .map(|task| (task.line_number, task.text.as_str(), task.done))
+                .collect::<Vec<_>>(),
+            vec![(5, "buy milk", false), (6, "walk the dog", true)]
+        );
+    }
+
+    #[test]
+    fn tasks_can_have_due_dates_and_tags() {
+        let task = parse_task("- [ ] buy milk due:2015-10-21 @errands, @home", 1).unwrap();
+
+        assert_eq!(task.due, NaiveDate::from_ymd_opt(2015, 10, 21));
+        assert_eq!(task.tags, vec!["errands".to_string(), "home".to_string()]);
+    }
+
+    #[test_case("- [ ] buy milk\n- [x] walk the dog\n", 1, "- [x] buy milk\n- [x] walk the dog\n"; "checks unchecked task")]
+    #[test_case("- [ ] buy milk\n  - [X] walk the dog", 2, "- [ ] buy milk\n  - [ ] walk the dog"; "unchecks checked task")]
+    #[test_case("- [ ] buy milk\r\n- [ ] walk the dog\r\n", 1, "- [x] buy milk\r\n- [ ] walk the dog\r\n"; "preserves line endings")]
+    fn toggling_a_task_flips_its_checkbox(contents: &str, line_number: usize, expected: &str) {
+        let (toggled, _task) = toggle_task(contents, line_number).expect("task was not found");
+
+        assert_eq!(toggled, expected);
+    }
+
+    #[test_case(0; "line zero")]
+    #[test_case(2; "not a task")]
+    #[test_case(10; "past the end")]
+    fn toggling_requires_a_task_on_the_line(line_number: usize) {
+        assert_eq!(
+            toggle_task("- [ ] buy milk\njust text\n", line_number),
+            None
+        );
+    }
+}
@@ -0,0 +1,462 @@
+use std::fs;
+
+use chrono::{DateTime, FixedOffset, TimeZone};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use itertools::Itertools;
+use quicknotes::{
+    DailyConfig, DateDialect, ImportConflict, ImportSource, InboxConfig, NoteConfig, NoteKind,
+};
+use sha2::{Digest, Sha256};
+use tempfile::tempdir;
+use test_case::test_case;
+use testutil::AppendEditor;
+
+mod testutil;
+
+fn test_time() -> DateTime<FixedOffset> {
+    FixedOffset::east_opt(-7 * 60 * 60)
+        .unwrap()
+        .with_ymd_and_hms(2015, 10, 21, 7, 28, 0)
+        .single()
+        .unwrap()
+}
+
+#[test]
+fn exports_indexed_notes_to_html() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("# Design\n\nIt runs on **1.21 gigawatts**.\n".to_string());
+    quicknotes::make_note(&config, &editor, "Flux Capacitor".to_string(), &test_time())
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    editor.note_contents(
+        "- [ ] finish the [[flux capacitor]]\n- [x] find [[plutonium]]\n".to_string(),
+    );
+    let datetime = test_time() + chrono::TimeDelta::days(1);
+    quicknotes::make_or_open_daily(&config, &editor, datetime.date_naive(), &datetime)
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    let out_dir = tempdir().expect("could not create output directory");
+    let notes = quicknotes::indexed_notes(&config).expect("could not read indexed notes");
+    let exported =
+        quicknotes::export_html(&config, notes, out_dir.path()).expect("could not export notes");
+
+    assert_eq!(exported, 2);
+
+    let note_page = fs::read_to_string(out_dir.path().join("notes/flux-capacitor.html"))
+        .expect("could not read note page");
+    insta::assert_snapshot!("note_page", note_page);
+
+    let daily_page = fs::read_to_string(out_dir.path().join("daily/2015-10-22.html"))
+        .expect("could not read daily page");
+    insta::assert_snapshot!("daily_page", daily_page);
+
+    let index_page =
+        fs::read_to_string(out_dir.path().join("index.html")).expect("could not read index page");
+    insta::assert_snapshot!("index_page", index_page);
+
+    let archive_page = fs::read_to_string(out_dir.path().join("archive.html"))
+        .expect("could not read archive page");
+    insta::assert_snapshot!("archive_page", archive_page);
+}
+
+#[test]
+fn archive_is_not_exported_without_dailies() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    quicknotes::make_note(&config, &editor, "my cool note".to_string(), &test_time())
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    let out_dir = tempdir().expect("could not create output directory");
+    let notes = quicknotes::indexed_notes(&config).expect("could not read indexed notes");
+    quicknotes::export_html(&config, notes, out_dir.path()).expect("could not export notes");
+
+    assert!(out_dir.path().join("notes/my-cool-note.html").exists());
+    assert!(out_dir.path().join("index.html").exists());
+    assert!(!out_dir.path().join("archive.html").exists());
+}
+
+#[test]
+fn bodies_of_notes_marked_encrypted_are_not_exported() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    // e.g. marked as encrypted by hand, but not yet opened with a passphrase
+    let path = roots.note_root.path().join("notes/combination.txt");
+    fs::create_dir_all(path.parent().unwrap()).expect("could not create directory");
+    fs::write(
+        &path,
+        "---\ntitle = \"combination\"\ncreated_at = 2015-10-21T07:28:00-07:00\nencrypted = true\n---\n\n12-34-56\n",
+    )
+    .expect("could not write note");
+    quicknotes::index_notes(&config).expect("could not index notes");
+
+    let out_dir = tempdir().expect("could not create output directory");
+    let notes = quicknotes::indexed_notes(&config).expect("could not read indexed notes");
+    quicknotes::export_html(&config, notes, out_dir.path()).expect("could not export notes");
+
+    let page = fs::read_to_string(out_dir.path().join("notes/combination.html"))
+        .expect("could not read note page");
+    assert!(page.contains("combination"));
+    assert!(!page.contains("12-34-56"));
+}
+
+#[test]
+fn bundles_can_be_imported_into_another_root() {
+    let source_roots = testutil::setup_filesystem();
+    let source_config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: source_roots.note_root.path().to_owned(),
+        temp_root_override: Some(source_roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig {
+            filename_format: "%Y/%m/%d".to_string(),
+            ..DailyConfig::default()
+        },
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    quicknotes::make_note(
+        &source_config,
+        &editor,
+        "my cool note".to_string(),
+        &test_time(),
+    )
+    .expect("could not write note")
+    .expect("file has contents, so path should have been returned");
+
+    editor.note_contents("today was a cool day\n".to_string());
+    let datetime = test_time();
+    quicknotes::make_or_open_daily(&source_config, &editor, datetime.date_naive(), &datetime)
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    let bundle_dir = tempdir().expect("could not create bundle directory");
+    let bundle_path = bundle_dir.path().join("notes.tar.gz");
+    let bundled =
+        quicknotes::export_bundle(&source_config, &bundle_path).expect("could not export bundle");
+    assert_eq!(bundled, 2);
+
+    let destination_roots = testutil::setup_filesystem();
+    let destination_config = NoteConfig {
+        root_dir: destination_roots.note_root.path().to_owned(),
+        temp_root_override: Some(destination_roots.temp_root.path().to_owned()),
+        ..source_config
+    };
+
+    let report = quicknotes::import_bundle(&destination_config, &bundle_path)
+        .expect("could not import bundle");
+
+    let destination_root = destination_roots.note_root.path();
+    assert_eq!(
+        report.imported.into_iter().sorted().collect::<Vec<_>>(),
+        vec![
+            destination_root.join("daily/2015/10/21.txt"),
+            destination_root.join("notes/my-cool-note.txt"),
+        ]
+    );
+    assert!(report.unchanged.is_empty());
+    assert!(report.conflicts.is_empty());
+
+    let dates =
+        quicknotes::indexed_daily_dates(&destination_config).expect("could not load daily dates");
+    assert_eq!(
+        dates.into_iter().collect::<Vec<_>>(),
+        vec![datetime.date_naive()]
+    );
+
+    let notes = quicknotes::indexed_notes_with_kind(&destination_config, NoteKind::Note)
+        .expect("could not read indexed notes");
+    assert_eq!(
+        notes.into_keys().collect::<Vec<_>>(),
+        vec![destination_root.join("notes/my-cool-note.txt")]
+    );
+}
+
+#[test]
+fn importing_a_bundle_does_not_clobber_existing_notes() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    let cool_note_path =
+        quicknotes::make_note(&config, &editor, "my cool note".to_string(), &test_time())
+            .expect("could not write note")
+            .expect("file has contents, so path should have been returned");
+    let awesome_note_path = quicknotes::make_note(
+        &config,
+        &editor,
+        "my awesome note".to_string(),
+        &test_time(),
+    )
+    .expect("could not write note")
+    .expect("file has contents, so path should have been returned");
+
+    let bundle_dir = tempdir().expect("could not create bundle directory");
+    let bundle_path = bundle_dir.path().join("notes.tar.gz");
+    quicknotes::export_bundle(&config, &bundle_path).expect("could not export bundle");
+
+    fs::write(&cool_note_path, "changed since the export\n").expect("could not change note");
+
+    let report = quicknotes::import_bundle(&config, &bundle_path).expect("could not import bundle");
+
+    assert!(report.imported.is_empty());
+    assert_eq!(report.unchanged, vec![awesome_note_path]);
+    assert_eq!(
+        report.conflicts,
+        vec![ImportConflict {
+            existing_path: cool_note_path.clone(),
+            imported_path: roots.note_root.path().join("notes/my-cool-note-1.txt"),
+        }]
+    );
+    assert_eq!(
+        fs::read_to_string(&cool_note_path).expect("could not read note"),
+        "changed since the export\n"
+    );
+}
+
+#[test_case("drafts/sneaky.txt", "note"; "outside of any kind directory")]
+#[test_case("daily/2015-10-21.txt", "note"; "in the directory for another kind")]
+#[test_case("sneaky.txt", "note"; "in the root")]
+fn bundles_cannot_place_notes_outside_their_kind_directory(path: &str, kind: &str) {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let contents = b"---\ntitle = \"sneaky\"\ncreated_at = 2015-10-21T07:28:00-07:00\n---\n";
+    let manifest = format!(
+        "version = 1\n[[notes]]\npath = \"{path}\"\nsha256 = \"{:x}\"\nkind = \"{kind}\"\n",
+        Sha256::digest(contents)
+    );
+    let bundle_dir = tempdir().expect("could not create bundle directory");
+    let bundle_path = bundle_dir.path().join("notes.tar.gz");
+    let bundle_file = fs::File::create(&bundle_path).expect("could not create bundle");
+    let mut builder = tar::Builder::new(GzEncoder::new(bundle_file, Compression::default()));
+    for (name, data) in [("manifest.toml", manifest.as_bytes()), (path, contents)] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, name, data)
+            .expect("could not add to bundle");
+    }
+    builder
+        .into_inner()
+        .and_then(GzEncoder::finish)
+        .expect("could not finish bundle");
+
+    let result = quicknotes::import_bundle(&config, &bundle_path);
+
+    assert!(result.is_err(), "expected import to be rejected");
+    assert!(!roots.note_root.path().join(path).exists());
+}
+
+#[test]
+fn obsidian_vaults_can_be_imported() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let vault = tempdir().expect("could not create vault directory");
+    fs::create_dir_all(vault.path().join(".obsidian")).expect("could not create directory");
+    fs::create_dir_all(vault.path().join("projects")).expect("could not create directory");
+    fs::write(vault.path().join(".obsidian/notes.md"), "# Not a note\n")
+        .expect("could not write file");
+    fs::write(vault.path().join("image.png"), "not a note").expect("could not write file");
+    fs::write(
+        vault.path().join("Flux Capacitor.md"),
+        "---\ncreated: 2015-10-21T07:28:00-07:00\ntags: [time-travel, inventions]\naliases: [flux]\n---\n\nIt's what makes [[Time Circuits|time travel]] possible.\n",
+    )
+    .expect("could not write file");
+    fs::write(
+        vault.path().join("projects/time circuits.md"),
+        "# Time Circuits\n\nSet the destination time.\n",
+    )
+    .expect("could not write file");
+    fs::write(vault.path().join("2015-10-21.md"), "today was a cool day\n")
+        .expect("could not write file");
+
+    let report = quicknotes::import_directory(&config, ImportSource::Obsidian, vault.path())
+        .expect("could not import notes");
+
+    let root = roots.note_root.path();
+    assert_eq!(
+        report.imported.into_iter().sorted().collect::<Vec<_>>(),
+        vec![
+            root.join("daily/2015-10-21.txt"),
+            root.join("notes/flux-capacitor.txt"),
+            root.join("notes/time-circuits.txt"),
+        ]
+    );
+    assert!(report.conflicts.is_empty());
+
+    let note_contents =
+        fs::read_to_string(root.join("notes/flux-capacitor.txt")).expect("could not read note");
+    insta::assert_snapshot!(note_contents);
+
+    let notes = quicknotes::indexed_notes_with_kind(&config, NoteKind::Note)
+        .expect("could not read indexed notes");
+    assert_eq!(
+        notes
+            .into_values()
+            .map(|note| note.preamble.title)
+            .sorted()
+            .collect::<Vec<_>>(),
+        vec!["Flux Capacitor", "Time Circuits"]
+    );
+
+    let dates = quicknotes::indexed_daily_dates(&config).expect("could not load daily dates");
+    assert_eq!(
+        dates.into_iter().collect::<Vec<_>>(),
+        vec![test_time().date_naive()]
+    );
+}
+
+#[test]
+fn importing_a_directory_again_skips_notes_that_were_already_imported() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("already here\n".to_string());
+    quicknotes::make_or_open_daily(&config, &editor, test_time().date_naive(), &test_time())
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    let vault = tempdir().expect("could not create vault directory");
+    fs::write(
+        vault.path().join("Flux Capacitor.md"),
+        "---\ncreated: 2015-10-21T07:28:00-07:00\n---\n\nIt's what makes time travel possible.\n",
+    )
+    .expect("could not write file");
+    fs::write(vault.path().join("2015-10-21.md"), "today was a cool day\n")
+        .expect("could not write file");
+
+    let first_report = quicknotes::import_directory(&config, ImportSource::Obsidian, vault.path())
+        .expect("could not import notes");
+    let second_report = quicknotes::import_directory(&config, ImportSource::Obsidian, vault.path())
+        .expect("could not import notes");
+
+    assert!(second_report.imported.is_empty());
+    assert!(second_report.conflicts.is_empty());
+    assert_eq!(
+        second_report
+            .unchanged
+            .into_iter()
+            .sorted()
+            .collect::<Vec<_>>(),
+        first_report
+            .imported
+            .into_iter()
+            .chain(
+                first_report
+                    .conflicts
+                    .into_iter()
+                    .map(|conflict| conflict.imported_path)
+            )
+            .sorted()
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        fs::read_dir(roots.note_root.path().join("notes"))
+            .expect("could not read notes directory")
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn imported_dailies_that_already_exist_become_notes() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("today was a cool day\n".to_string());
+    let datetime = test_time();
+    let daily_path =
+        quicknotes::make_or_open_daily(&config, &editor, datetime.date_naive(), &datetime)
+            .expect("could not write note")
+            .expect("file has contents, so path should have been returned");
+
+    let folder = tempdir().expect("could not create import directory");
+    fs::write(folder.path().join("2015-10-21.md"), "an imported day\n")
+        .expect("could not write file");
+
+    let report = quicknotes::import_directory(&config, ImportSource::Plain, folder.path())
+        .expect("could not import notes");
+
+    assert!(report.imported.is_empty());
+    assert_eq!(
+        report.conflicts,
+        vec![ImportConflict {
+            existing_path: daily_path,
+            imported_path: roots.note_root.path().join("notes/20151021.txt"),
+        }]
+    );
+}
@@ -1,7 +1,11 @@
-use chrono::{DateTime, FixedOffset, TimeZone};
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone};
 use itertools::Itertools;
-use quicknotes::{NoteConfig, NoteKind};
-use testutil::{AppendEditor, OverwriteEditor};
+use quicknotes::{
+    DailyConfig, DateDialect, Direction, HealthIssue, InboxConfig, NoteConfig, NoteKind,
+    OrphanedNote,
+};
+use test_case::test_case;
+use testutil::{AbortingEditor, AppendEditor, OverwriteEditor};
 
 mod testutil;
 
@@ -60,6 +64,9 @@ fn indexes_existing_files_on_disk() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     quicknotes::index_notes(&config).expect("could not index notes");
@@ -125,6 +132,9 @@ fn deleted_files_are_removed_from_the_index() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     quicknotes::index_notes(&config).expect("could not index notes");
@@ -150,6 +160,9 @@ fn notes_are_added_to_the_index_when_they_are_created() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     let mut editor = AppendEditor::new();
@@ -217,6 +230,9 @@ fn opening_a_note_reindexes_it() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     quicknotes::index_notes(&config).expect("could not index notes");
@@ -301,6 +317,9 @@ fn editing_a_note_to_have_an_invalid_preamble_removes_it_from_the_index() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     quicknotes::index_notes(&config).expect("could not index notes");
@@ -340,6 +359,9 @@ fn daily_notes_are_marked_with_daily_kind() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     let mut append_editor = AppendEditor::new();
@@ -370,6 +392,9 @@ fn regular_notes_are_marked_with_notes_kind() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     let mut append_editor = AppendEditor::new();
@@ -461,6 +486,9 @@ fn can_lookup_only_one_kind_of_note() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     quicknotes::index_notes(&config).expect("could not index notes");
@@ -476,3 +504,535 @@ fn can_lookup_only_one_kind_of_note() {
         vec![(daily_note_path, "2015-10-21".to_string())]
     )
 }
+
+#[test]
+fn aborting_the_editor_does_not_reindex_the_note() {
+    let roots = testutil::setup_filesystem();
+    let awesome_note_path = roots
+        .note_root
+        .path()
+        .join("notes")
+        .join("my-awesome-note.txt");
+
+    std::fs::write(
+        &awesome_note_path,
+        textwrap::dedent(
+            r#"
+            ---
+            title = "my awesome note"
+            created_at = 2015-10-22T07:28:00-07:00
+            ---
+            "#
+            .trim_start_matches("\n"),
+        ),
+    )
+    .expect("could not write note");
+
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    quicknotes::index_notes(&config).expect("could not index notes");
+    let mut overwrite_editor = OverwriteEditor::new();
+    overwrite_editor.note_contents(textwrap::dedent(
+        r#"
+            ---
+            title = "my super awesome note"
+            created_at = 2015-10-22T07:28:00-07:00
+            ---
+            "#
+        .trim_start_matches("\n"),
+    ));
+
+    let res = quicknotes::open_note(
+        &config,
+        AbortingEditor::new(&overwrite_editor),
+        NoteKind::Note,
+        &awesome_note_path,
+    );
+
+    assert!(res.is_err(), "aborted edit should have produced an error");
+
+    let notes = quicknotes::indexed_notes(&config).expect("could not read indexed notes");
+
+    assert_eq!(
+        notes
+            .into_iter()
+            .map(|(path, note)| (path, note.preamble.title))
+            .collect::<Vec<_>>(),
+        vec![(awesome_note_path, "my awesome note".to_string())]
+    )
+}
+
+#[test]
+fn opening_a_note_records_its_usage() {
+    let roots = testutil::setup_filesystem();
+    let cool_note_path = roots
+        .note_root
+        .path()
+        .join("notes")
+        .join("my-cool-note.txt");
+
+    std::fs::write(
+        &cool_note_path,
+        textwrap::dedent(
+            r#"
+            ---
+            title = "my cool note"
+            created_at = 2015-10-21T07:28:00-07:00
+            ---
+            "#
+            .trim_start_matches("\n"),
+        ),
+    )
+    .expect("could not write note");
+
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    quicknotes::index_notes(&config).expect("could not index notes");
+
+    let editor = AppendEditor::new();
+    quicknotes::open_note(&config, &editor, NoteKind::Note, &cool_note_path)
+        .expect("could not open note for editing");
+    quicknotes::open_note(&config, &editor, NoteKind::Note, &cool_note_path)
+        .expect("could not open note for editing");
+
+    // Reindexing should not lose usage
+    quicknotes::index_notes(&config).expect("could not re-index notes");
+
+    let usage = quicknotes::note_usage(&config).expect("could not read note usage");
+
+    assert_eq!(
+        usage
+            .into_iter()
+            .map(|(path, usage)| (path, usage.open_count))
+            .collect::<Vec<_>>(),
+        vec![(cool_note_path, 2)]
+    )
+}
+
+#[test]
+fn editing_a_note_stamps_and_indexes_its_modification_time() {
+    let roots = testutil::setup_filesystem();
+    let cool_note_path = roots
+        .note_root
+        .path()
+        .join("notes")
+        .join("my-cool-note.txt");
+
+    std::fs::write(
+        &cool_note_path,
+        textwrap::dedent(
+            r#"
+            ---
+            title = "my cool note"
+            created_at = 2015-10-21T07:28:00-07:00
+            ---
+            "#
+            .trim_start_matches("\n"),
+        ),
+    )
+    .expect("could not write note");
+
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    quicknotes::index_notes(&config).expect("could not index notes");
+
+    // Opening the note without changing it should leave it untouched
+    quicknotes::open_note(
+        &config,
+        AppendEditor::new(),
+        NoteKind::Note,
+        &cool_note_path,
+    )
+    .expect("could not open note for editing");
+
+    let unchanged = quicknotes::read_note(&cool_note_path).expect("could not read note");
+    assert_eq!(unchanged.preamble.updated_at, None);
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello world".to_string());
+    quicknotes::open_note(&config, &editor, NoteKind::Note, &cool_note_path)
+        .expect("could not open note for editing");
+
+    let edited = quicknotes::read_note(&cool_note_path).expect("could not read note");
+    assert!(edited.preamble.updated_at.is_some());
+    assert!(edited.body.ends_with("hello world"));
+
+    let indexed_notes = quicknotes::indexed_notes(&config).expect("could not read index");
+    assert_eq!(
+        indexed_notes[&cool_note_path].preamble.updated_at,
+        edited.preamble.updated_at
+    );
+}
+
+#[test]
+fn tasks_can_be_toggled_in_place() {
+    let roots = testutil::setup_filesystem();
+    let todo_path = roots.note_root.path().join("notes").join("todo.txt");
+
+    std::fs::write(
+        &todo_path,
+        textwrap::dedent(
+            r#"
+            ---
+            title = "todo"
+            created_at = 2015-10-21T07:28:00-07:00
+            ---
+
+            - [ ] buy milk
+            - [x] walk the dog
+            "#
+            .trim_start_matches("\n"),
+        ),
+    )
+    .expect("could not write note");
+
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    quicknotes::index_notes(&config).expect("could not index notes");
+
+    let task = quicknotes::toggle_task(&config, &todo_path, 6).expect("could not toggle task");
+    assert!(task.done);
+    // updated_at was added to the preamble, which moves the task down a line
+    assert_eq!(task.line_number, 7);
+
+    let contents = quicknotes::read_note(&todo_path).expect("could not read note");
+    assert_eq!(contents.body, "\n- [x] buy milk\n- [x] walk the dog\n");
+    assert!(contents.preamble.updated_at.is_some());
+
+    let tasks = quicknotes::indexed_tasks(&config)
+        .expect("could not read tasks")
+        .into_iter()
+        .map(|indexed_task| (indexed_task.task.line_number, indexed_task.task.done))
+        .collect::<Vec<_>>();
+    assert_eq!(tasks, vec![(7, true), (8, true)]);
+
+    let not_a_task = quicknotes::toggle_task(&config, &todo_path, 2);
+    assert!(not_a_task.is_err());
+}
+
+#[test]
+fn tasks_are_not_toggled_in_notes_outside_of_the_index() {
+    let roots = testutil::setup_filesystem();
+    let todo_path = roots.note_root.path().join("notes").join("todo.txt");
+    let contents = textwrap::dedent(
+        r#"
+        ---
+        title = "todo"
+        created_at = 2015-10-21T07:28:00-07:00
+        ---
+
+        - [ ] buy milk
+        "#
+        .trim_start_matches("\n"),
+    );
+    std::fs::write(&todo_path, &contents).expect("could not write note");
+
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let result = quicknotes::toggle_task(&config, &todo_path, 6);
+    assert!(result.is_err(), "expected error, got {result:?}");
+
+    let toggled_contents = std::fs::read_to_string(&todo_path).expect("could not read note");
+    assert_eq!(toggled_contents, contents);
+}
+
+#[test_case(NaiveDate::from_ymd_opt(2015, 10, 21).unwrap(), Direction::Previous, Some("2015-10-20"); "previous skips missing days")]
+#[test_case(NaiveDate::from_ymd_opt(2015, 10, 20).unwrap(), Direction::Previous, Some("2015-10-18"); "previous excludes the given date")]
+#[test_case(NaiveDate::from_ymd_opt(2015, 10, 18).unwrap(), Direction::Next, Some("2015-10-20"); "next excludes the given date")]
+#[test_case(NaiveDate::from_ymd_opt(2015, 10, 20).unwrap(), Direction::Next, None; "nothing after the last note")]
+fn adjacent_dailies_are_found_in_the_index(
+    date: NaiveDate,
+    direction: Direction,
+    expected_stem: Option<&str>,
+) {
+    let roots = testutil::setup_filesystem();
+    for day in ["2015-10-18", "2015-10-20"] {
+        std::fs::write(
+            roots
+                .note_root
+                .path()
+                .join("daily")
+                .join(format!("{day}.txt")),
+            format!("---\ntitle = \"{day}\"\ncreated_at = 2015-10-21T07:28:00-07:00\n---\n"),
+        )
+        .expect("could not write note");
+    }
+
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    quicknotes::index_notes(&config).expect("could not index notes");
+
+    let adjacent = quicknotes::adjacent_daily(&config, date, direction)
+        .expect("could not look up adjacent daily");
+
+    assert_eq!(
+        adjacent,
+        expected_stem.map(|stem| {
+            (
+                NaiveDate::parse_from_str(stem, "%Y-%m-%d").unwrap(),
+                roots
+                    .note_root
+                    .path()
+                    .join("daily")
+                    .join(format!("{stem}.txt")),
+            )
+        })
+    );
+}
+
+#[test]
+fn orphaned_notes_can_be_adopted() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    quicknotes::make_note(&config, &editor, "indexed note".to_string(), &test_time())
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    let root = roots.note_root.path();
+    let hand_made_path = root.join("notes/hand-made-note.txt");
+    let daily_path = root.join("daily/2015-10-21.txt");
+    std::fs::write(&hand_made_path, "written by hand\n").expect("could not write note");
+    std::fs::write(root.join("notes/image.png"), "not a note").expect("could not write file");
+    std::fs::create_dir_all(root.join("daily")).expect("could not create directory");
+    std::fs::write(&daily_path, "today was a cool day\n").expect("could not write note");
+
+    let orphans = quicknotes::orphaned_notes(&config);
+    assert_eq!(
+        orphans
+            .iter()
+            .map(|orphan| (orphan.path.clone(), orphan.kind))
+            .collect::<Vec<_>>(),
+        vec![
+            (daily_path.clone(), NoteKind::Daily),
+            (hand_made_path.clone(), NoteKind::Note),
+        ]
+    );
+
+    for orphan in &orphans {
+        quicknotes::adopt_note(&config, orphan).expect("could not adopt note");
+    }
+
+    let notes = quicknotes::indexed_notes(&config).expect("could not read indexed notes");
+    assert_eq!(notes[&hand_made_path].preamble.title, "hand made note");
+    assert_eq!(notes[&daily_path].preamble.title, "2015-10-21");
+    assert_eq!(
+        notes[&daily_path].preamble.date,
+        NaiveDate::from_ymd_opt(2015, 10, 21)
+    );
+
+    let hand_made = quicknotes::read_note(&hand_made_path).expect("could not read note");
+    assert_eq!(hand_made.body.trim(), "written by hand");
+
+    assert!(quicknotes::orphaned_notes(&config).is_empty());
+    let result = quicknotes::adopt_note(&config, &orphans[0]);
+    assert!(result.is_err(), "expected error, got {result:?}");
+}
+
+#[test]
+fn notes_with_a_malformed_preamble_are_reported_but_not_adopted() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let root = roots.note_root.path();
+    let malformed_path = root.join("notes/malformed-note.txt");
+    let malformed_contents = "---\ntitle = \"malformed\"\ncreated_at = yesterday\n---\n\nhi\n";
+    std::fs::create_dir_all(root.join("notes")).expect("could not create directory");
+    std::fs::write(&malformed_path, malformed_contents).expect("could not write note");
+
+    assert!(quicknotes::orphaned_notes(&config).is_empty());
+
+    let issues = quicknotes::check_health(&config).expect("could not check health");
+    assert_eq!(issues.len(), 1);
+    assert!(
+        matches!(&issues[0], HealthIssue::MalformedPreamble { path, .. } if *path == malformed_path),
+        "expected malformed preamble, got {issues:?}"
+    );
+    assert!(!issues[0].is_fixable());
+
+    let orphan = OrphanedNote {
+        path: malformed_path.clone(),
+        kind: NoteKind::Note,
+    };
+    let result = quicknotes::adopt_note(&config, &orphan);
+    assert!(result.is_err(), "expected error, got {result:?}");
+    assert_eq!(
+        std::fs::read_to_string(&malformed_path).expect("could not read note"),
+        malformed_contents
+    );
+}
+
+#[test]
+fn orphaned_dailies_are_dated_with_the_configured_filename_format() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig {
+            filename_format: "%Y/%m/%d".to_string(),
+            ..DailyConfig::default()
+        },
+        inbox: InboxConfig::default(),
+    };
+
+    let daily_path = roots.note_root.path().join("daily/2015/10/21.txt");
+    std::fs::create_dir_all(daily_path.parent().unwrap()).expect("could not create directory");
+    std::fs::write(&daily_path, "today was a cool day\n").expect("could not write note");
+
+    let orphans = quicknotes::orphaned_notes(&config);
+    assert_eq!(orphans.len(), 1);
+    let preamble = quicknotes::adopt_note(&config, &orphans[0]).expect("could not adopt note");
+
+    assert_eq!(preamble.date, NaiveDate::from_ymd_opt(2015, 10, 21));
+    assert_eq!(preamble.title, "2015-10-21");
+}
+
+#[test]
+fn health_issues_are_found_and_safe_ones_are_fixed() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    let make_note = |title: &str| {
+        quicknotes::make_note(&config, &editor, title.to_string(), &test_time())
+            .expect("could not write note")
+            .expect("file has contents, so path should have been returned")
+    };
+    let first_cool_path = make_note("cool note");
+    let second_cool_path = make_note("Cool Note");
+    let deleted_path = make_note("deleted note");
+
+    let root = roots.note_root.path();
+    let preamble = "---\ntitle = \"{title}\"\ncreated_at = 2015-10-21T07:28:00-07:00\n---\n";
+    let daily_path = root.join("daily/2015-10-22.txt");
+    let markdown_path = root.join("notes/markdown-note.md");
+    let image_path = root.join("notes/image.png");
+    let tempfile_path = roots.temp_root.path().join(".tmpAbC123.txt");
+    std::fs::create_dir_all(root.join("daily")).expect("could not create directory");
+    std::fs::write(&daily_path, preamble.replace("{title}", "2015-10-21"))
+        .expect("could not write note");
+    std::fs::write(&markdown_path, preamble.replace("{title}", "markdown note"))
+        .expect("could not write note");
+    std::fs::write(&image_path, "not a note").expect("could not write file");
+    std::fs::write(&tempfile_path, preamble.replace("{title}", "lost note"))
+        .expect("could not write note");
+    quicknotes::index_notes(&config).expect("could not index notes");
+    std::fs::remove_file(&deleted_path).expect("could not delete note");
+
+    let issues = quicknotes::check_health(&config).expect("could not check health");
+    assert_eq!(
+        issues,
+        vec![
+            HealthIssue::StaleIndexEntry {
+                path: deleted_path.clone()
+            },
+            HealthIssue::DuplicateTitle {
+                title: "Cool Note".to_string(),
+                paths: vec![second_cool_path, first_cool_path],
+            },
+            HealthIssue::DailyDateMismatch {
+                path: daily_path,
+                title_date: NaiveDate::from_ymd_opt(2015, 10, 21).unwrap(),
+                filename_date: NaiveDate::from_ymd_opt(2015, 10, 22).unwrap(),
+            },
+            HealthIssue::WrongExtension {
+                path: image_path.clone(),
+                kind: NoteKind::Note,
+                renamed_path: None,
+            },
+            HealthIssue::WrongExtension {
+                path: markdown_path.clone(),
+                kind: NoteKind::Note,
+                renamed_path: Some(root.join("notes/markdown-note.txt")),
+            },
+            HealthIssue::PreservedTempfile {
+                path: tempfile_path
+            },
+        ]
+    );
+
+    for issue in issues.iter().filter(|issue| issue.is_fixable()) {
+        quicknotes::fix_health_issue(&config, issue).expect("could not fix issue");
+    }
+
+    let notes = quicknotes::indexed_notes(&config).expect("could not read indexed notes");
+    assert!(!notes.contains_key(&deleted_path));
+    assert!(!notes.contains_key(&markdown_path));
+    assert_eq!(
+        notes[&root.join("notes/markdown-note.txt")].preamble.title,
+        "markdown note"
+    );
+
+    let remaining_issues = quicknotes::check_health(&config).expect("could not check health");
+    assert!(remaining_issues.iter().all(|issue| !issue.is_fixable()));
+    assert_eq!(remaining_issues.len(), 4);
+    assert!(quicknotes::fix_health_issue(&config, &remaining_issues[0]).is_err());
+}
@@ -1,8 +1,17 @@
-use std::fs::{self, OpenOptions};
+use std::{
+    fs::{self, OpenOptions},
+    path::Path,
+};
 
 use chrono::{DateTime, FixedOffset, TimeZone};
-use quicknotes::NoteConfig;
-use testutil::{AppendEditor, SwappingEditor};
+use itertools::Itertools;
+use quicknotes::{
+    DailyConfig, DateDialect, Direction, InboxConfig, NoteConfig, NoteKind, Period, Position,
+};
+use test_case::test_case;
+use testutil::{
+    AbortingEditor, AppendEditor, CrashingEditor, PositionRecordingEditor, SwappingEditor,
+};
 
 mod testutil;
 
@@ -14,6 +23,29 @@ fn test_time() -> DateTime<FixedOffset> {
         .unwrap()
 }
 
+/// Rewrite the journal entry of a draft as if the process writing it had exited, optionally
+/// changing where the draft is meant to be stored.
+#[cfg(unix)]
+fn abandon_draft(journal_path: &Path, destination: Option<&Path>) {
+    let mut exited = std::process::Command::new("true")
+        .spawn()
+        .expect("could not start process");
+    exited.wait().expect("could not wait for process");
+
+    let journal = fs::read_to_string(journal_path).expect("could not read journal");
+    let journal = journal
+        .lines()
+        .map(|line| match destination {
+            _ if line.starts_with("pid = ") => format!("pid = {}", exited.id()),
+            Some(destination) if line.starts_with("destination = ") => {
+                format!("destination = {:?}", destination.to_str().unwrap())
+            }
+            _ => line.to_string(),
+        })
+        .join("\n");
+    fs::write(journal_path, journal).expect("could not write journal");
+}
+
 #[test]
 fn writes_notes_to_notes_directory() {
     let roots = testutil::setup_filesystem();
@@ -21,6 +53,9 @@ fn writes_notes_to_notes_directory() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     let mut editor = AppendEditor::new();
@@ -46,6 +81,9 @@ fn writes_dailies_to_notes_directory() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     let mut editor = AppendEditor::new();
@@ -65,6 +103,44 @@ fn writes_dailies_to_notes_directory() {
     insta::assert_snapshot!(note_contents);
 }
 
+#[test_case(Period::Weekly, "weekly/2015-W43.txt"; "weekly")]
+#[test_case(Period::Monthly, "monthly/2015-10.txt"; "monthly")]
+fn writes_periodic_notes_to_their_directories(period: Period, expected_relative_path: &str) {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("this was a cool week\n".to_string());
+    let datetime = test_time();
+
+    let stored_path = quicknotes::make_or_open_periodic(
+        &config,
+        editor,
+        period,
+        datetime.date_naive(),
+        &datetime,
+    )
+    .expect("could not write note")
+    .expect("file has contents, so path should have been returned");
+
+    let expected_note_path = roots.note_root.path().join(expected_relative_path);
+    assert_eq!(stored_path, expected_note_path);
+
+    let indexed_notes = quicknotes::indexed_notes_with_kind(&config, period.note_kind())
+        .expect("could not read index");
+    assert_eq!(
+        indexed_notes[&expected_note_path].preamble.title,
+        expected_note_path.file_stem().unwrap().to_str().unwrap()
+    );
+}
+
 #[test]
 fn writes_notes_to_notes_directory_even_if_inode_changes() {
     let roots = testutil::setup_filesystem();
@@ -72,6 +148,9 @@ fn writes_notes_to_notes_directory_even_if_inode_changes() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     let mut append_editor = AppendEditor::new();
@@ -97,6 +176,9 @@ fn editing_an_existing_daily_alters_the_same_file() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     let datetime = test_time();
@@ -124,6 +206,9 @@ fn opening_two_notes_with_the_same_name_prevents_clobbering() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     let mut editor = AppendEditor::new();
@@ -165,6 +250,9 @@ fn opening_two_notes_with_the_same_name_prevents_clobbering_even_if_collision_ex
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     let mut editor = AppendEditor::new();
@@ -231,6 +319,69 @@ fn opening_two_notes_with_the_same_name_prevents_clobbering_even_if_collision_ex
     insta::assert_snapshot!(upd_note_contents);
 }
 
+#[test]
+fn notes_can_be_kept_in_a_sqlite_vault() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+    let backend = quicknotes::SqliteBackend::open_in_memory().expect("could not open vault");
+
+    let mut paths = Vec::new();
+    for contents in ["hello, world!\n", "hello again\n"] {
+        let mut editor = AppendEditor::new();
+        editor.note_contents(contents.to_string());
+        let path = quicknotes::make_note_in(
+            &backend,
+            &config,
+            editor,
+            "my cool note".to_string(),
+            &test_time(),
+        )
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+        paths.push(path);
+    }
+
+    assert_eq!(
+        paths,
+        vec![
+            roots.note_root.path().join("notes/my-cool-note.txt"),
+            roots.note_root.path().join("notes/my-cool-note-1.txt"),
+        ]
+    );
+    assert!(paths.iter().all(|path| !path.exists()));
+
+    let contents = quicknotes::read_note_in(&backend, &paths[1]).expect("could not read note");
+    assert_eq!(contents.preamble.title, "my cool note");
+    assert_eq!(contents.body, "\nhello again\n");
+
+    quicknotes::index_notes_in(&backend, &config).expect("could not index notes");
+    let indexed_paths = quicknotes::indexed_notes(&config)
+        .expect("could not read index")
+        .into_keys()
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        indexed_paths,
+        paths.iter().cloned().sorted().collect::<Vec<_>>()
+    );
+
+    quicknotes::delete_note_in(&backend, &config, &paths[0]).expect("could not delete note");
+    assert!(quicknotes::read_note_in(&backend, &paths[0]).is_err());
+    let indexed_paths = quicknotes::indexed_notes(&config)
+        .expect("could not read index")
+        .into_keys()
+        .collect::<Vec<_>>();
+    assert_eq!(indexed_paths, vec![paths[1].clone()]);
+}
+
 #[test]
 fn writing_nothing_to_file_results_in_no_file_written() {
     let roots = testutil::setup_filesystem();
@@ -238,6 +389,9 @@ fn writing_nothing_to_file_results_in_no_file_written() {
         file_extension: "txt".to_string(),
         root_dir: roots.note_root.path().to_owned(),
         temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
     };
 
     let stored_path = quicknotes::make_note(
@@ -253,3 +407,990 @@ fn writing_nothing_to_file_results_in_no_file_written() {
     let contents = fs::read_dir(roots.note_root).expect("could not read notes dir");
     assert!(contents.into_iter().next().is_none());
 }
+
+#[test]
+fn aborting_the_editor_results_in_no_file_written() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut append_editor = AppendEditor::new();
+    append_editor.note_contents("hello, world!\n".to_string());
+    let editor = AbortingEditor::new(append_editor);
+
+    let res = quicknotes::make_note(&config, editor, "my cool note".to_string(), &test_time());
+
+    assert!(res.is_err(), "aborted edit should have produced an error");
+
+    let contents =
+        fs::read_dir(roots.note_root.path().join("notes")).expect("could not read notes dir");
+    assert!(contents.into_iter().next().is_none());
+}
+
+#[test]
+fn dailies_are_opened_at_the_end_of_the_note() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let datetime = test_time();
+    let mut editor = AppendEditor::new();
+    editor.note_contents("today was a cool day".to_string());
+    quicknotes::make_or_open_daily(&config, &editor, datetime.date_naive(), &datetime)
+        .expect("could not write note");
+
+    let recording_editor = PositionRecordingEditor::new();
+    quicknotes::make_or_open_daily(&config, &recording_editor, datetime.date_naive(), &datetime)
+        .expect("could not open note");
+
+    // five preamble lines, a blank line, and then the text we wrote
+    assert_eq!(recording_editor.last_position(), Some(Position::new(7, 21)));
+}
+
+#[test]
+fn unfinished_tasks_are_carried_over_into_new_dailies() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig {
+            carry_over_tasks: true,
+            ..DailyConfig::default()
+        },
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("- [ ] buy milk\n- [x] walk the dog\n  - [ ] call mom\n".to_string());
+    let yesterday = test_time() - chrono::TimeDelta::days(1);
+    quicknotes::make_or_open_daily(&config, &editor, yesterday.date_naive(), &yesterday)
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    editor.note_contents("today was a cool day\n".to_string());
+    let datetime = test_time();
+    let stored_path =
+        quicknotes::make_or_open_daily(&config, &editor, datetime.date_naive(), &datetime)
+            .expect("could not write note")
+            .expect("file has contents, so path should have been returned");
+
+    let note_contents = fs::read_to_string(stored_path).expect("failed to open note");
+
+    insta::assert_snapshot!(note_contents);
+}
+
+#[test]
+fn unedited_carried_over_tasks_result_in_no_file_written() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig {
+            carry_over_tasks: true,
+            ..DailyConfig::default()
+        },
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("- [ ] buy milk\n".to_string());
+    let yesterday = test_time() - chrono::TimeDelta::days(1);
+    quicknotes::make_or_open_daily(&config, &editor, yesterday.date_naive(), &yesterday)
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    let datetime = test_time();
+    let stored_path = quicknotes::make_or_open_daily(
+        &config,
+        AppendEditor::new(),
+        datetime.date_naive(),
+        &datetime,
+    )
+    .expect("could not write note");
+
+    assert_eq!(stored_path, None);
+    assert!(!roots.note_root.path().join("daily/2015-10-21.txt").exists());
+}
+
+#[test]
+fn tasks_are_not_carried_over_unless_enabled() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("- [ ] buy milk\n".to_string());
+    let yesterday = test_time() - chrono::TimeDelta::days(1);
+    quicknotes::make_or_open_daily(&config, &editor, yesterday.date_naive(), &yesterday)
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    editor.note_contents("today was a cool day\n".to_string());
+    let datetime = test_time();
+    let stored_path =
+        quicknotes::make_or_open_daily(&config, &editor, datetime.date_naive(), &datetime)
+            .expect("could not write note")
+            .expect("file has contents, so path should have been returned");
+
+    let note_contents = fs::read_to_string(stored_path).expect("failed to open note");
+
+    assert!(!note_contents.contains("buy milk"));
+}
+
+#[test]
+fn dailies_can_use_custom_filename_and_title_formats() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig {
+            carry_over_tasks: true,
+            filename_format: "%Y/%m/%d".to_string(),
+            title_format: "%A, %b %-d %Y".to_string(),
+            ..DailyConfig::default()
+        },
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("- [ ] buy milk\n".to_string());
+    let last_month = test_time() - chrono::TimeDelta::days(30);
+    quicknotes::make_or_open_daily(&config, &editor, last_month.date_naive(), &last_month)
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    editor.note_contents("today was a cool day\n".to_string());
+    let datetime = test_time();
+    let stored_path =
+        quicknotes::make_or_open_daily(&config, &editor, datetime.date_naive(), &datetime)
+            .expect("could not write note")
+            .expect("file has contents, so path should have been returned");
+
+    assert_eq!(
+        stored_path,
+        roots.note_root.path().join("daily/2015/10/21.txt")
+    );
+
+    let note_contents = fs::read_to_string(stored_path).expect("failed to open note");
+    insta::assert_snapshot!(note_contents);
+
+    let dates = quicknotes::indexed_daily_dates(&config).expect("could not load daily dates");
+    assert_eq!(
+        dates.into_iter().collect::<Vec<_>>(),
+        vec![last_month.date_naive(), datetime.date_naive()]
+    );
+}
+
+#[test]
+fn dailies_are_found_where_they_were_made_after_the_filename_format_changes() {
+    let roots = testutil::setup_filesystem();
+    let mut config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("today was a cool day\n".to_string());
+    let datetime = test_time();
+    let stored_path =
+        quicknotes::make_or_open_daily(&config, &editor, datetime.date_naive(), &datetime)
+            .expect("could not write note")
+            .expect("file has contents, so path should have been returned");
+
+    config.daily.filename_format = "%Y/%m/%d".to_string();
+    let dailies = quicknotes::indexed_dailies(&config).expect("could not load dailies");
+
+    assert_eq!(dailies.get(&datetime.date_naive()), Some(&stored_path));
+    let adjacent = quicknotes::adjacent_daily(
+        &config,
+        datetime.date_naive() - chrono::TimeDelta::days(1),
+        Direction::Next,
+    )
+    .expect("could not find adjacent daily");
+    assert_eq!(adjacent, Some((datetime.date_naive(), stored_path)));
+}
+
+#[test_case("/%Y-%m-%d", "%Y-%m-%d"; "absolute filename")]
+#[test_case("../%Y-%m-%d", "%Y-%m-%d"; "filename outside of daily directory")]
+#[test_case("%Y-%m-%d", "%H:%M"; "title with time")]
+fn invalid_daily_formats_are_rejected(filename_format: &str, title_format: &str) {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig {
+            filename_format: filename_format.to_string(),
+            title_format: title_format.to_string(),
+            ..DailyConfig::default()
+        },
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("today was a cool day\n".to_string());
+    let datetime = test_time();
+    let result = quicknotes::make_or_open_daily(&config, &editor, datetime.date_naive(), &datetime);
+
+    assert!(result.is_err(), "expected error, got {result:?}");
+}
+
+#[test]
+fn capture_creates_the_inbox_and_appends_entries() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let stored_path =
+        quicknotes::capture(&config, "buy milk", &test_time()).expect("could not capture");
+    assert_eq!(stored_path, roots.note_root.path().join("inbox.txt"));
+
+    let later = test_time() + chrono::TimeDelta::hours(1);
+    quicknotes::capture(&config, "call mom\nabout dinner\n", &later).expect("could not capture");
+
+    let inbox_contents = fs::read_to_string(stored_path).expect("failed to open inbox");
+    insta::assert_snapshot!(inbox_contents);
+
+    let entries = quicknotes::inbox_entries(&config).expect("could not read inbox");
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| entry.text.as_str())
+            .collect::<Vec<_>>(),
+        vec!["buy milk", "call mom\nabout dinner"]
+    );
+}
+
+#[test_case("inbox", "   \n"; "blank text")]
+#[test_case("../inbox", "buy milk"; "inbox outside of root directory")]
+fn invalid_captures_are_rejected(inbox_filename: &str, text: &str) {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig {
+            filename: inbox_filename.to_string(),
+        },
+    };
+
+    let result = quicknotes::capture(&config, text, &test_time());
+
+    assert!(result.is_err(), "expected error, got {result:?}");
+}
+
+#[test]
+fn inbox_entries_can_be_moved_to_a_new_note() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    quicknotes::capture(&config, "buy milk", &test_time()).expect("could not capture");
+    quicknotes::capture(&config, "call mom", &test_time()).expect("could not capture");
+    let entries = quicknotes::inbox_entries(&config).expect("could not read inbox");
+
+    let stored_path = quicknotes::move_inbox_entry_to_note(&config, &entries[0], &test_time())
+        .expect("could not move entry");
+
+    assert_eq!(
+        stored_path,
+        roots.note_root.path().join("notes/buy-milk.txt")
+    );
+    let note = quicknotes::read_note(&stored_path).expect("could not read note");
+    assert_eq!(note.preamble.title, "buy milk");
+    assert_eq!(note.body.trim(), "buy milk");
+
+    let indexed_notes =
+        quicknotes::indexed_notes_with_kind(&config, NoteKind::Note).expect("could not read index");
+    assert!(indexed_notes.contains_key(&stored_path));
+
+    let remaining = quicknotes::inbox_entries(&config).expect("could not read inbox");
+    assert_eq!(remaining, entries[1..]);
+}
+
+#[test]
+fn inbox_entries_can_be_moved_to_an_existing_daily() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("today was a cool day\n".to_string());
+    let datetime = test_time();
+    quicknotes::make_or_open_daily(&config, &editor, datetime.date_naive(), &datetime)
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    quicknotes::capture(&config, "call mom\nabout dinner", &datetime).expect("could not capture");
+    let entries = quicknotes::inbox_entries(&config).expect("could not read inbox");
+
+    let later = datetime + chrono::TimeDelta::hours(1);
+    let stored_path =
+        quicknotes::move_inbox_entry_to_daily(&config, &entries[0], datetime.date_naive(), &later)
+            .expect("could not move entry");
+
+    assert_eq!(
+        stored_path,
+        roots.note_root.path().join("daily/2015-10-21.txt")
+    );
+    let note_contents = fs::read_to_string(stored_path).expect("failed to open note");
+    insta::assert_snapshot!(note_contents);
+
+    let remaining = quicknotes::inbox_entries(&config).expect("could not read inbox");
+    assert!(remaining.is_empty());
+}
+
+#[test]
+fn inbox_entries_can_be_moved_to_a_new_daily() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let datetime = test_time();
+    quicknotes::capture(&config, "buy milk", &datetime).expect("could not capture");
+    let entries = quicknotes::inbox_entries(&config).expect("could not read inbox");
+
+    let stored_path = quicknotes::move_inbox_entry_to_daily(
+        &config,
+        &entries[0],
+        datetime.date_naive(),
+        &datetime,
+    )
+    .expect("could not move entry");
+
+    let note = quicknotes::read_note(&stored_path).expect("could not read note");
+    assert_eq!(note.preamble.title, "2015-10-21");
+    assert_eq!(note.preamble.date, Some(datetime.date_naive()));
+    assert_eq!(note.body.trim(), "buy milk");
+
+    let dates = quicknotes::indexed_daily_dates(&config).expect("could not load daily dates");
+    assert!(dates.contains(&datetime.date_naive()));
+}
+
+#[test]
+fn inbox_entries_can_be_deleted() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    quicknotes::capture(&config, "buy milk", &test_time()).expect("could not capture");
+    quicknotes::capture(&config, "call mom", &test_time()).expect("could not capture");
+    let entries = quicknotes::inbox_entries(&config).expect("could not read inbox");
+
+    quicknotes::delete_inbox_entry(&config, &entries[1]).expect("could not delete entry");
+
+    let remaining = quicknotes::inbox_entries(&config).expect("could not read inbox");
+    assert_eq!(remaining, entries[..1]);
+
+    let result = quicknotes::delete_inbox_entry(&config, &entries[1]);
+    assert!(result.is_err(), "expected error, got {result:?}");
+}
+
+#[test]
+fn notes_that_cannot_be_stored_can_be_recovered() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    // Without a notes directory, the note has nowhere to go
+    fs::remove_dir(roots.note_root.path().join("notes")).expect("could not remove directory");
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    let result = quicknotes::make_note(&config, &editor, "my cool note".to_string(), &test_time());
+    assert!(result.is_err(), "expected error, got {result:?}");
+
+    let destination = roots.note_root.path().join("notes/my-cool-note.txt");
+    let recoverable = quicknotes::recoverable_notes(&config).expect("could not list notes");
+    assert_eq!(recoverable.len(), 1);
+    assert_eq!(
+        recoverable[0].path,
+        roots.note_root.path().join("recovery/my-cool-note.txt")
+    );
+    assert_eq!(recoverable[0].destination, destination);
+    assert_eq!(recoverable[0].kind, NoteKind::Note);
+
+    let stored_path =
+        quicknotes::recover_note(&config, &recoverable[0]).expect("could not recover note");
+
+    assert_eq!(stored_path, destination);
+    let note = quicknotes::read_note(&stored_path).expect("could not read note");
+    assert_eq!(note.preamble.title, "my cool note");
+    assert_eq!(note.body.trim(), "hello, world!");
+
+    let notes = quicknotes::indexed_notes(&config).expect("could not read indexed notes");
+    assert!(notes.contains_key(&stored_path));
+    assert!(quicknotes::recoverable_notes(&config)
+        .expect("could not list notes")
+        .is_empty());
+}
+
+#[test_case("../outside.txt"; "outside of the notes root")]
+#[test_case("notes/../../outside.txt"; "escaping a note directory")]
+#[test_case("drafts/sneaky.txt"; "in the notes root, but not a note directory")]
+fn notes_are_not_recovered_outside_of_the_note_directories(destination: &str) {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    fs::remove_dir(roots.note_root.path().join("notes")).expect("could not remove directory");
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    let result = quicknotes::make_note(&config, &editor, "my cool note".to_string(), &test_time());
+    assert!(result.is_err(), "expected error, got {result:?}");
+
+    // e.g. a sidecar that was edited, or synced from somewhere else
+    let destination = roots.note_root.path().join(destination);
+    let sidecar_path = roots
+        .note_root
+        .path()
+        .join("recovery/my-cool-note.txt.recovery.toml");
+    let sidecar = fs::read_to_string(&sidecar_path).expect("could not read sidecar");
+    let sidecar = sidecar
+        .lines()
+        .map(|line| {
+            if line.starts_with("destination = ") {
+                format!("destination = {:?}", destination.to_str().unwrap())
+            } else {
+                line.to_string()
+            }
+        })
+        .join("\n");
+    fs::write(&sidecar_path, sidecar).expect("could not write sidecar");
+
+    let recoverable = quicknotes::recoverable_notes(&config).expect("could not list notes");
+    assert_eq!(recoverable[0].destination, destination);
+    let result = quicknotes::recover_note(&config, &recoverable[0]);
+
+    assert!(result.is_err(), "expected error, got {result:?}");
+    assert!(!destination.exists());
+    assert_eq!(
+        quicknotes::recoverable_notes(&config)
+            .expect("could not list notes")
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn recovered_notes_do_not_clobber_notes_stored_since() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    fs::remove_dir(roots.note_root.path().join("notes")).expect("could not remove directory");
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    let result = quicknotes::make_note(&config, &editor, "my cool note".to_string(), &test_time());
+    assert!(result.is_err(), "expected error, got {result:?}");
+
+    fs::create_dir(roots.note_root.path().join("notes")).expect("could not create directory");
+    editor.note_contents("a different note\n".to_string());
+    let existing_path =
+        quicknotes::make_note(&config, &editor, "my cool note".to_string(), &test_time())
+            .expect("could not write note")
+            .expect("file has contents, so path should have been returned");
+
+    let recoverable = quicknotes::recoverable_notes(&config).expect("could not list notes");
+    let stored_path =
+        quicknotes::recover_note(&config, &recoverable[0]).expect("could not recover note");
+
+    assert_eq!(
+        stored_path,
+        roots.note_root.path().join("notes/my-cool-note-1.txt")
+    );
+    let existing = quicknotes::read_note(&existing_path).expect("could not read note");
+    assert_eq!(existing.body.trim(), "a different note");
+    let recovered = quicknotes::read_note(&stored_path).expect("could not read note");
+    assert_eq!(recovered.body.trim(), "hello, world!");
+}
+
+#[test]
+fn drafts_are_removed_once_notes_are_stored() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    quicknotes::make_note(&config, &editor, "my cool note".to_string(), &test_time())
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    let drafts = fs::read_dir(config.drafts_directory_path()).expect("could not read drafts");
+    assert_eq!(drafts.count(), 0);
+}
+
+#[test]
+#[cfg(unix)]
+fn abandoned_drafts_can_be_stored() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    let crashing_editor = CrashingEditor::new(editor);
+    let result = std::panic::catch_unwind(|| {
+        quicknotes::make_note(
+            &config,
+            &crashing_editor,
+            "my cool note".to_string(),
+            &test_time(),
+        )
+    });
+    assert!(result.is_err(), "expected panic, got {result:?}");
+
+    // This process is still running, so its drafts aren't abandoned until it's gone
+    assert!(quicknotes::abandoned_drafts(&config)
+        .expect("could not list drafts")
+        .is_empty());
+    let journal_path = fs::read_dir(config.drafts_directory_path())
+        .expect("could not read drafts")
+        .map(|entry| entry.expect("could not read draft").path())
+        .find(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .expect("draft should be in the journal");
+    abandon_draft(&journal_path, None);
+
+    let drafts = quicknotes::abandoned_drafts(&config).expect("could not list drafts");
+    assert_eq!(drafts.len(), 1);
+    assert_eq!(
+        drafts[0].destination,
+        roots.note_root.path().join("notes/my-cool-note.txt")
+    );
+    assert_eq!(drafts[0].kind, NoteKind::Note);
+
+    let stored_path = quicknotes::store_draft(&config, &drafts[0], None)
+        .expect("could not store draft")
+        .expect("draft has contents, so path should have been returned");
+
+    let note = quicknotes::read_note(&stored_path).expect("could not read note");
+    assert_eq!(note.preamble.title, "my cool note");
+    assert_eq!(note.body.trim(), "hello, world!");
+    let notes = quicknotes::indexed_notes(&config).expect("could not read indexed notes");
+    assert!(notes.contains_key(&stored_path));
+    assert_eq!(
+        fs::read_dir(config.drafts_directory_path())
+            .expect("could not read drafts")
+            .count(),
+        0
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn drafts_are_not_stored_outside_of_the_note_directories() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("hello, world!\n".to_string());
+    let crashing_editor = CrashingEditor::new(editor);
+    let result = std::panic::catch_unwind(|| {
+        quicknotes::make_note(
+            &config,
+            &crashing_editor,
+            "my cool note".to_string(),
+            &test_time(),
+        )
+    });
+    assert!(result.is_err(), "expected panic, got {result:?}");
+
+    // e.g. a journal that was edited, or synced from somewhere else
+    let destination = roots.temp_root.path().join("outside.txt");
+    let journal_path = fs::read_dir(config.drafts_directory_path())
+        .expect("could not read drafts")
+        .map(|entry| entry.expect("could not read draft").path())
+        .find(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .expect("draft should be in the journal");
+    abandon_draft(&journal_path, Some(&destination));
+
+    let drafts = quicknotes::abandoned_drafts(&config).expect("could not list drafts");
+    assert_eq!(drafts[0].destination, destination);
+
+    let result = quicknotes::store_draft(&config, &drafts[0], None);
+    assert!(result.is_err(), "expected error, got {result:?}");
+    let result = quicknotes::resume_draft(&config, AppendEditor::new(), &drafts[0], None);
+    assert!(result.is_err(), "expected error, got {result:?}");
+
+    assert!(!destination.exists());
+    assert!(drafts[0].path.exists());
+}
+
+#[test]
+fn encrypted_notes_are_only_stored_encrypted() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("- [ ] rotate the root password\n".to_string());
+    let path = quicknotes::make_encrypted_note(
+        &config,
+        &editor,
+        "hunter2",
+        "incident".to_string(),
+        &test_time(),
+    )
+    .expect("could not write note")
+    .expect("file has contents, so path should have been returned");
+
+    let stored_contents = fs::read_to_string(&path).expect("could not read note");
+    assert!(stored_contents.contains("encrypted = true"));
+    assert!(!stored_contents.contains("root password"));
+    assert!(quicknotes::indexed_tasks(&config)
+        .expect("could not read tasks")
+        .is_empty());
+    assert!(quicknotes::open_note(&config, &editor, NoteKind::Note, &path).is_err());
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("- [x] rotated\n".to_string());
+    quicknotes::open_encrypted_note(&config, &editor, NoteKind::Note, &path, "hunter2")
+        .expect("could not open note");
+
+    let stored_contents = fs::read_to_string(&path).expect("could not read note");
+    assert!(!stored_contents.contains("rotated"));
+
+    let contents = quicknotes::read_encrypted_note(&path, "hunter2").expect("could not read note");
+    assert_eq!(
+        contents.body,
+        "\n- [ ] rotate the root password\n- [x] rotated\n"
+    );
+    assert!(contents.preamble.updated_at.is_some());
+    assert!(quicknotes::read_encrypted_note(&path, "hunter3").is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn drafts_of_encrypted_notes_need_a_passphrase_to_be_stored() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("the vault combination is 12-34-56\n".to_string());
+    let crashing_editor = CrashingEditor::new(editor);
+
+    // New encrypted notes are never written to the drafts directory, nor left behind in plain text
+    let result = std::panic::catch_unwind(|| {
+        quicknotes::make_encrypted_note(
+            &config,
+            &crashing_editor,
+            "hunter2",
+            "vault".to_string(),
+            &test_time(),
+        )
+    });
+    assert!(result.is_err(), "expected panic, got {result:?}");
+    assert!(!config.drafts_directory_path().exists());
+    assert_eq!(
+        fs::read_dir(roots.temp_root.path())
+            .expect("could not read temporary directory")
+            .count(),
+        0
+    );
+
+    // A plain note can still be marked as encrypted while it is being written
+    let result = std::panic::catch_unwind(|| {
+        quicknotes::make_note(&config, &crashing_editor, "vault".to_string(), &test_time())
+    });
+    assert!(result.is_err(), "expected panic, got {result:?}");
+
+    let draft_paths = fs::read_dir(config.drafts_directory_path())
+        .expect("could not read drafts")
+        .map(|entry| entry.expect("could not read draft").path())
+        .collect::<Vec<_>>();
+    let (journal_paths, draft_paths): (Vec<_>, Vec<_>) =
+        draft_paths.into_iter().partition(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        });
+    let draft_contents = fs::read_to_string(&draft_paths[0]).expect("could not read draft");
+    fs::write(
+        &draft_paths[0],
+        draft_contents.replacen("---\n", "---\nencrypted = true\n", 1),
+    )
+    .expect("could not write draft");
+
+    abandon_draft(&journal_paths[0], None);
+
+    let drafts = quicknotes::abandoned_drafts(&config).expect("could not list drafts");
+    assert_eq!(drafts.len(), 1);
+    assert!(drafts[0].is_encrypted());
+
+    let result = quicknotes::store_draft(&config, &drafts[0], None);
+    assert!(result.is_err(), "expected error, got {result:?}");
+    assert!(drafts[0].path.exists());
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("and the alarm code is 7890\n".to_string());
+    let stored_path = quicknotes::resume_draft(&config, &editor, &drafts[0], Some("hunter2"))
+        .expect("could not resume draft")
+        .expect("draft has contents, so path should have been returned");
+
+    let stored_contents = fs::read_to_string(&stored_path).expect("could not read note");
+    assert!(!stored_contents.contains("12-34-56"));
+    assert!(!stored_contents.contains("7890"));
+    let contents =
+        quicknotes::read_encrypted_note(&stored_path, "hunter2").expect("could not read note");
+    assert_eq!(
+        contents.body.trim(),
+        "the vault combination is 12-34-56\nand the alarm code is 7890"
+    );
+    assert!(!drafts[0].path.exists());
+}
+
+#[test]
+fn attachments_are_linked_and_deleted_with_their_note() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let mut editor = AppendEditor::new();
+    editor.note_contents("the server fell over\n".to_string());
+    let note_path = quicknotes::make_note(&config, &editor, "incident".to_string(), &test_time())
+        .expect("could not write note")
+        .expect("file has contents, so path should have been returned");
+
+    let file = roots.temp_root.path().join("server log.txt");
+    fs::write(&file, "ERROR: out of memory\n").expect("could not write file");
+    let attachment_paths = (0..2)
+        .map(|_n| {
+            quicknotes::attach_file(&config, &note_path, NoteKind::Note, &file, &test_time())
+                .expect("could not attach file")
+        })
+        .collect::<Vec<_>>();
+
+    let attachments_directory = roots.note_root.path().join("attachments/notes/incident");
+    assert_eq!(
+        attachment_paths,
+        vec![
+            attachments_directory.join("server log.txt"),
+            attachments_directory.join("server log-1.txt"),
+        ]
+    );
+
+    let contents = fs::read_to_string(&note_path).expect("could not read note");
+    assert!(contents.ends_with(concat!(
+        "the server fell over\n\n",
+        "[server log.txt](../attachments/notes/incident/server%20log.txt)\n\n",
+        "[server log-1.txt](../attachments/notes/incident/server%20log-1.txt)\n",
+    )));
+
+    // Attachments must survive the index being rebuilt
+    quicknotes::index_notes(&config).expect("could not index notes");
+    assert_eq!(
+        quicknotes::note_attachments(&config, &note_path).expect("could not look up attachments"),
+        attachment_paths
+    );
+
+    quicknotes::delete_note(&config, &note_path).expect("could not delete note");
+
+    assert!(!note_path.exists());
+    assert!(!attachments_directory.exists());
+    assert!(file.exists());
+    assert!(quicknotes::indexed_notes(&config)
+        .expect("could not read index")
+        .is_empty());
+}
+
+#[test]
+fn attachments_of_notes_of_different_kinds_are_kept_apart() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let root = roots.note_root.path();
+    let preamble = "---\ntitle = \"2015-10-21\"\ncreated_at = 2015-10-21T07:28:00-07:00\n---\n";
+    let note_path = root.join("notes/2015-10-21.txt");
+    let daily_path = root.join("daily/2015-10-21.txt");
+    for path in [&note_path, &daily_path] {
+        fs::create_dir_all(path.parent().unwrap()).expect("could not create directory");
+        fs::write(path, preamble).expect("could not write note");
+    }
+
+    let file = roots.temp_root.path().join("photo.png");
+    fs::write(&file, "not really a photo").expect("could not write file");
+    let note_attachment =
+        quicknotes::attach_file(&config, &note_path, NoteKind::Note, &file, &test_time())
+            .expect("could not attach file");
+    let daily_attachment =
+        quicknotes::attach_file(&config, &daily_path, NoteKind::Daily, &file, &test_time())
+            .expect("could not attach file");
+
+    assert_eq!(
+        note_attachment,
+        root.join("attachments/notes/2015-10-21/photo.png")
+    );
+    assert_eq!(
+        daily_attachment,
+        root.join("attachments/daily/2015-10-21/photo.png")
+    );
+
+    quicknotes::delete_note(&config, &note_path).expect("could not delete note");
+    assert!(daily_attachment.exists());
+}
+
+#[test]
+fn attachments_are_removed_if_they_cannot_be_linked() {
+    let roots = testutil::setup_filesystem();
+    let config = NoteConfig {
+        file_extension: "txt".to_string(),
+        root_dir: roots.note_root.path().to_owned(),
+        temp_root_override: Some(roots.temp_root.path().to_owned()),
+        date_dialect: DateDialect::default(),
+        daily: DailyConfig::default(),
+        inbox: InboxConfig::default(),
+    };
+
+    let note_path = roots.note_root.path().join("notes/unfinished.txt");
+    let contents = "---\ntitle = \"unfinished\"\n";
+    fs::create_dir_all(note_path.parent().unwrap()).expect("could not create directory");
+    fs::write(&note_path, contents).expect("could not write note");
+
+    let file = roots.temp_root.path().join("server log.txt");
+    fs::write(&file, "ERROR: out of memory\n").expect("could not write file");
+    let result = quicknotes::attach_file(&config, &note_path, NoteKind::Note, &file, &test_time());
+
+    assert!(result.is_err(), "expected error, got {result:?}");
+    assert_eq!(
+        fs::read_to_string(&note_path).expect("could not read note"),
+        contents
+    );
+    assert_eq!(
+        fs::read_dir(roots.note_root.path().join("attachments/notes/unfinished"))
+            .expect("could not read attachments directory")
+            .count(),
+        0
+    );
+    assert!(quicknotes::note_attachments(&config, &note_path)
+        .expect("could not look up attachments")
+        .is_empty());
+}
@@ -1,10 +1,11 @@
 // each test file is its own crate, so just because something is used in one place doesn't make it dead
 #![allow(dead_code)]
 
+use std::cell::Cell;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 
-use quicknotes::Editor;
+use quicknotes::{EditOutcome, Editor, Position};
 use tempfile::{tempdir, NamedTempFile, TempDir};
 
 pub struct FilesystemRoots {
@@ -32,7 +33,7 @@ impl Editor for AppendEditor {
         "test_append_editor"
     }
 
-    fn edit(&self, path: &std::path::Path) -> std::io::Result<()> {
+    fn edit(&self, path: &std::path::Path) -> std::io::Result<EditOutcome> {
         if let Some(to_insert) = self.to_insert.as_ref() {
             let mut file = OpenOptions::new()
                 .append(true)
@@ -41,7 +42,7 @@ impl Editor for AppendEditor {
 
             write!(file, "{to_insert}")?;
         }
-        Ok(())
+        Ok(EditOutcome::Completed)
     }
 }
 
@@ -65,7 +66,7 @@ impl Editor for OverwriteEditor {
         "test_overwrite_editor"
     }
 
-    fn edit(&self, path: &std::path::Path) -> std::io::Result<()> {
+    fn edit(&self, path: &std::path::Path) -> std::io::Result<EditOutcome> {
         if let Some(to_insert) = self.to_insert.as_ref() {
             let mut file = OpenOptions::new()
                 .write(true)
@@ -76,7 +77,7 @@ impl Editor for OverwriteEditor {
             write!(file, "{to_insert}")?;
         }
 
-        Ok(())
+        Ok(EditOutcome::Completed)
     }
 }
 
@@ -95,16 +96,96 @@ impl<E: Editor> Editor for SwappingEditor<E> {
         "test_swapping_editor"
     }
 
-    fn edit(&self, path: &std::path::Path) -> std::io::Result<()> {
+    fn edit(&self, path: &std::path::Path) -> std::io::Result<EditOutcome> {
         // Emulate something like vim, which will move the file into place. This breaks
         // implementations that depend on the original tempfiles inode
         let swap_path = NamedTempFile::new()?.into_temp_path();
         fs::copy(path, &swap_path)?;
-        self.inner.edit(&swap_path)?;
+        let outcome = self.inner.edit(&swap_path)?;
         fs::rename(&swap_path, path)?;
         swap_path.keep()?;
 
-        Ok(())
+        Ok(outcome)
+    }
+}
+
+pub struct AbortingEditor<E> {
+    inner: E,
+}
+
+impl<E: Editor> AbortingEditor<E> {
+    pub fn new(editor: E) -> Self {
+        Self { inner: editor }
+    }
+}
+
+impl<E: Editor> Editor for AbortingEditor<E> {
+    fn name(&self) -> &str {
+        "test_aborting_editor"
+    }
+
+    fn edit(&self, path: &std::path::Path) -> std::io::Result<EditOutcome> {
+        // Emulate something like vim's :cq, where the file may have been written, but the
+        // editor exits with a failure
+        self.inner.edit(path)?;
+
+        Ok(EditOutcome::Aborted)
+    }
+}
+
+pub struct CrashingEditor<E> {
+    inner: E,
+}
+
+impl<E: Editor> CrashingEditor<E> {
+    pub fn new(editor: E) -> Self {
+        Self { inner: editor }
+    }
+}
+
+impl<E: Editor> Editor for CrashingEditor<E> {
+    fn name(&self) -> &str {
+        "test_crashing_editor"
+    }
+
+    fn edit(&self, path: &std::path::Path) -> std::io::Result<EditOutcome> {
+        // Emulate quicknotes going away while the editor is open, after the note was written
+        self.inner.edit(path)?;
+
+        panic!("the editor crashed");
+    }
+}
+
+#[derive(Default)]
+pub struct PositionRecordingEditor {
+    last_position: Cell<Option<Position>>,
+}
+
+impl PositionRecordingEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn last_position(&self) -> Option<Position> {
+        self.last_position.get()
+    }
+}
+
+impl Editor for PositionRecordingEditor {
+    fn name(&self) -> &str {
+        "test_position_recording_editor"
+    }
+
+    fn edit(&self, _path: &std::path::Path) -> std::io::Result<EditOutcome> {
+        self.last_position.set(None);
+
+        Ok(EditOutcome::Completed)
+    }
+
+    fn edit_at(&self, _path: &std::path::Path, position: Position) -> std::io::Result<EditOutcome> {
+        self.last_position.set(Some(position));
+
+        Ok(EditOutcome::Completed)
     }
 }
 
@@ -116,6 +197,10 @@ pub fn setup_filesystem() -> FilesystemRoots {
         .expect("could not make notes dir for testing");
     std::fs::create_dir(note_root.path().join("daily"))
         .expect("could not make daily dir for testing");
+    std::fs::create_dir(note_root.path().join("weekly"))
+        .expect("could not make weekly dir for testing");
+    std::fs::create_dir(note_root.path().join("monthly"))
+        .expect("could not make monthly dir for testing");
 
     FilesystemRoots {
         note_root,